@@ -0,0 +1,56 @@
+//! Rendering benchmarks. `draw_rows` writes to stdout like the hex view does,
+//! so run them with stdout redirected: `cargo bench --bench render > /dev/null`
+
+use std::io::{Write, stdout};
+
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use lesbin::{encoding::TextEncoding, format_row, util::{LineColor, LineWriter, TextWriter}};
+
+/// Rows that fit onto a big terminal window, which is what gets drawn for every frame
+const ROWS: usize = 200;
+
+fn bench_format_row(c: &mut Criterion) {
+    let data: Vec<u8> = (0..ROWS * 0x10).map(|i| (i * 7 + i / 3) as u8).collect();
+    let mut group = c.benchmark_group("format_row");
+    
+    for encoding in [TextEncoding::Ascii, TextEncoding::Latin1, TextEncoding::Utf16Le, TextEncoding::ShiftJis, TextEncoding::Ebcdic] {
+        group.bench_function(BenchmarkId::from_parameter(format!("{encoding:?}")), |b| {
+            let mut writer = TextWriter::new(true);
+            
+            b.iter(|| {
+                writer.buffer.clear();
+                
+                for (row, row_bytes) in data.chunks(0x10).enumerate() {
+                    format_row(&mut writer, row * 0x10, row_bytes, [None; 0x10], LineColor::Address, encoding, true);
+                    writer.end_line();
+                }
+                
+                writer.buffer.len()
+            });
+        });
+    }
+    
+    group.finish();
+}
+
+/// Draws a whole window of rows the way the hex view does, including queuing them for the terminal
+fn bench_draw_rows(c: &mut Criterion) {
+    let data: Vec<u8> = (0..ROWS * 0x10).map(|i| (i * 7 + i / 3) as u8).collect();
+    
+    c.bench_function("draw_rows", |b| {
+        let mut writer = LineWriter::new(0, 0);
+        
+        b.iter(|| {
+            for (row, row_bytes) in data.chunks(0x10).enumerate() {
+                writer.move_to(2, row as u16).unwrap();
+                format_row(&mut writer, row * 0x10, row_bytes, [None; 0x10], LineColor::Address, TextEncoding::Ascii, true);
+                writer.flush().unwrap();
+            }
+            
+            stdout().flush().unwrap();
+        });
+    });
+}
+
+criterion_group!(benches, bench_format_row, bench_draw_rows);
+criterion_main!(benches);
@@ -0,0 +1,295 @@
+use std::{ffi::{OsStr, OsString}, path::PathBuf};
+
+use anyhow::{Result, bail};
+
+use crate::dump::DumpOptions;
+
+pub const USAGE: &str = "\
+Usage: lesbin [options] <file>...
+       lesbin [options] -          (read from stdin)
+
+Options:
+  --goto <offset>      Start with the cursor at a hexadecimal offset
+  --readonly           Don't allow editing or saving
+  --new                Start with an empty file if the file doesn't exist yet
+  --compare <file>     Highlight bytes that differ from another file
+  --apply <patch>      Apply an IPS or text patch after opening the file
+  --config <path>      Use a different config file
+  --mmap               Always memory map the file instead of reading it into memory
+  --no-session         Don't restore or remember the position in the file
+  
+  --dump               Print the file as text instead of starting the TUI
+                       (happens automatically when the output isn't a terminal)
+  --offset <offset>    Hexadecimal offset to start dumping at
+  --length <length>    Hexadecimal amount of bytes to dump
+  --color              Keep the colors when dumping
+  
+  --help               Show this message
+  --version            Show the version number";
+
+/// What the program should do according to the command line
+pub enum Command {
+    Run(Options),
+    Help,
+    Version,
+}
+
+#[derive(Debug, Default)]
+pub struct Options {
+    pub input_files: Vec<PathBuf>,
+    pub compare_file: Option<PathBuf>,
+    pub patch_file: Option<PathBuf>,
+    pub config_file: Option<PathBuf>,
+    pub goto: Option<usize>,
+    pub readonly: bool,
+    pub create_new: bool,
+    pub force_mmap: bool,
+    pub no_session: bool,
+    
+    pub dump_mode: bool,
+    pub dump_options: DumpOptions,
+}
+
+/// Parses the command line arguments (without the program name).
+/// Options taking a value accept it both as the next argument and after a `=`.
+/// Arguments don't need to be valid UTF-8, since file names often aren't.
+/// `output_is_terminal` tells whether the file gets dumped even without `--dump`.
+pub fn parse(args: impl IntoIterator<Item = OsString>, output_is_terminal: bool) -> Result<Command> {
+    let mut options = Options::default();
+    let mut only_files = false;
+    // the first option given that only does something when dumping
+    let mut dump_option = None;
+    
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if only_files || arg == "-" || !arg.as_encoded_bytes().starts_with(b"-") {
+            options.input_files.push(arg.into());
+            continue;
+        }
+        
+        let (name, mut inline_value) = split_option(&arg);
+        let name = name.to_string_lossy();
+        
+        let mut value = || match inline_value.take().or_else(|| args.next()) {
+            Some(value) => Ok(value),
+            None => bail!("Missing value for {name}"),
+        };
+        
+        match &*name {
+            "--help" | "-h" => return Ok(Command::Help),
+            "--version" | "-V" => return Ok(Command::Version),
+            "--" => only_files = true,
+            "--goto" => options.goto = Some(parse_offset(&name, &value()?)?),
+            "--readonly" => options.readonly = true,
+            "--new" => options.create_new = true,
+            "--compare" => options.compare_file = Some(value()?.into()),
+            "--apply" => options.patch_file = Some(value()?.into()),
+            "--config" => options.config_file = Some(value()?.into()),
+            "--mmap" => options.force_mmap = true,
+            "--no-session" => options.no_session = true,
+            "--dump" => options.dump_mode = true,
+            "--offset" => options.dump_options.offset = parse_offset(&name, &value()?)?,
+            "--length" => options.dump_options.length = Some(parse_offset(&name, &value()?)?),
+            "--color" => options.dump_options.colored = true,
+            _ => bail!("Unknown option '{}', see --help for all options", arg.to_string_lossy()),
+        }
+        
+        // flags leave the value after the `=` alone
+        if inline_value.is_some() {
+            bail!("{name} doesn't take a value");
+        }
+        
+        if matches!(&*name, "--offset" | "--length" | "--color") {
+            dump_option.get_or_insert(name.into_owned());
+        }
+    }
+    
+    validate(&options)?;
+    
+    if let Some(name) = dump_option && !options.dump_mode && output_is_terminal {
+        bail!("{name} can only be used together with --dump");
+    }
+    
+    Ok(Command::Run(options))
+}
+
+fn validate(options: &Options) -> Result<()> {
+    if options.dump_mode {
+        let tui_only = [
+            ("--goto", options.goto.is_some()),
+            ("--compare", options.compare_file.is_some()),
+            ("--apply", options.patch_file.is_some()),
+        ];
+        
+        if let Some((name, _)) = tui_only.into_iter().find(|(_, used)| *used) {
+            bail!("{name} cannot be used together with --dump");
+        }
+    }
+    
+    if options.readonly {
+        if options.patch_file.is_some() {
+            bail!("--apply cannot be used together with --readonly");
+        }
+        if options.create_new {
+            bail!("--new cannot be used together with --readonly");
+        }
+    }
+    
+    if options.input_files.iter().filter(|input_file| input_file.as_os_str() == "-").count() > 1 {
+        bail!("Standard input can only be read once");
+    }
+    
+    Ok(())
+}
+
+/// Splits `--name=value` into its name and value
+fn split_option(arg: &OsStr) -> (&OsStr, Option<OsString>) {
+    let bytes = arg.as_encoded_bytes();
+    
+    match bytes.iter().position(|&byte| byte == b'=') {
+        Some(index) => {
+            // SAFETY: both halves are split right next to an ASCII character, which keeps them valid
+            let (name, value) = unsafe {
+                (OsStr::from_encoded_bytes_unchecked(&bytes[..index]), OsStr::from_encoded_bytes_unchecked(&bytes[index + 1..]))
+            };
+            
+            (name, Some(value.to_owned()))
+        },
+        None => (arg, None),
+    }
+}
+
+/// Parses a hexadecimal number, with or without 0x prefix
+fn parse_offset(name: &str, value: &OsStr) -> Result<usize> {
+    let value = value.to_string_lossy();
+    let digits = value.strip_prefix("0x").unwrap_or(&value);
+    
+    match usize::from_str_radix(digits, 16) {
+        Ok(number) => Ok(number),
+        Err(_) => bail!("Invalid value '{value}' for {name}, expected a hexadecimal number"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn parse_args(args: &[&str]) -> Result<Command> {
+        parse(args.iter().map(OsString::from), true)
+    }
+    
+    fn options(args: &[&str]) -> Options {
+        match parse_args(args).unwrap() {
+            Command::Run(options) => options,
+            _ => panic!("{args:?} should run"),
+        }
+    }
+    
+    fn error(args: &[&str]) -> String {
+        match parse_args(args) {
+            Ok(_) => panic!("{args:?} should be rejected"),
+            Err(err) => err.to_string(),
+        }
+    }
+    
+    #[test]
+    fn values_after_equals_sign_or_as_next_argument() {
+        let separate = options(&["--goto", "1f0", "--config", "a.toml", "file"]);
+        let inline = options(&["--goto=1f0", "--config=a.toml", "file"]);
+        
+        for options in [separate, inline] {
+            assert_eq!(options.goto, Some(0x1f0));
+            assert_eq!(options.config_file, Some(PathBuf::from("a.toml")));
+            assert_eq!(options.input_files, [PathBuf::from("file")]);
+        }
+        
+        assert_eq!(options(&["--dump", "--length", "0x20"]).dump_options.length, Some(0x20));
+        assert_eq!(options(&["--dump", "--length=0x20"]).dump_options.length, Some(0x20));
+        
+        // only the first equals sign separates the value
+        assert_eq!(options(&["--compare=a=b"]).compare_file, Some(PathBuf::from("a=b")));
+        assert_eq!(options(&["--compare="]).compare_file, Some(PathBuf::new()));
+    }
+    
+    #[test]
+    fn missing_or_invalid_values() {
+        assert_eq!(error(&["file", "--goto"]), "Missing value for --goto");
+        assert_eq!(error(&["--compare"]), "Missing value for --compare");
+        assert_eq!(error(&["--goto", "xyz"]), "Invalid value 'xyz' for --goto, expected a hexadecimal number");
+        assert_eq!(error(&["--offset="]), "Invalid value '' for --offset, expected a hexadecimal number");
+        assert_eq!(error(&["--bogus"]), "Unknown option '--bogus', see --help for all options");
+        
+        // options without a value don't take one from the next argument
+        let options = options(&["--readonly", "file"]);
+        assert!(options.readonly);
+        assert_eq!(options.input_files, [PathBuf::from("file")]);
+    }
+    
+    #[test]
+    fn flags_dont_take_a_value() {
+        assert_eq!(error(&["--readonly=x", "file"]), "--readonly doesn't take a value");
+        assert_eq!(error(&["--dump=", "file"]), "--dump doesn't take a value");
+        assert_eq!(error(&["file", "--mmap=yes"]), "--mmap doesn't take a value");
+    }
+    
+    #[test]
+    fn dump_options_need_dump_mode() {
+        assert_eq!(error(&["--offset", "10", "file"]), "--offset can only be used together with --dump");
+        assert_eq!(error(&["file", "--length=20", "--offset=0"]), "--length can only be used together with --dump");
+        assert_eq!(error(&["--color", "file"]), "--color can only be used together with --dump");
+        
+        // the file gets dumped anyway when the output isn't a terminal
+        let Ok(Command::Run(options)) = parse(["--offset", "10", "file"].map(OsString::from), false) else {
+            panic!("--offset should be allowed when the output isn't a terminal");
+        };
+        assert_eq!(options.dump_options.offset, 0x10);
+    }
+    
+    #[test]
+    fn everything_after_double_dash_is_a_file() {
+        let options = options(&["--mmap", "--", "--readonly", "-", "--help"]);
+        
+        assert!(options.force_mmap);
+        assert!(!options.readonly);
+        assert_eq!(options.input_files, [PathBuf::from("--readonly"), PathBuf::from("-"), PathBuf::from("--help")]);
+    }
+    
+    #[test]
+    fn help_and_version_skip_validation() {
+        // but invalid arguments before them are still reported
+        assert!(parse_args(&["--goto", "xyz", "--help"]).is_err());
+        assert!(parse_args(&["--bogus=1", "-h"]).is_err());
+        
+        assert!(matches!(parse_args(&["--dump", "--goto", "0", "-h"]), Ok(Command::Help)));
+        assert!(matches!(parse_args(&["-", "-", "--version"]), Ok(Command::Version)));
+    }
+    
+    #[test]
+    fn stdin_can_only_be_read_once() {
+        assert_eq!(options(&["-", "file"]).input_files, [PathBuf::from("-"), PathBuf::from("file")]);
+        assert_eq!(error(&["-", "file", "-"]), "Standard input can only be read once");
+        assert_eq!(error(&["--", "-", "-"]), "Standard input can only be read once");
+    }
+    
+    #[test]
+    fn tui_only_options_are_rejected_with_dump() {
+        assert_eq!(error(&["--dump", "--goto", "10", "file"]), "--goto cannot be used together with --dump");
+        assert_eq!(error(&["--compare=other", "--dump", "file"]), "--compare cannot be used together with --dump");
+        assert_eq!(error(&["--dump", "--apply", "patch.ips", "file"]), "--apply cannot be used together with --dump");
+        
+        let options = options(&["--dump", "--offset", "10", "--length", "20", "--color", "--readonly", "file"]);
+        assert!(options.dump_mode);
+        assert_eq!((options.dump_options.offset, options.dump_options.length), (0x10, Some(0x20)));
+        assert!(options.dump_options.colored);
+    }
+    
+    #[test]
+    fn editing_options_are_rejected_with_readonly() {
+        assert_eq!(error(&["--readonly", "--apply", "patch.ips", "file"]), "--apply cannot be used together with --readonly");
+        assert_eq!(error(&["--new", "--readonly", "file"]), "--new cannot be used together with --readonly");
+        
+        let options = options(&["--readonly", "--compare", "other", "--goto", "10", "file"]);
+        assert!(options.readonly);
+        assert_eq!(options.compare_file, Some(PathBuf::from("other")));
+    }
+}
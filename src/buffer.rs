@@ -0,0 +1,621 @@
+use std::{
+    collections::BTreeMap,
+    fs::{self, File, OpenOptions},
+    io::{self, Read, Seek, SeekFrom, Write},
+    ops::Range,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::{Result, bail};
+use memchr::memmem;
+use memmap2::Mmap;
+
+/// Files larger than this are memory mapped (or read on demand if that fails)
+/// instead of being loaded into memory entirely
+const LARGE_FILE_THRESHOLD: u64 = 64 * 1024 * 1024;
+
+/// Amount of data around the viewed position that a windowed buffer keeps in memory
+const WINDOW_SIZE: usize = 4 * 1024 * 1024;
+
+/// Amount of data searched at once
+pub const SEARCH_CHUNK_SIZE: usize = 1024 * 1024;
+
+/// The contents of the file being edited
+pub trait FileBuffer: Send {
+    fn len(&self) -> usize;
+    
+    /// Returns the bytes in `range` (including edits), reading them from disk if necessary
+    fn read(&mut self, range: Range<usize>) -> Result<&[u8]>;
+    
+    fn write_byte(&mut self, offset: usize, value: u8) -> Result<()>;
+    
+    /// Writes the contents including all edits to `path`
+    fn save(&mut self, path: &Path) -> Result<()>;
+    
+    /// Picks up data that has been appended to the file at `path`, which is now `new_len` bytes long
+    fn grow(&mut self, path: &Path, new_len: usize) -> Result<()>;
+    
+    /// Appends zeroes until the buffer is `new_len` bytes long
+    fn extend(&mut self, new_len: usize) -> Result<()>;
+    
+    /// Creates an independent reader over the current contents, e.g. for searching on another thread.
+    /// Edits made afterwards don't affect the snapshot.
+    fn snapshot(&self) -> Result<Box<dyn FileBuffer>>;
+    
+    fn read_byte(&mut self, offset: usize) -> Result<u8> {
+        Ok(self.read(offset..offset + 1)?[0])
+    }
+}
+
+/// Opens a file, loading it into memory if it's small and memory mapping it otherwise.
+/// If memory mapping fails, the file is read on demand instead and a warning is returned.
+pub fn open(path: &Path, force_mmap: bool) -> io::Result<(Box<dyn FileBuffer>, Option<String>)> {
+    let file = File::open(path)?;
+    
+    if !force_mmap && file.metadata()?.len() <= LARGE_FILE_THRESHOLD {
+        let mut bytes = Vec::new();
+        (&file).read_to_end(&mut bytes)?;
+        return Ok((Box::new(InMemoryBuffer(Arc::new(bytes))), None));
+    }
+    
+    match MmapBuffer::new(path, &file) {
+        Ok(buffer) => Ok((Box::new(buffer), None)),
+        Err(err) => {
+            let warning = format!("Warning: Could not memory map file ({err}), reading it on demand instead");
+            Ok((Box::new(WindowedBuffer::new(path, file)?), Some(warning)))
+        },
+    }
+}
+
+/// Finds the first occurrence of `needle` at or after `start`.
+/// The buffer is searched in chunks that overlap by the length of the needle,
+/// so matches crossing chunk boundaries are found as well.
+/// 
+/// `on_progress` gets called with the current position before each chunk
+/// and can return false to abort the search.
+pub fn find(
+    buffer: &mut dyn FileBuffer,
+    needle: &[u8],
+    start: usize,
+    mut on_progress: impl FnMut(usize) -> bool,
+) -> Result<Option<usize>> {
+    if needle.is_empty() {
+        return Ok(None);
+    }
+    
+    let finder = memmem::Finder::new(needle);
+    let len = buffer.len();
+    let mut chunk_start = start;
+    
+    while chunk_start + needle.len() <= len {
+        if !on_progress(chunk_start) {
+            return Ok(None);
+        }
+        
+        let chunk_end = usize::min(chunk_start + SEARCH_CHUNK_SIZE + needle.len() - 1, len);
+        let chunk = buffer.read(chunk_start..chunk_end)?;
+        
+        if let Some(index) = finder.find(chunk) {
+            return Ok(Some(chunk_start + index));
+        }
+        
+        chunk_start += SEARCH_CHUNK_SIZE;
+    }
+    
+    Ok(None)
+}
+
+/// Finds every (non-overlapping) occurrence of `needle`, in the same chunks as [`find`].
+/// `on_match` gets called with the offset of each match and `on_progress` with the position before each chunk,
+/// and either of them can return false to stop.
+pub fn find_all(
+    buffer: &mut dyn FileBuffer,
+    needle: &[u8],
+    mut on_progress: impl FnMut(usize) -> bool,
+    mut on_match: impl FnMut(usize) -> bool,
+) -> Result<()> {
+    if needle.is_empty() {
+        return Ok(());
+    }
+    
+    let finder = memmem::Finder::new(needle);
+    let len = buffer.len();
+    let mut chunk_start = 0;
+    // end of the last match, the next one can't start before it even if it's in the next chunk
+    let mut search_start = 0;
+    
+    while chunk_start + needle.len() <= len {
+        if !on_progress(chunk_start) {
+            return Ok(());
+        }
+        
+        let chunk_end = usize::min(chunk_start + SEARCH_CHUNK_SIZE + needle.len() - 1, len);
+        let read_start = usize::max(chunk_start, search_start);
+        let chunk = buffer.read(read_start..chunk_end)?;
+        
+        // matches starting in the overlap get found in the next chunk
+        let offsets = finder.find_iter(chunk).map(|index| read_start + index);
+        for offset in offsets.take_while(|&offset| offset < chunk_start + SEARCH_CHUNK_SIZE) {
+            if !on_match(offset) {
+                return Ok(());
+            }
+            
+            search_start = offset + needle.len();
+        }
+        
+        chunk_start += SEARCH_CHUNK_SIZE;
+    }
+    
+    Ok(())
+}
+
+/// Finds the first offset at or after `start` whose byte isn't `value`, e.g. the end of a run of padding
+pub fn find_other_than(buffer: &mut dyn FileBuffer, value: u8, start: usize) -> Result<Option<usize>> {
+    let len = buffer.len();
+    
+    for chunk_start in (start..len).step_by(SEARCH_CHUNK_SIZE) {
+        let chunk = buffer.read(chunk_start..usize::min(chunk_start + SEARCH_CHUNK_SIZE, len))?;
+        
+        if let Some(index) = chunk.iter().position(|&byte| byte != value) {
+            return Ok(Some(chunk_start + index));
+        }
+    }
+    
+    Ok(None)
+}
+
+/// Finds the last offset before `end` whose byte isn't `value`
+pub fn rfind_other_than(buffer: &mut dyn FileBuffer, value: u8, end: usize) -> Result<Option<usize>> {
+    let mut chunk_end = usize::min(end, buffer.len());
+    
+    while chunk_end > 0 {
+        let chunk_start = chunk_end.saturating_sub(SEARCH_CHUNK_SIZE);
+        let chunk = buffer.read(chunk_start..chunk_end)?;
+        
+        if let Some(index) = chunk.iter().rposition(|&byte| byte != value) {
+            return Ok(Some(chunk_start + index));
+        }
+        
+        chunk_end = chunk_start;
+    }
+    
+    Ok(None)
+}
+
+/// Fails if `range` doesn't lie within a buffer of `len` bytes, so every kind of buffer rejects the same reads
+fn check_range(range: &Range<usize>, len: usize) -> Result<()> {
+    if range.start > range.end || range.end > len {
+        bail!("Cannot read 0x{:x}..0x{:x}, the file is only 0x{len:x} bytes long", range.start, range.end);
+    }
+    
+    Ok(())
+}
+
+fn is_same_file(a: &Path, b: &Path) -> bool {
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+/// Buffer holding the entire file in memory
+pub struct InMemoryBuffer(pub Arc<Vec<u8>>);
+
+impl FileBuffer for InMemoryBuffer {
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+    
+    fn read(&mut self, range: Range<usize>) -> Result<&[u8]> {
+        check_range(&range, self.0.len())?;
+        Ok(&self.0[range])
+    }
+    
+    fn write_byte(&mut self, offset: usize, value: u8) -> Result<()> {
+        // only copies the data if a snapshot of it is still around
+        Arc::make_mut(&mut self.0)[offset] = value;
+        Ok(())
+    }
+    
+    fn save(&mut self, path: &Path) -> Result<()> {
+        fs::write(path, self.0.as_slice())?;
+        Ok(())
+    }
+    
+    fn grow(&mut self, path: &Path, new_len: usize) -> Result<()> {
+        let mut file = File::open(path)?;
+        file.seek(SeekFrom::Start(self.0.len() as u64))?;
+        
+        let bytes = Arc::make_mut(&mut self.0);
+        let prev_len = bytes.len();
+        bytes.resize(new_len, 0);
+        
+        if let Err(err) = file.read_exact(&mut bytes[prev_len..]) {
+            bytes.truncate(prev_len);
+            return Err(err.into());
+        }
+        
+        Ok(())
+    }
+    
+    fn extend(&mut self, new_len: usize) -> Result<()> {
+        Arc::make_mut(&mut self.0).resize(new_len, 0);
+        Ok(())
+    }
+    
+    fn snapshot(&self) -> Result<Box<dyn FileBuffer>> {
+        Ok(Box::new(InMemoryBuffer(self.0.clone())))
+    }
+}
+
+/// Buffer that only keeps a window of the file in memory and reads the rest on demand.
+/// Edits are stored separately until they get saved.
+pub struct WindowedBuffer {
+    path: PathBuf,
+    file: File,
+    len: usize,
+    
+    window_start: usize,
+    window: Vec<u8>,
+    
+    edits: BTreeMap<usize, u8>,
+}
+
+impl WindowedBuffer {
+    fn new(path: &Path, file: File) -> io::Result<Self> {
+        let len = file.metadata()?.len() as usize;
+        
+        Ok(Self {
+            path: PathBuf::from(path),
+            file,
+            len,
+            window_start: 0,
+            window: Vec::new(),
+            edits: BTreeMap::new(),
+        })
+    }
+    
+    fn load_window(&mut self, range: Range<usize>) -> Result<()> {
+        // center the window around the requested range so scrolling in either direction stays cheap
+        let size = usize::max(WINDOW_SIZE, range.len());
+        let start = range.start.saturating_sub((size - range.len()) / 2);
+        let end = usize::min(start + size, self.len);
+        
+        self.window.resize(end - start, 0);
+        self.file.seek(SeekFrom::Start(start as u64))?;
+        self.file.read_exact(&mut self.window)?;
+        self.window_start = start;
+        
+        for (&offset, &value) in self.edits.range(start..end) {
+            self.window[offset - start] = value;
+        }
+        
+        Ok(())
+    }
+}
+
+impl FileBuffer for WindowedBuffer {
+    fn len(&self) -> usize {
+        self.len
+    }
+    
+    fn read(&mut self, range: Range<usize>) -> Result<&[u8]> {
+        check_range(&range, self.len)?;
+        let window_end = self.window_start + self.window.len();
+        
+        if range.start < self.window_start || range.end > window_end {
+            self.load_window(range.clone())?;
+        }
+        
+        Ok(&self.window[range.start - self.window_start..range.end - self.window_start])
+    }
+    
+    fn write_byte(&mut self, offset: usize, value: u8) -> Result<()> {
+        self.edits.insert(offset, value);
+        
+        if let Some(byte) = offset.checked_sub(self.window_start).and_then(|i| self.window.get_mut(i)) {
+            *byte = value;
+        }
+        
+        Ok(())
+    }
+    
+    fn save(&mut self, path: &Path) -> Result<()> {
+        if !is_same_file(&self.path, path) {
+            // copy the unmodified file first, then continue working on the copy
+            fs::copy(&self.path, path)?;
+            self.path = PathBuf::from(path);
+            self.file = File::open(path)?;
+        }
+        
+        let mut file = OpenOptions::new().write(true).open(&self.path)?;
+        
+        for (&offset, &value) in &self.edits {
+            file.seek(SeekFrom::Start(offset as u64))?;
+            file.write_all(&[value])?;
+        }
+        
+        file.flush()?;
+        self.edits.clear();
+        Ok(())
+    }
+    
+    fn grow(&mut self, _path: &Path, new_len: usize) -> Result<()> {
+        // new data gets read on demand like everything else
+        self.len = new_len;
+        Ok(())
+    }
+    
+    fn extend(&mut self, _new_len: usize) -> Result<()> {
+        bail!("Files this large cannot be extended");
+    }
+    
+    fn snapshot(&self) -> Result<Box<dyn FileBuffer>> {
+        Ok(Box::new(WindowedBuffer {
+            path: self.path.clone(),
+            file: File::open(&self.path)?,
+            len: self.len,
+            window_start: 0,
+            window: Vec::new(),
+            edits: self.edits.clone(),
+        }))
+    }
+}
+
+/// Buffer reading directly from a read-only memory mapping of the file.
+/// Edits are kept in an overlay that is applied when reading and saving.
+pub struct MmapBuffer {
+    path: PathBuf,
+    map: Arc<Mmap>,
+    
+    overlay: BTreeMap<usize, u8>,
+    /// Copy of the last range read that contained edits
+    scratch: Vec<u8>,
+}
+
+impl MmapBuffer {
+    fn new(path: &Path, file: &File) -> io::Result<Self> {
+        // SAFETY: the mapping is only ever read from. Other programs modifying the file while it's open
+        // can still cause inconsistent reads, and if they truncate it, reading the part that has been cut off
+        // raises SIGBUS and crashes lesbin. Checking the size first can't rule that out either, since the file
+        // can shrink right after, so this is accepted for the large files that get mapped.
+        let map = Arc::new(unsafe { Mmap::map(file)? });
+        
+        Ok(Self {
+            path: PathBuf::from(path),
+            map,
+            overlay: BTreeMap::new(),
+            scratch: Vec::new(),
+        })
+    }
+    
+    fn remap(&mut self) -> io::Result<()> {
+        let file = File::open(&self.path)?;
+        // SAFETY: see `MmapBuffer::new`
+        self.map = Arc::new(unsafe { Mmap::map(&file)? });
+        Ok(())
+    }
+}
+
+impl FileBuffer for MmapBuffer {
+    fn len(&self) -> usize {
+        self.map.len()
+    }
+    
+    fn read(&mut self, range: Range<usize>) -> Result<&[u8]> {
+        check_range(&range, self.map.len())?;
+        if self.overlay.range(range.clone()).next().is_none() {
+            return Ok(&self.map[range]);
+        }
+        
+        self.scratch.clear();
+        self.scratch.extend_from_slice(&self.map[range.clone()]);
+        
+        for (&offset, &value) in self.overlay.range(range.clone()) {
+            self.scratch[offset - range.start] = value;
+        }
+        
+        Ok(&self.scratch)
+    }
+    
+    fn write_byte(&mut self, offset: usize, value: u8) -> Result<()> {
+        if self.map[offset] == value {
+            self.overlay.remove(&offset);
+        } else {
+            self.overlay.insert(offset, value);
+        }
+        
+        Ok(())
+    }
+    
+    fn save(&mut self, path: &Path) -> Result<()> {
+        let same_file = is_same_file(&self.path, path);
+        
+        if !same_file {
+            // the mapping is read-only, so write a full copy and apply the edits to that
+            fs::write(path, &self.map[..])?;
+            self.path = PathBuf::from(path);
+        }
+        
+        let mut file = OpenOptions::new().write(true).open(&self.path)?;
+        
+        for (&offset, &value) in &self.overlay {
+            file.seek(SeekFrom::Start(offset as u64))?;
+            file.write_all(&[value])?;
+        }
+        
+        file.flush()?;
+        self.overlay.clear();
+        
+        if !same_file {
+            self.remap()?;
+        }
+        
+        Ok(())
+    }
+    
+    fn grow(&mut self, _path: &Path, _new_len: usize) -> Result<()> {
+        self.remap()?;
+        Ok(())
+    }
+    
+    fn extend(&mut self, _new_len: usize) -> Result<()> {
+        bail!("Memory mapped files cannot be extended");
+    }
+    
+    fn snapshot(&self) -> Result<Box<dyn FileBuffer>> {
+        Ok(Box::new(MmapBuffer {
+            path: self.path.clone(),
+            map: self.map.clone(),
+            overlay: self.overlay.clone(),
+            scratch: Vec::new(),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, process};
+    
+    use super::*;
+    
+    /// Runs `check` on every kind of buffer over the same `bytes`, so they can be compared
+    fn for_each_buffer(name: &str, bytes: &[u8], mut check: impl FnMut(&str, &mut dyn FileBuffer)) {
+        let path = env::temp_dir().join(format!("lesbin-buffer-{name}-test-{}", process::id()));
+        fs::write(&path, bytes).unwrap();
+        
+        check("in memory", &mut InMemoryBuffer(Arc::new(bytes.to_vec())));
+        check("mmap", &mut MmapBuffer::new(&path, &File::open(&path).unwrap()).unwrap());
+        check("windowed", &mut WindowedBuffer::new(&path, File::open(&path).unwrap()).unwrap());
+        
+        fs::remove_file(&path).unwrap();
+    }
+    
+    #[test]
+    fn buffers_read_the_same_bytes() {
+        let bytes: Vec<u8> = (0..WINDOW_SIZE + 1000).map(|i| (i * 7 % 251) as u8).collect();
+        let len = bytes.len();
+        
+        for_each_buffer("read", &bytes, |kind, buffer| {
+            assert_eq!(buffer.len(), len, "{kind}");
+            
+            for range in [0..16, len - 16..len, WINDOW_SIZE - 8..WINDOW_SIZE + 8, 0..len, 5..5] {
+                assert_eq!(buffer.read(range.clone()).unwrap(), &bytes[range.clone()], "{kind} {range:?}");
+            }
+            
+            buffer.write_byte(WINDOW_SIZE, 0xaa).unwrap();
+            assert_eq!(buffer.read_byte(WINDOW_SIZE).unwrap(), 0xaa, "{kind}");
+            let around = [bytes[WINDOW_SIZE - 1], 0xaa, bytes[WINDOW_SIZE + 1]];
+            assert_eq!(buffer.read(WINDOW_SIZE - 1..WINDOW_SIZE + 2).unwrap(), around, "{kind}");
+            
+            // going back to the original value keeps reading the same
+            buffer.write_byte(WINDOW_SIZE, bytes[WINDOW_SIZE]).unwrap();
+            assert_eq!(buffer.read(0..len).unwrap(), bytes, "{kind}");
+        });
+    }
+    
+    #[test]
+    fn buffers_reject_the_same_reads_out_of_bounds() {
+        for_each_buffer("out-of-bounds", &[1, 2, 3, 4], |kind, buffer| {
+            let err = buffer.read(2..5).unwrap_err();
+            assert_eq!(err.to_string(), "Cannot read 0x2..0x5, the file is only 0x4 bytes long", "{kind}");
+            
+            #[expect(clippy::reversed_empty_ranges)]
+            let backwards = 3..2;
+            assert!(buffer.read(backwards).is_err(), "{kind}");
+            assert!(buffer.read(5..6).is_err(), "{kind}");
+            assert!(buffer.read_byte(4).is_err(), "{kind}");
+            
+            // the buffer can still be read afterwards
+            assert_eq!(buffer.read(4..4).unwrap(), [], "{kind}");
+            assert_eq!(buffer.read(0..4).unwrap(), [1, 2, 3, 4], "{kind}");
+        });
+    }
+    
+    #[test]
+    fn buffers_find_the_same_matches_across_chunks() {
+        const CHUNK: usize = SEARCH_CHUNK_SIZE;
+        
+        let mut bytes = vec![0; 2 * CHUNK + 1000];
+        let len = bytes.len();
+        // at the start, crossing the first chunk boundary, ending right at and starting right at the second one, and at the end
+        let offsets = [5, CHUNK - 3, 2 * CHUNK - 6, 2 * CHUNK, len - 6];
+        for offset in offsets {
+            bytes[offset..offset + 6].copy_from_slice(b"needle");
+        }
+        
+        for_each_buffer("find", &bytes, |kind, buffer| {
+            let mut matches = Vec::new();
+            find_all(buffer, b"needle", |_| true, |offset| {
+                matches.push(offset);
+                true
+            }).unwrap();
+            assert_eq!(matches, offsets, "{kind}");
+            
+            let mut found = Vec::new();
+            let mut start = 0;
+            while let Some(offset) = find(buffer, b"needle", start, |_| true).unwrap() {
+                found.push(offset);
+                start = offset + 1;
+            }
+            assert_eq!(found, offsets, "{kind}");
+            
+            assert_eq!(find(buffer, b"needles", 0, |_| true).unwrap(), None, "{kind}");
+            assert_eq!(find(buffer, b"", 0, |_| true).unwrap(), None, "{kind}");
+            
+            // edits are searched as well
+            buffer.write_byte(CHUNK - 3, b'N').unwrap();
+            assert_eq!(find(buffer, b"Needle", 0, |_| true).unwrap(), Some(CHUNK - 3), "{kind}");
+            assert_eq!(find(buffer, b"needle", 6, |_| true).unwrap(), Some(2 * CHUNK - 6), "{kind}");
+        });
+    }
+    
+    #[test]
+    fn matches_dont_overlap_across_chunks() {
+        const CHUNK: usize = SEARCH_CHUNK_SIZE;
+        
+        let mut bytes = vec![0; 2 * CHUNK];
+        bytes[CHUNK - 1..CHUNK + 3].fill(b'a');
+        bytes[10..13].fill(b'a');
+        
+        let mut matches = Vec::new();
+        find_all(&mut InMemoryBuffer(Arc::new(bytes)), b"aa", |_| true, |offset| {
+            matches.push(offset);
+            true
+        }).unwrap();
+        
+        // the same as within a chunk, instead of starting over at the chunk boundary
+        assert_eq!(matches, [10, CHUNK - 1, CHUNK + 1]);
+    }
+    
+    #[test]
+    fn buffers_skip_the_same_runs() {
+        const CHUNK: usize = SEARCH_CHUNK_SIZE;
+        
+        let mut bytes = vec![0xff; 2 * CHUNK + 1000];
+        let len = bytes.len();
+        bytes[7] = 0;
+        bytes[CHUNK + 100] = 0;
+        
+        for_each_buffer("runs", &bytes, |kind, buffer| {
+            assert_eq!(find_other_than(buffer, 0xff, 0).unwrap(), Some(7), "{kind}");
+            assert_eq!(find_other_than(buffer, 0xff, 8).unwrap(), Some(CHUNK + 100), "{kind}");
+            assert_eq!(find_other_than(buffer, 0xff, CHUNK + 101).unwrap(), None, "{kind}");
+            assert_eq!(find_other_than(buffer, 0xff, len).unwrap(), None, "{kind}");
+            
+            assert_eq!(rfind_other_than(buffer, 0xff, len).unwrap(), Some(CHUNK + 100), "{kind}");
+            assert_eq!(rfind_other_than(buffer, 0xff, CHUNK + 100).unwrap(), Some(7), "{kind}");
+            assert_eq!(rfind_other_than(buffer, 0xff, 7).unwrap(), None, "{kind}");
+            assert_eq!(rfind_other_than(buffer, 0xff, usize::MAX).unwrap(), Some(CHUNK + 100), "{kind}");
+            
+            assert_eq!(find_other_than(buffer, 0, 0).unwrap(), Some(0), "{kind}");
+            assert_eq!(rfind_other_than(buffer, 0, len).unwrap(), Some(len - 1), "{kind}");
+            
+            // edits are taken into account
+            buffer.write_byte(CHUNK + 100, 0xff).unwrap();
+            assert_eq!(find_other_than(buffer, 0xff, 8).unwrap(), None, "{kind}");
+            assert_eq!(rfind_other_than(buffer, 0xff, len).unwrap(), Some(7), "{kind}");
+        });
+    }
+}
@@ -1,93 +1,730 @@
-use std::fmt::{self, Display, Write};
+use std::{
+    fmt::{self, Display, Write},
+    fs,
+    io::ErrorKind,
+    path::Path,
+};
 
+use anyhow::{Result, anyhow, bail};
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 use serde::{Deserialize, de};
 
+use crate::{encoding::TextEncoding, theme::{ColorMode, Style, Theme}};
+
+const DEFAULT_CONFIG: &str = include_str!("res/default_config.toml");
+
 #[derive(Debug, Deserialize)]
 pub struct Config {
     pub appearance: Appearance,
     pub keybinds: Keybinds,
+    pub statusbar: StatusBar,
+    pub strings: StringsConfig,
+    pub navigation: NavigationConfig,
+    pub session: SessionConfig,
+    #[serde(default)]
+    pub theme: ThemeConfig,
+}
+
+impl Config {
+    /// Loads the config from `path`, or from the platform's config directory if no path is given.
+    /// The config gets created with the default settings on the first run.
+    /// Settings missing from the config keep their default values.
+    /// 
+    /// Never fails, if the config can't be read or is invalid the default settings get used.
+    /// Any problems (including unknown settings) are returned as warnings to show to the user.
+    pub fn load(path: Option<&Path>) -> (Self, Vec<String>) {
+        let path = match path {
+            Some(path) => path.to_owned(),
+            None => match dirs::config_dir() {
+                Some(config_dir) => config_dir.join("lesbin").join("config.toml"),
+                None => {
+                    let warning = "Warning: Could not find the config directory, using the default config".to_owned();
+                    return (Self::default_config(), vec![warning]);
+                },
+            },
+        };
+        
+        match fs::read_to_string(&path) {
+            Ok(content) => match Self::parse(&content) {
+                Ok(result) => result,
+                Err(err) => {
+                    let warning = format!("Warning: Invalid config '{}' ({err:#}), using the default config", path.display());
+                    (Self::default_config(), vec![warning])
+                },
+            },
+            Err(err) if err.kind() == ErrorKind::NotFound => {
+                let created = path.parent().map_or(Ok(()), fs::create_dir_all)
+                    .and_then(|()| fs::write(&path, DEFAULT_CONFIG));
+                
+                let warning = created.err().map(|err| {
+                    format!("Warning: Could not create config '{}' ({err}), using the default config", path.display())
+                });
+                
+                (Self::default_config(), Vec::from_iter(warning))
+            },
+            Err(err) => {
+                let warning = format!("Warning: Could not read config '{}' ({err}), using the default config", path.display());
+                (Self::default_config(), vec![warning])
+            },
+        }
+    }
+    
+    /// Parses a config on top of the default one, returning warnings about unknown settings
+    fn parse(content: &str) -> Result<(Self, Vec<String>)> {
+        let user_table = content.parse::<toml::Table>().map_err(|err| {
+            match err.span() {
+                Some(span) => {
+                    let (line, column) = line_column(content, span.start);
+                    anyhow!("line {line}, column {column}: {}", err.message().trim_end())
+                },
+                None => anyhow!("{}", err.message().trim_end()),
+            }
+        })?;
+        
+        let mut table = DEFAULT_CONFIG.parse::<toml::Table>().expect("Default config should be valid");
+        merge_tables(&mut table, user_table);
+        
+        let mut warnings = Vec::new();
+        let mut unknown_keys = Vec::new();
+        
+        let mut track = serde_path_to_error::Track::new();
+        let deserializer = serde_path_to_error::Deserializer::new(toml::Value::Table(table), &mut track);
+        
+        // optional values show up as ? in the path
+        let result = serde_ignored::deserialize(deserializer, |path| unknown_keys.push(format!("'{}'", path.to_string().replace(".?", ""))));
+        let config: Self = result.map_err(|err| anyhow!("{}: {}", track.path(), err.message()))?;
+        
+        if !unknown_keys.is_empty() {
+            warnings.push(format!("Warning: Unknown config settings {}", unknown_keys.join(", ")));
+        }
+        
+        warnings.extend(config.keybinds.validate()?);
+        Ok((config, warnings))
+    }
+    
+    pub fn default_config() -> Self {
+        toml::from_str(DEFAULT_CONFIG).expect("Default config should be valid")
+    }
+}
+
+/// Recursively overwrites the values in `base` with the ones in `overrides`
+fn merge_tables(base: &mut toml::Table, overrides: toml::Table) {
+    for (key, value) in overrides {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base_table)), toml::Value::Table(table)) => merge_tables(base_table, table),
+            (_, value) => {
+                base.insert(key, value);
+            },
+        }
+    }
+}
+
+/// Converts a byte offset into a 1-based line and column
+fn line_column(content: &str, offset: usize) -> (usize, usize) {
+    let before = &content[..offset];
+    let line_start = before.rfind('\n').map_or(0, |newline| newline + 1);
+    
+    (before.matches('\n').count() + 1, before[line_start..].chars().count() + 1)
 }
 
 #[derive(Debug, Deserialize)]
 pub struct Appearance {
     pub margin_horizontal: u16,
     pub margin_vertical: u16,
+    /// Colors supported by the terminal, detected automatically if not set
+    #[serde(default)]
+    pub color_mode: Option<ColorMode>,
+    /// Encoding the text column starts out with
+    pub text_encoding: TextEncoding,
+    /// Whether control characters are shown as symbols like ␀ in the text column, instead of as dots
+    pub control_pictures: bool,
+}
+
+/// Settings for finding the strings in a file
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct StringsConfig {
+    /// Shortest run of printable characters that counts as a string
+    pub min_length: usize,
+    /// Whether UTF-16 strings are found as well
+    pub utf16: bool,
+}
+
+/// Step sizes of the bigger cursor movements with Ctrl
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct NavigationConfig {
+    /// Bytes that Ctrl+Left and Ctrl+Right move by, stopping at multiples of it
+    pub word_size: usize,
+    /// Rows that Ctrl+Up and Ctrl+Down move by
+    pub row_step: usize,
+}
+
+/// What gets remembered about files between runs
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct SessionConfig {
+    /// Whether files open at the position they were left at last time
+    pub restore_position: bool,
+}
+
+/// What gets shown on both sides of the bottom line, from left to right
+#[derive(Debug, Deserialize)]
+pub struct StatusBar {
+    pub left: Vec<StatusItem>,
+    pub right: Vec<StatusItem>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(untagged, expecting = "expected an item name like \"percent\" or a table like { text = \" = \" }")]
+pub enum StatusItem {
+    Named(NamedStatusItem),
+    /// Literal text, which only shows up when the items next to it do
+    Text { text: String },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NamedStatusItem {
+    /// Keyboard shortcuts (on both lines)
+    Hints,
+    Filename,
+    /// Offset of the byte under the cursor
+    Offset,
+    /// Value of the byte under the cursor
+    Value,
+    Selection,
+    Percent,
+    Differences,
+    Follow,
+    /// Legend of the entropy colors, while they're shown
+    Entropy,
+    /// Encoding of the text column, unless it's ASCII
+    Encoding,
+}
+
+/// Built-in theme plus optional overrides for each of its styles
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct ThemeConfig {
+    pub base: BaseTheme,
+    pub regular: Option<Style>,
+    pub emphasis: Option<Style>,
+    pub highlighted: Option<Style>,
+    pub text_cursor: Option<Style>,
+    pub modified: Option<Style>,
+    pub different: Option<Style>,
+    pub address: Option<Style>,
+    pub zero: Option<Style>,
+    pub title: Option<Style>,
+}
+
+#[derive(Debug, Default, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BaseTheme {
+    #[default]
+    Dark,
+    Light,
+}
+
+impl ThemeConfig {
+    pub fn build(&self) -> Theme {
+        let base = match self.base {
+            BaseTheme::Dark => Theme::dark(),
+            BaseTheme::Light => Theme::light(),
+        };
+        
+        Theme {
+            regular: self.regular.unwrap_or(base.regular),
+            emphasis: self.emphasis.unwrap_or(base.emphasis),
+            highlighted: self.highlighted.unwrap_or(base.highlighted),
+            text_cursor: self.text_cursor.unwrap_or(base.text_cursor),
+            modified: self.modified.unwrap_or(base.modified),
+            different: self.different.unwrap_or(base.different),
+            address: self.address.unwrap_or(base.address),
+            zero: self.zero.unwrap_or(base.zero),
+            title: self.title.unwrap_or(base.title),
+            entropy: base.entropy,
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
 pub struct Keybinds {
-    pub quit: Keybind,
-    pub save: Keybind,
-    pub left: Keybind,
-    pub down: Keybind,
-    pub up: Keybind,
-    pub right: Keybind,
-    pub toggle_cursor: Keybind,
-    pub edit: Keybind,
-    pub go_to: Keybind,
-    pub find: Keybind,
-    pub find_binary: Keybind,
-    pub find_text: Keybind,
-}
-
-#[derive(Debug, Clone, Copy)]
+    pub quit: Bindings,
+    pub save: Bindings,
+    pub save_as: Bindings,
+    pub export_patch: Bindings,
+    pub apply_patch: Bindings,
+    pub next_difference: Bindings,
+    pub prev_difference: Bindings,
+    pub next_change: Bindings,
+    pub prev_change: Bindings,
+    pub jump_back: Bindings,
+    pub jump_forward: Bindings,
+    pub next_run: Bindings,
+    pub prev_run: Bindings,
+    pub next_nonzero: Bindings,
+    pub prev_nonzero: Bindings,
+    pub toggle_entropy: Bindings,
+    pub histogram: Bindings,
+    pub strings: Bindings,
+    pub hash: Bindings,
+    pub decode: Bindings,
+    pub go_to_signature: Bindings,
+    pub sections: Bindings,
+    pub cycle_encoding: Bindings,
+    pub toggle_control_pictures: Bindings,
+    pub reload: Bindings,
+    pub follow: Bindings,
+    pub left: Bindings,
+    pub down: Bindings,
+    pub up: Bindings,
+    pub right: Bindings,
+    pub center_cursor: Bindings,
+    pub toggle_cursor: Bindings,
+    pub split: Bindings,
+    pub switch_pane: Bindings,
+    pub next_file: Bindings,
+    pub prev_file: Bindings,
+    pub switch_file: Bindings,
+    pub edit: Bindings,
+    pub go_to: Bindings,
+    pub find: Bindings,
+    pub find_binary: Bindings,
+    pub find_text: Bindings,
+    pub find_next: Bindings,
+    pub list_matches: Bindings,
+}
+
+/// Actions that can only be used in the prompt after pressing find
+const FIND_PROMPT_ACTIONS: &[&str] = &["find_binary", "find_text", "find_next"];
+
+/// Actions that still work while typing hex digits, when editing bytes or in the go to prompt
+const HEX_INPUT_ACTIONS: &[&str] = &["quit", "save", "left", "down", "up", "right", "center_cursor"];
+
+impl Keybinds {
+    fn actions(&self) -> [(&'static str, &Bindings); 44] {
+        [
+            ("quit", &self.quit),
+            ("save", &self.save),
+            ("save_as", &self.save_as),
+            ("export_patch", &self.export_patch),
+            ("apply_patch", &self.apply_patch),
+            ("next_difference", &self.next_difference),
+            ("prev_difference", &self.prev_difference),
+            ("next_change", &self.next_change),
+            ("prev_change", &self.prev_change),
+            ("jump_back", &self.jump_back),
+            ("jump_forward", &self.jump_forward),
+            ("next_run", &self.next_run),
+            ("prev_run", &self.prev_run),
+            ("next_nonzero", &self.next_nonzero),
+            ("prev_nonzero", &self.prev_nonzero),
+            ("toggle_entropy", &self.toggle_entropy),
+            ("histogram", &self.histogram),
+            ("strings", &self.strings),
+            ("hash", &self.hash),
+            ("decode", &self.decode),
+            ("go_to_signature", &self.go_to_signature),
+            ("sections", &self.sections),
+            ("cycle_encoding", &self.cycle_encoding),
+            ("toggle_control_pictures", &self.toggle_control_pictures),
+            ("reload", &self.reload),
+            ("follow", &self.follow),
+            ("left", &self.left),
+            ("down", &self.down),
+            ("up", &self.up),
+            ("right", &self.right),
+            ("center_cursor", &self.center_cursor),
+            ("toggle_cursor", &self.toggle_cursor),
+            ("split", &self.split),
+            ("switch_pane", &self.switch_pane),
+            ("next_file", &self.next_file),
+            ("prev_file", &self.prev_file),
+            ("switch_file", &self.switch_file),
+            ("edit", &self.edit),
+            ("go_to", &self.go_to),
+            ("find", &self.find),
+            ("find_binary", &self.find_binary),
+            ("find_text", &self.find_text),
+            ("find_next", &self.find_next),
+            ("list_matches", &self.list_matches),
+        ]
+    }
+    
+    /// Checks that no key triggers several actions at once, which is an error.
+    /// Keys that get in the way of typing hex digits only produce a warning.
+    pub fn validate(&self) -> Result<Option<String>> {
+        let actions = self.actions();
+        
+        let main_actions = actions.iter().filter(|(name, _)| *name == "find_next" || !FIND_PROMPT_ACTIONS.contains(name));
+        let find_prompt_actions = actions.iter().filter(|(name, _)| FIND_PROMPT_ACTIONS.contains(name));
+        
+        let mut conflicts = find_conflicts(main_actions.collect());
+        conflicts.extend(find_conflicts(find_prompt_actions.collect()));
+        
+        if !conflicts.is_empty() {
+            bail!("Conflicting keybinds: {}", conflicts.join(", "));
+        }
+        
+        let hex_digit_keys = actions.iter()
+            .filter(|(name, _)| HEX_INPUT_ACTIONS.contains(name))
+            .flat_map(|(name, bindings)| bindings.0.iter().map(move |keybind| (name, keybind)))
+            .filter(|(_, keybind)| keybind.modifiers.difference(KeyModifiers::SHIFT).is_empty()
+                && matches!(keybind.code, KeyCode::Char(c) if c.is_ascii_hexdigit()))
+            .map(|(name, keybind)| format!("{name} ('{keybind}')"))
+            .collect::<Vec<_>>();
+        
+        if hex_digit_keys.is_empty() {
+            return Ok(None);
+        }
+        
+        Ok(Some(format!("Warning: The keybinds for {} also trigger when typing hex digits", hex_digit_keys.join(", "))))
+    }
+}
+
+/// Lists every key that is bound to more than one of the actions
+fn find_conflicts(actions: Vec<&(&'static str, &Bindings)>) -> Vec<String> {
+    let mut conflicts = Vec::new();
+    
+    for (i, (name, bindings)) in actions.iter().enumerate() {
+        for (other_name, other_bindings) in &actions[i + 1..] {
+            for keybind in &bindings.0 {
+                if other_bindings.0.iter().any(|other| other.normalized() == keybind.normalized()) {
+                    conflicts.push(format!("'{keybind}' is bound to both {name} and {other_name}"));
+                }
+            }
+        }
+    }
+    
+    conflicts
+}
+
+/// All keys bound to an action, written in the config as a single keybind or an array of them
+#[derive(Debug, Clone)]
+pub struct Bindings(Vec<Keybind>);
+
+impl Bindings {
+    pub fn matches(&self, event: KeyEvent) -> bool {
+        self.0.iter().any(|keybind| keybind.matches(event))
+    }
+    
+    /// Key press of the first keybind, for doing the action without pressing it
+    pub fn key_event(&self) -> KeyEvent {
+        KeyEvent::new(self.0[0].code, self.0[0].modifiers)
+    }
+}
+
+/// Only shows the first keybind, to keep the hints short
+impl Display for Bindings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0[0])
+    }
+}
+
+impl<'de> Deserialize<'de> for Bindings {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>
+    {
+        #[derive(Deserialize)]
+        #[serde(untagged, expecting = "expected a keybind or an array of keybinds")]
+        enum OneOrMany {
+            One(String),
+            Many(Vec<String>),
+        }
+        
+        let strings = match OneOrMany::deserialize(deserializer)? {
+            OneOrMany::One(string) => vec![string],
+            OneOrMany::Many(strings) => strings,
+        };
+        
+        if strings.is_empty() {
+            return Err(de::Error::invalid_length(0, &"at least one keybind"));
+        }
+        
+        let keybinds = strings.iter()
+            .map(|string| Keybind::parse(string).ok_or_else(|| invalid_keybind(string)))
+            .collect::<Result<_, _>>()?;
+        
+        Ok(Self(keybinds))
+    }
+}
+
+/// Key combination, written in the config either as `"^S"` (Ctrl+S)
+/// or with its modifiers spelled out like `"ctrl+alt+s"`, `"shift+del"` or `"F3"`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Keybind {
-    pub control: bool,
-    pub key: char,
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
 }
 
+/// Names of keys that aren't a single character, with the one used for displaying them first
+const KEY_NAMES: &[(KeyCode, &[&str])] = &[
+    (KeyCode::Char(' '), &["Space"]),
+    (KeyCode::Enter, &["Enter", "Return"]),
+    (KeyCode::Tab, &["Tab"]),
+    (KeyCode::BackTab, &["BackTab"]),
+    (KeyCode::Backspace, &["Backspace"]),
+    (KeyCode::Esc, &["Esc", "Escape"]),
+    (KeyCode::Delete, &["Del", "Delete"]),
+    (KeyCode::Insert, &["Ins", "Insert"]),
+    (KeyCode::Home, &["Home"]),
+    (KeyCode::End, &["End"]),
+    (KeyCode::PageUp, &["PgUp", "PageUp"]),
+    (KeyCode::PageDown, &["PgDn", "PageDown"]),
+    (KeyCode::Up, &["Up"]),
+    (KeyCode::Down, &["Down"]),
+    (KeyCode::Left, &["Left"]),
+    (KeyCode::Right, &["Right"]),
+];
+
+const MODIFIER_NAMES: &[(KeyModifiers, &[&str])] = &[
+    (KeyModifiers::CONTROL, &["Ctrl", "Control"]),
+    (KeyModifiers::ALT, &["Alt", "Meta", "Option"]),
+    (KeyModifiers::SHIFT, &["Shift"]),
+    (KeyModifiers::SUPER, &["Super", "Cmd"]),
+];
+
 impl Keybind {
+    /// Checks whether a key press triggers this keybind.
+    /// Letters match regardless of case (and therefore of Shift), all other keys need the exact same modifiers.
     pub fn matches(self, event: KeyEvent) -> bool {
-        let KeyCode::Char(c) = event.code else {
-            return false;
+        match (self.code, event.code) {
+            (KeyCode::Char(key), KeyCode::Char(c)) => {
+                let ignore_shift = |modifiers: KeyModifiers| modifiers.difference(KeyModifiers::SHIFT);
+                
+                key.eq_ignore_ascii_case(&c) && ignore_shift(self.modifiers) == ignore_shift(event.modifiers)
+            },
+            (code, event_code) => code == event_code && self.modifiers == event.modifiers,
+        }
+    }
+    
+    /// Turns keybinds that match the same keys into the same value
+    fn normalized(self) -> Self {
+        match self.code {
+            KeyCode::Char(c) => Self {
+                code: KeyCode::Char(c.to_ascii_lowercase()),
+                modifiers: self.modifiers.difference(KeyModifiers::SHIFT),
+            },
+            _ => self,
+        }
+    }
+    
+    fn parse(string: &str) -> Option<Self> {
+        if let Some(code) = parse_key_code(string) {
+            return Some(Self {
+                code,
+                modifiers: KeyModifiers::NONE,
+            });
+        }
+        
+        // the short form for Ctrl, e.g. ^S
+        if let Some(key) = string.strip_prefix('^') && !key.is_empty() {
+            return Some(Self {
+                code: parse_key_code(key)?,
+                modifiers: KeyModifiers::CONTROL,
+            });
+        }
+        
+        // a trailing + is the key itself, like in "ctrl++"
+        let (modifiers, key) = match string.strip_suffix("++") {
+            Some(modifiers) => (modifiers, "+"),
+            None => string.rsplit_once('+').unwrap_or(("", string)),
+        };
+        
+        let mut keybind = Self {
+            code: parse_key_code(key)?,
+            modifiers: KeyModifiers::NONE,
         };
         
-        let control = event.modifiers.contains(KeyModifiers::CONTROL);
+        for modifier in modifiers.split('+').filter(|modifier| !modifier.is_empty()) {
+            let (flag, _) = MODIFIER_NAMES.iter()
+                .find(|(_, names)| names.iter().any(|name| name.eq_ignore_ascii_case(modifier)))?;
+            
+            keybind.modifiers |= *flag;
+        }
         
-        let char_matches = self.key.to_ascii_lowercase() == c || self.key.to_ascii_uppercase() == c;
-        self.control == control && char_matches
+        Some(keybind)
     }
 }
 
+fn parse_key_code(key: &str) -> Option<KeyCode> {
+    let mut chars = key.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Some(KeyCode::Char(c));
+    }
+    
+    if let Some(number) = key.strip_prefix(['F', 'f']) {
+        return number.parse().ok().filter(|number| (1..=24).contains(number)).map(KeyCode::F);
+    }
+    
+    KEY_NAMES.iter()
+        .find(|(_, names)| names.iter().any(|name| name.eq_ignore_ascii_case(key)))
+        .map(|(code, _)| *code)
+}
+
 impl Display for Keybind {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        if self.control {
+        let is_char = matches!(self.code, KeyCode::Char(c) if c != ' ');
+        
+        if self.modifiers == KeyModifiers::CONTROL && is_char {
             write!(f, "^")?;
+        } else {
+            for (flag, names) in MODIFIER_NAMES {
+                if self.modifiers.contains(*flag) {
+                    write!(f, "{}+", names[0])?;
+                }
+            }
         }
         
-        f.write_char(self.key)
+        match self.code {
+            KeyCode::Char(c) if is_char => f.write_char(c.to_ascii_uppercase()),
+            KeyCode::F(number) => write!(f, "F{number}"),
+            code => {
+                let (_, names) = KEY_NAMES.iter().find(|(key_code, _)| *key_code == code).ok_or(fmt::Error)?;
+                f.write_str(names[0])
+            },
+        }
     }
 }
 
-impl<'de> Deserialize<'de> for Keybind {
-    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
-    where
-        D: serde::Deserializer<'de>
-    {
-        let string = String::deserialize(deserializer)?;
-        
-        let mut control = false;
-        let mut key = None;
-        for c in string.chars() {
-            if key.is_some() {
-                return Err(de::Error::invalid_value(de::Unexpected::Str(&string), &"a valid keybind definition"));
-            }
-            
-            if c == '^' {
-                control = true;
-            } else {
-                key = Some(c);
-            }
+fn invalid_keybind<E: de::Error>(string: &str) -> E {
+    E::invalid_value(
+        de::Unexpected::Str(string),
+        &"a key like \"Q\", \"^S\", \"F3\", \"pageup\" or \"ctrl+alt+s\"",
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    /// Warnings from parsing a config that only changes some keybinds
+    fn warnings(keybinds: &str) -> Result<Vec<String>> {
+        Config::parse(&format!("[keybinds]\n{keybinds}")).map(|(_, warnings)| warnings)
+    }
+    
+    fn error(keybinds: &str) -> String {
+        match warnings(keybinds) {
+            Ok(warnings) => panic!("{keybinds:?} should be an error, got {warnings:?}"),
+            Err(err) => err.to_string(),
         }
+    }
+    
+    #[test]
+    fn default_keybinds_are_valid() {
+        assert_eq!(warnings("").unwrap(), Vec::<String>::new());
+    }
+    
+    #[test]
+    fn keys_bound_twice_conflict() {
+        assert_eq!(error("split = \"Q\""), "Conflicting keybinds: 'Q' is bound to both quit and split");
         
-        let Some(key) = key else {
-            return Err(de::Error::invalid_value(de::Unexpected::Str(&string), &"a valid keybind definition"));
-        };
+        // letters match regardless of case and Shift
+        assert!(error("split = \"shift+q\"").contains("quit and split"));
+        assert!(error("split = [\"X\", \"q\"]").contains("quit and split"));
+        
+        // other modifiers make it a different key
+        assert!(warnings("split = \"^Q\"").is_ok());
+        assert!(warnings("split = \"alt+q\"").is_ok());
+    }
+    
+    #[test]
+    fn every_conflict_gets_listed() {
+        let err = error("split = \"Q\"\nedit = \"G\"");
+        
+        assert!(err.contains("quit and split"), "{err}");
+        assert!(err.contains("edit and go_to"), "{err}");
+    }
+    
+    #[test]
+    fn find_prompt_keys_only_conflict_with_each_other() {
+        // only pressed after find, so they can share keys with the main actions
+        assert!(warnings("find_binary = \"Q\"\nfind_text = \"E\"").is_ok());
+        
+        assert!(error("find_binary = \"T\"").contains("find_binary and find_text"));
+        
+        // find_next works both in the prompt and outside of it
+        assert!(error("find_next = \"Q\"").contains("quit and find_next"));
+        assert!(error("find_next = \"B\"").contains("find_binary and find_next"));
+    }
+    
+    #[test]
+    fn hex_digit_keys_warn_only_where_digits_are_typed() {
+        assert_eq!(warnings("down = \"a\"").unwrap(), ["Warning: The keybinds for down ('A') also trigger when typing hex digits"]);
+        assert_eq!(warnings("save = [\"^S\", \"shift+5\"]").unwrap(), ["Warning: The keybinds for save ('Shift+5') also trigger when typing hex digits"]);
+        
+        // other actions can't be pressed while typing hex digits anyway
+        assert!(warnings("edit = \"a\"").unwrap().is_empty());
+        // keys with modifiers and other characters aren't hex digits
+        assert!(warnings("down = \"alt+a\"").unwrap().is_empty());
+        assert!(warnings("down = \"!\"").unwrap().is_empty());
+    }
+    
+    #[test]
+    fn hex_digit_warning_lists_every_key() {
+        let warnings = warnings("left = \"1\"\nright = \"2\"").unwrap();
+        
+        assert_eq!(warnings, ["Warning: The keybinds for left ('1'), right ('2') also trigger when typing hex digits"]);
+    }
+    
+    fn keybind(string: &str) -> Keybind {
+        Keybind::parse(string).unwrap_or_else(|| panic!("{string:?} should be a valid keybind"))
+    }
+    
+    #[test]
+    fn ctrl_shorthand() {
+        assert_eq!(keybind("^S"), Keybind { code: KeyCode::Char('S'), modifiers: KeyModifiers::CONTROL });
+        assert_eq!(keybind("^s").to_string(), "^S");
+        assert_eq!(keybind("^Home"), Keybind { code: KeyCode::Home, modifiers: KeyModifiers::CONTROL });
+        
+        // a lone ^ is just the key
+        assert_eq!(keybind("^"), Keybind { code: KeyCode::Char('^'), modifiers: KeyModifiers::NONE });
+    }
+    
+    #[test]
+    fn spelled_out_modifiers() {
+        let ctrl_alt_s = Keybind { code: KeyCode::Char('s'), modifiers: KeyModifiers::CONTROL | KeyModifiers::ALT };
+        assert_eq!(keybind("ctrl+alt+s"), ctrl_alt_s);
+        assert_eq!(keybind("Control+Meta+s"), ctrl_alt_s);
+        assert_eq!(keybind("ALT+CTRL+s"), ctrl_alt_s);
+        assert_eq!(ctrl_alt_s.to_string(), "Ctrl+Alt+S");
+        
+        // only Ctrl on its own gets shortened
+        assert_eq!(keybind("ctrl+s").to_string(), "^S");
+        assert_eq!(keybind("shift+ctrl+s").to_string(), "Ctrl+Shift+S");
+        assert_eq!(keybind("cmd+q").to_string(), "Super+Q");
+        assert_eq!(keybind("ctrl++").to_string(), "^+");
+    }
+    
+    #[test]
+    fn named_keys() {
+        assert_eq!(keybind("shift+del"), Keybind { code: KeyCode::Delete, modifiers: KeyModifiers::SHIFT });
+        assert_eq!(keybind("pageup").to_string(), "PgUp");
+        assert_eq!(keybind("Escape").to_string(), "Esc");
+        assert_eq!(keybind("return").to_string(), "Enter");
+        assert_eq!(keybind(" ").to_string(), "Space");
+        assert_eq!(keybind("ctrl+space").to_string(), "Ctrl+Space");
+        assert_eq!(keybind("F3").to_string(), "F3");
+        assert_eq!(keybind("alt+f24").to_string(), "Alt+F24");
+    }
+    
+    #[test]
+    fn displayed_keybinds_parse_back() {
+        let strings = ["Q", "^S", "^+", "ctrl+alt+s", "shift+del", "Shift+Tab", "pagedown", "space", "f1", "super+left", "#", "ctrl+shift+alt+super+x"];
+        
+        for string in strings {
+            let parsed = keybind(string);
+            assert_eq!(keybind(&parsed.to_string()).normalized(), parsed.normalized(), "{string:?} -> {parsed}");
+        }
+    }
+    
+    #[test]
+    fn invalid_keybinds() {
+        for string in ["", "ctrl+", "foo", "hyper+s", "ctrl+foo", "^Foo", "F0", "F25", "^F25", "ctrl+alt"] {
+            assert_eq!(Keybind::parse(string), None, "{string:?}");
+        }
         
-        Ok(Self {
-            control,
-            key,
-        })
+        assert!(error("quit = \"ctrl+\"").contains("invalid value"));
+        assert!(error("quit = []").contains("at least one keybind"));
     }
 }
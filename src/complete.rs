@@ -0,0 +1,286 @@
+use std::{env, fs, path::PathBuf};
+
+use crossterm::event::KeyEvent;
+
+use crate::prompt::PromptBuffer;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirEntry {
+    pub name: String,
+    pub is_dir: bool,
+}
+
+/// Possible completions for the last component of a path
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Completion {
+    /// Everything up to and including the last path separator
+    pub dir: String,
+    /// Names matching the last component, directories with a trailing '/'
+    pub candidates: Vec<String>,
+    /// The longest prefix shared by all candidates
+    pub common_prefix: String,
+}
+
+/// Completes the last component of `input` against `listing`,
+/// which should contain the entries of the directory `input` points into
+pub fn complete(input: &str, listing: &[DirEntry]) -> Completion {
+    let (dir, prefix) = split_path(input);
+    
+    let mut candidates: Vec<String> = listing.iter()
+        .filter(|entry| entry.name.starts_with(prefix))
+        // hidden files only show up if they're asked for
+        .filter(|entry| prefix.starts_with('.') || !entry.name.starts_with('.'))
+        .map(|entry| if entry.is_dir {
+            format!("{}/", entry.name)
+        } else {
+            entry.name.clone()
+        })
+        .collect();
+    
+    candidates.sort();
+    
+    let common_prefix = match candidates.split_first() {
+        Some((first, rest)) => rest.iter().fold(first.as_str(), |common, candidate| {
+            let len = common.char_indices()
+                .zip(candidate.chars())
+                .find(|((_, a), b)| a != b)
+                .map_or(usize::min(common.len(), candidate.len()), |((i, _), _)| i);
+            &common[..len]
+        }).to_owned(),
+        None => prefix.to_owned(),
+    };
+    
+    Completion {
+        dir: dir.to_owned(),
+        candidates,
+        common_prefix,
+    }
+}
+
+/// Splits a path into the directory part (including the trailing separator) and the last component
+pub fn split_path(input: &str) -> (&str, &str) {
+    let split_idx = input.rfind(is_separator).map_or(0, |i| i + 1);
+    input.split_at(split_idx)
+}
+
+/// Replaces a leading `~` with the home directory
+pub fn expand_tilde(input: &str) -> String {
+    let Some(rest) = input.strip_prefix('~') else {
+        return input.to_owned();
+    };
+    
+    if !rest.is_empty() && !rest.starts_with(is_separator) {
+        return input.to_owned();
+    }
+    
+    match home_dir() {
+        Some(home) => format!("{}{rest}", home.display()),
+        None => input.to_owned(),
+    }
+}
+
+/// Reads the entries of the directory a (partial) path points into
+pub fn list_dir(dir: &str) -> Vec<DirEntry> {
+    let dir = if dir.is_empty() { "." } else { dir };
+    
+    let Ok(read_dir) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    
+    read_dir
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let name = entry.file_name().into_string().ok()?;
+            // follow symlinks so linked directories can be drilled into as well
+            let is_dir = entry.path().is_dir();
+            Some(DirEntry { name, is_dir })
+        })
+        .collect()
+}
+
+fn home_dir() -> Option<PathBuf> {
+    env::var_os("HOME")
+        .or_else(|| env::var_os("USERPROFILE"))
+        .filter(|home| !home.is_empty())
+        .map(PathBuf::from)
+}
+
+fn is_separator(c: char) -> bool {
+    c == '/' || (cfg!(windows) && c == '\\')
+}
+
+/// Text input for a file path with tab completion
+#[derive(Debug, Default)]
+pub struct PathInput {
+    pub buffer: PromptBuffer,
+    /// Candidates from the previous Tab press that further presses cycle through
+    pub completion: Option<Completion>,
+    cycle_idx: Option<usize>,
+}
+
+impl PathInput {
+    pub fn new(buffer: String) -> Self {
+        Self {
+            buffer: PromptBuffer::new(buffer),
+            completion: None,
+            cycle_idx: None,
+        }
+    }
+    
+    /// Edits the path like any other prompt, starting over with the completion if anything has been typed
+    pub fn handle_key(&mut self, event: KeyEvent) {
+        if self.buffer.handle_key(event, |_| true) {
+            self.reset_completion();
+        }
+    }
+    
+    /// The path with `~` expanded
+    pub fn path(&self) -> String {
+        expand_tilde(self.buffer.text())
+    }
+    
+    /// Completes as far as all candidates agree on the first press,
+    /// then cycles through the candidates on further presses
+    pub fn tab(&mut self) {
+        if let Some(completion) = &self.completion
+            && completion.candidates.len() > 1
+        {
+            let idx = self.cycle_idx.map_or(0, |idx| (idx + 1) % completion.candidates.len());
+            self.buffer = PromptBuffer::new(format!("{}{}", completion.dir, completion.candidates[idx]));
+            self.cycle_idx = Some(idx);
+            return;
+        }
+        
+        let input = expand_tilde(self.buffer.text());
+        let (dir, _) = split_path(&input);
+        let completion = complete(&input, &list_dir(dir));
+        
+        self.buffer = PromptBuffer::new(format!("{}{}", completion.dir, completion.common_prefix));
+        self.completion = Some(completion);
+        self.cycle_idx = None;
+    }
+    
+    fn reset_completion(&mut self) {
+        self.completion = None;
+        self.cycle_idx = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process;
+    
+    use super::*;
+    
+    fn file(name: &str) -> DirEntry {
+        DirEntry { name: name.to_owned(), is_dir: false }
+    }
+    
+    fn dir(name: &str) -> DirEntry {
+        DirEntry { name: name.to_owned(), is_dir: true }
+    }
+    
+    #[test]
+    fn unique_prefix_completes_fully() {
+        let completion = complete("src/ma", &[file("main.rs"), file("lib.rs"), dir("res")]);
+        
+        assert_eq!(completion.dir, "src/");
+        assert_eq!(completion.candidates, ["main.rs"]);
+        assert_eq!(completion.common_prefix, "main.rs");
+    }
+    
+    #[test]
+    fn shared_prefix_completes_as_far_as_candidates_agree() {
+        let completion = complete("sa", &[file("save.bin"), file("save.ips"), file("sample.bin"), file("other")]);
+        
+        assert_eq!(completion.candidates, ["sample.bin", "save.bin", "save.ips"]);
+        assert_eq!(completion.common_prefix, "sa");
+        
+        let completion = complete("sav", &[file("save.bin"), file("save.ips"), file("sample.bin")]);
+        assert_eq!(completion.common_prefix, "save.");
+    }
+    
+    #[test]
+    fn no_match_keeps_the_input() {
+        let completion = complete("dir/xyz", &[file("abc")]);
+        
+        assert!(completion.candidates.is_empty());
+        assert_eq!(completion.common_prefix, "xyz");
+    }
+    
+    #[test]
+    fn directories_get_a_trailing_slash() {
+        let completion = complete("", &[dir("target"), file("test.bin")]);
+        
+        assert_eq!(completion.candidates, ["target/", "test.bin"]);
+        assert_eq!(completion.common_prefix, "t");
+        
+        assert_eq!(complete("tar", &[dir("target")]).common_prefix, "target/");
+    }
+    
+    #[test]
+    fn hidden_files_need_a_leading_dot() {
+        let listing = [file(".hidden"), file("visible")];
+        
+        assert_eq!(complete("", &listing).candidates, ["visible"]);
+        assert_eq!(complete(".", &listing).candidates, [".hidden"]);
+    }
+    
+    #[test]
+    fn tilde_expands_to_the_home_directory() {
+        let Some(home) = home_dir() else {
+            return;
+        };
+        
+        assert_eq!(expand_tilde("~"), home.display().to_string());
+        assert_eq!(expand_tilde("~/file.bin"), format!("{}/file.bin", home.display()));
+        
+        // other users' home directories and tildes further in aren't expanded
+        assert_eq!(expand_tilde("~user/file.bin"), "~user/file.bin");
+        assert_eq!(expand_tilde("dir/~/file.bin"), "dir/~/file.bin");
+    }
+    
+    #[test]
+    fn tab_cycles_through_candidates() {
+        let dir = env::temp_dir().join(format!("lesbin-complete-test-{}", process::id()));
+        fs::create_dir_all(dir.join("subdir")).unwrap();
+        fs::write(dir.join("save.bin"), b"").unwrap();
+        fs::write(dir.join("save.ips"), b"").unwrap();
+        
+        let prefix = format!("{}/", dir.display());
+        
+        let mut input = PathInput::new(format!("{prefix}sa"));
+        input.tab();
+        assert_eq!(input.buffer.text(), format!("{prefix}save."));
+        input.tab();
+        assert_eq!(input.buffer.text(), format!("{prefix}save.bin"));
+        input.tab();
+        assert_eq!(input.buffer.text(), format!("{prefix}save.ips"));
+        input.tab();
+        assert_eq!(input.buffer.text(), format!("{prefix}save.bin"));
+        
+        let mut input = PathInput::new(format!("{prefix}su"));
+        input.tab();
+        assert_eq!(input.buffer.text(), format!("{prefix}subdir/"));
+        
+        fs::remove_dir_all(&dir).unwrap();
+    }
+    
+    #[test]
+    fn unreadable_directories_complete_nothing() {
+        let path = env::temp_dir().join(format!("lesbin-complete-missing-{}", process::id()));
+        let missing = format!("{}/fi", path.display());
+        
+        assert!(list_dir(&format!("{}/", path.display())).is_empty());
+        
+        let mut input = PathInput::new(missing.clone());
+        input.tab();
+        assert_eq!(input.buffer.text(), missing);
+        assert!(input.completion.as_ref().is_some_and(|completion| completion.candidates.is_empty()));
+        
+        // a file can't be listed either
+        fs::write(&path, b"").unwrap();
+        assert!(list_dir(&format!("{}/", path.display())).is_empty());
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,199 @@
+use std::fmt::Write;
+
+use base64::{Engine, engine::general_purpose::{STANDARD, STANDARD_NO_PAD, URL_SAFE, URL_SAFE_NO_PAD}};
+
+/// Most bytes that get decoded at once, since the results have to fit on one line each
+pub const MAX_DECODE_LEN: usize = 0x400;
+
+/// One way of reading a range of bytes that worked
+#[derive(Debug)]
+pub struct Interpretation {
+    pub name: &'static str,
+    pub text: String,
+}
+
+/// Tries to read `bytes` in several ways, returning the ones that succeed.
+/// The hex string of the bytes always works, so there is at least one interpretation.
+pub fn interpret(bytes: &[u8]) -> Vec<Interpretation> {
+    let mut interpretations = Vec::new();
+    
+    if let Some(text) = utf8(bytes) {
+        interpretations.push(Interpretation { name: "UTF-8", text });
+    }
+    if let Some(text) = base64(bytes) {
+        interpretations.push(Interpretation { name: "Base64", text });
+    }
+    if let Some(text) = leb128(bytes, false) {
+        interpretations.push(Interpretation { name: "ULEB128", text });
+    }
+    if let Some(text) = leb128(bytes, true) {
+        interpretations.push(Interpretation { name: "SLEB128", text });
+    }
+    
+    interpretations.push(Interpretation { name: "Hex", text: hex::encode(bytes) });
+    interpretations
+}
+
+/// Decodes the bytes as UTF-8, with invalid sequences written as `\x..` escapes and control characters escaped.
+/// Returns None if there is no printable character at all.
+fn utf8(bytes: &[u8]) -> Option<String> {
+    let mut text = String::new();
+    let mut has_printable = false;
+    
+    for chunk in bytes.utf8_chunks() {
+        for char in chunk.valid().chars() {
+            if char.is_control() {
+                text.extend(char.escape_default());
+            } else {
+                has_printable |= !char.is_whitespace();
+                text.push(char);
+            }
+        }
+        
+        for byte in chunk.invalid() {
+            let _ = write!(text, "\\x{byte:02x}");
+        }
+    }
+    
+    has_printable.then_some(text)
+}
+
+/// Decodes base64 (standard or URL-safe, with or without padding) and shows the result as hex and ASCII
+fn base64(bytes: &[u8]) -> Option<String> {
+    let trimmed = bytes.trim_ascii();
+    
+    let decoded = [STANDARD, STANDARD_NO_PAD, URL_SAFE, URL_SAFE_NO_PAD].iter()
+        .find_map(|engine| engine.decode(trimmed).ok())
+        .filter(|decoded| !decoded.is_empty())?;
+    
+    let hex = decoded.iter().map(|byte| format!("{byte:02x}")).collect::<Vec<_>>().join(" ");
+    let ascii: String = decoded.iter()
+        .map(|&byte| if byte.is_ascii_graphic() || byte == b' ' { byte as char } else { '.' })
+        .collect();
+    
+    Some(format!("{hex}  {ascii}"))
+}
+
+/// Reads the bytes as a sequence of LEB128 varints, like the ones used in DWARF or WebAssembly.
+/// Returns None if the last varint isn't terminated or one of them doesn't fit into 64 bits.
+fn leb128(bytes: &[u8], signed: bool) -> Option<String> {
+    let mut values = Vec::new();
+    let mut value: u64 = 0;
+    let mut shift = 0;
+    
+    for &byte in bytes {
+        if shift >= 64 {
+            return None;
+        }
+        
+        value |= ((byte & 0x7f) as u64) << shift;
+        shift += 7;
+        
+        if byte & 0x80 != 0 {
+            continue;
+        }
+        
+        if signed {
+            // extend the sign bit of the last group
+            let value = if shift < 64 && byte & 0x40 != 0 { value | !0 << shift } else { value };
+            values.push((value as i64).to_string());
+        } else {
+            values.push(value.to_string());
+        }
+        
+        value = 0;
+        shift = 0;
+    }
+    
+    (shift == 0 && !values.is_empty()).then(|| values.join(", "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn decoded(bytes: &[u8], name: &str) -> Option<String> {
+        interpret(bytes).into_iter().find(|interpretation| interpretation.name == name).map(|interpretation| interpretation.text)
+    }
+    
+    #[test]
+    fn hex_always_works() {
+        let interpretations = interpret(&[]);
+        assert_eq!(interpretations.len(), 1);
+        assert_eq!(interpretations[0].text, "");
+        
+        assert_eq!(interpret(&[0x00, 0xab, 0xff]).last().unwrap().text, "00abff");
+    }
+    
+    #[test]
+    fn utf8_escapes_what_it_cant_show() {
+        assert_eq!(decoded("héllo, 世界".as_bytes(), "UTF-8").as_deref(), Some("héllo, 世界"));
+        assert_eq!(decoded(b"a\xffb\x80", "UTF-8").as_deref(), Some("a\\xffb\\x80"));
+        assert_eq!(decoded(b"a\n\0b", "UTF-8").as_deref(), Some("a\\n\\u{0}b"));
+        
+        // cut off in the middle of a character
+        assert_eq!(decoded(b"\xe2\x82", "UTF-8"), None);
+        assert_eq!(decoded(b"x\xe2\x82", "UTF-8").as_deref(), Some("x\\xe2\\x82"));
+    }
+    
+    #[test]
+    fn utf8_needs_a_printable_character() {
+        assert_eq!(decoded(b" \t\r\n", "UTF-8"), None);
+        assert_eq!(decoded(b"\0\0\0\0", "UTF-8"), None);
+    }
+    
+    #[test]
+    fn base64_in_every_variant() {
+        assert_eq!(decoded(b"aGVsbG8=", "Base64").as_deref(), Some("68 65 6c 6c 6f  hello"));
+        assert_eq!(decoded(b"aGVsbG8", "Base64").as_deref(), Some("68 65 6c 6c 6f  hello"));
+        assert_eq!(decoded(b"+/+/", "Base64").as_deref(), Some("fb ff bf  ..."));
+        assert_eq!(decoded(b"-_-_", "Base64").as_deref(), Some("fb ff bf  ..."));
+        
+        // surrounding whitespace, e.g. a trailing newline, doesn't matter
+        assert_eq!(decoded(b"  aGk=\n", "Base64").as_deref(), Some("68 69  hi"));
+    }
+    
+    #[test]
+    fn invalid_base64_is_skipped() {
+        assert_eq!(decoded(b"a$b=", "Base64"), None);
+        assert_eq!(decoded(b"aGk=aGk=", "Base64"), None);
+        assert_eq!(decoded(b"", "Base64"), None);
+    }
+    
+    #[test]
+    fn leb128_groups_start_with_the_lowest_bits() {
+        assert_eq!(decoded(&[0xe5, 0x8e, 0x26], "ULEB128").as_deref(), Some("624485"));
+        assert_eq!(decoded(&[0xc0, 0xbb, 0x78], "SLEB128").as_deref(), Some("-123456"));
+    }
+    
+    #[test]
+    fn leb128_reads_varints_one_after_another() {
+        let bytes = [0x01, 0x7f, 0x80, 0x01, 0x00];
+        
+        assert_eq!(decoded(&bytes, "ULEB128").as_deref(), Some("1, 127, 128, 0"));
+        assert_eq!(decoded(&bytes, "SLEB128").as_deref(), Some("1, -1, 128, 0"));
+    }
+    
+    #[test]
+    fn leb128_up_to_64_bits() {
+        let mut max = vec![0xff; 9];
+        max.push(0x01);
+        assert_eq!(decoded(&max, "ULEB128"), Some(u64::MAX.to_string()));
+        
+        let mut minus_one = vec![0xff; 9];
+        minus_one.push(0x7f);
+        assert_eq!(decoded(&minus_one, "SLEB128").as_deref(), Some("-1"));
+        
+        let mut too_long = vec![0x80; 10];
+        too_long.push(0x01);
+        assert_eq!(decoded(&too_long, "ULEB128"), None);
+        assert_eq!(decoded(&too_long, "SLEB128"), None);
+    }
+    
+    #[test]
+    fn cut_off_leb128_is_skipped() {
+        assert_eq!(decoded(&[0x80], "ULEB128"), None);
+        assert_eq!(decoded(&[0x01, 0xe5, 0x8e], "ULEB128"), None);
+        assert_eq!(decoded(&[0x01, 0xe5, 0x8e], "SLEB128"), None);
+    }
+}
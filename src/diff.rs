@@ -0,0 +1,268 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+
+use crate::buffer::{self, FileBuffer};
+
+/// Amount of data compared at once when looking for the next difference
+const DIFF_CHUNK_SIZE: usize = 1024 * 1024;
+
+/// Second file that the edited file gets compared against.
+/// It's only used as a reference and never modified.
+pub struct Comparison {
+    pub name: PathBuf,
+    pub bytes: Box<dyn FileBuffer>,
+    
+    /// Number of bytes that differ, counting the part of the longer file
+    /// that goes past the end of the shorter one as different
+    pub differing_bytes: usize,
+}
+
+impl Comparison {
+    pub fn open(path: PathBuf, primary: &mut dyn FileBuffer) -> Result<Self> {
+        let (bytes, _) = buffer::open(&path, false)?;
+        
+        let mut comparison = Self {
+            name: path,
+            bytes,
+            differing_bytes: 0,
+        };
+        
+        comparison.recount(primary)?;
+        Ok(comparison)
+    }
+    
+    /// Counts the differing bytes from scratch, e.g. after the edited file has been reloaded
+    pub fn recount(&mut self, primary: &mut dyn FileBuffer) -> Result<()> {
+        let common_len = usize::min(primary.len(), self.bytes.len());
+        let mut differing_bytes = usize::max(primary.len(), self.bytes.len()) - common_len;
+        
+        for chunk_start in (0..common_len).step_by(DIFF_CHUNK_SIZE) {
+            let chunk = chunk_start..usize::min(chunk_start + DIFF_CHUNK_SIZE, common_len);
+            let (a, b) = (primary.read(chunk.clone())?, self.bytes.read(chunk)?);
+            
+            differing_bytes += a.iter().zip(b).filter(|(a, b)| a != b).count();
+        }
+        
+        self.differing_bytes = differing_bytes;
+        Ok(())
+    }
+    
+    /// Keeps the count of differing bytes up to date when a byte of the edited file changes
+    pub fn update_count(&mut self, offset: usize, prev_value: u8, new_value: u8) -> Result<()> {
+        if offset >= self.bytes.len() {
+            // past the end of the other file, so it's different either way
+            return Ok(());
+        }
+        
+        let other_value = self.bytes.read_byte(offset)?;
+        
+        if prev_value != other_value {
+            self.differing_bytes -= 1;
+        }
+        if new_value != other_value {
+            self.differing_bytes += 1;
+        }
+        
+        Ok(())
+    }
+    
+    /// Keeps the count of differing bytes up to date when a byte gets appended to the edited file
+    pub fn byte_appended(&mut self, offset: usize, value: u8) -> Result<()> {
+        if offset >= self.bytes.len() {
+            self.differing_bytes += 1;
+        } else if self.bytes.read_byte(offset)? == value {
+            // the byte of the other file used to be past the end of the edited file
+            self.differing_bytes -= 1;
+        }
+        
+        Ok(())
+    }
+    
+    /// Returns which bytes of a row of the edited file differ from the other file
+    pub fn row_differences(&mut self, offset: usize, row_bytes: &[u8]) -> Result<[bool; 0x10]> {
+        let mut differences = [true; 0x10];
+        
+        let other_end = usize::min(offset + row_bytes.len(), self.bytes.len());
+        let other_bytes = self.bytes.read(usize::min(offset, other_end)..other_end)?;
+        
+        for (i, (a, b)) in row_bytes.iter().zip(other_bytes).enumerate() {
+            differences[i] = a != b;
+        }
+        
+        Ok(differences)
+    }
+    
+    /// Finds the start of the next run of differing bytes after the one at `start`
+    pub fn next_difference(&mut self, primary: &mut dyn FileBuffer, start: usize) -> Result<Option<usize>> {
+        let Some(run_end) = self.find(primary, start, false)? else {
+            return Ok(None);
+        };
+        
+        self.find(primary, run_end, true)
+    }
+    
+    /// Finds the start of the closest run of differing bytes before `start`
+    pub fn prev_difference(&mut self, primary: &mut dyn FileBuffer, start: usize) -> Result<Option<usize>> {
+        let Some(run_last) = self.rfind(primary, start, true)? else {
+            return Ok(None);
+        };
+        
+        let run_start = self.rfind(primary, run_last, false)?.map_or(0, |before_run| before_run + 1);
+        Ok(Some(run_start))
+    }
+    
+    /// Finds the first offset at or after `start` whose byte either differs or matches.
+    /// Only offsets within the edited file are considered.
+    fn find(&mut self, primary: &mut dyn FileBuffer, start: usize, differing: bool) -> Result<Option<usize>> {
+        let common_len = usize::min(primary.len(), self.bytes.len());
+        
+        for chunk_start in (start..common_len).step_by(DIFF_CHUNK_SIZE) {
+            let chunk = chunk_start..usize::min(chunk_start + DIFF_CHUNK_SIZE, common_len);
+            let (a, b) = (primary.read(chunk.clone())?, self.bytes.read(chunk)?);
+            
+            if let Some(index) = a.iter().zip(b).position(|(a, b)| (a != b) == differing) {
+                return Ok(Some(chunk_start + index));
+            }
+        }
+        
+        // everything past the end of the other file differs
+        let tail_start = usize::max(start, common_len);
+        if differing && tail_start < primary.len() {
+            return Ok(Some(tail_start));
+        }
+        
+        Ok(None)
+    }
+    
+    /// Finds the last offset before `end` whose byte either differs or matches
+    fn rfind(&mut self, primary: &mut dyn FileBuffer, end: usize, differing: bool) -> Result<Option<usize>> {
+        let common_len = usize::min(primary.len(), self.bytes.len());
+        let end = usize::min(end, primary.len());
+        
+        // everything past the end of the other file differs
+        if differing && end > common_len {
+            return Ok(Some(end - 1));
+        }
+        
+        let mut chunk_end = usize::min(end, common_len);
+        
+        while chunk_end > 0 {
+            let chunk = chunk_end.saturating_sub(DIFF_CHUNK_SIZE)..chunk_end;
+            let (a, b) = (primary.read(chunk.clone())?, self.bytes.read(chunk.clone())?);
+            
+            if let Some(index) = a.iter().zip(b).rposition(|(a, b)| (a != b) == differing) {
+                return Ok(Some(chunk.start + index));
+            }
+            
+            chunk_end = chunk.start;
+        }
+        
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    
+    use super::*;
+    use crate::buffer::InMemoryBuffer;
+    
+    fn buffer(bytes: Vec<u8>) -> InMemoryBuffer {
+        InMemoryBuffer(Arc::new(bytes))
+    }
+    
+    /// Compares `primary` against `other`, counting the differences like opening the other file does
+    fn compare(primary: &mut InMemoryBuffer, other: Vec<u8>) -> Comparison {
+        let mut comparison = Comparison {
+            name: PathBuf::from("other"),
+            bytes: Box::new(buffer(other)),
+            differing_bytes: 0,
+        };
+        
+        comparison.recount(primary).unwrap();
+        comparison
+    }
+    
+    #[test]
+    fn equal_files_have_no_differences() {
+        let mut primary = buffer(vec![1, 2, 3]);
+        let mut comparison = compare(&mut primary, vec![1, 2, 3]);
+        
+        assert_eq!(comparison.differing_bytes, 0);
+        assert_eq!(comparison.next_difference(&mut primary, 0).unwrap(), None);
+        assert_eq!(comparison.prev_difference(&mut primary, 3).unwrap(), None);
+        assert_eq!(comparison.row_differences(0, &[1, 2, 3]).unwrap()[..3], [false; 3]);
+        
+        let mut empty = buffer(Vec::new());
+        assert_eq!(compare(&mut empty, Vec::new()).differing_bytes, 0);
+    }
+    
+    #[test]
+    fn difference_at_the_last_byte() {
+        let mut primary = buffer(vec![1, 2, 3]);
+        let mut comparison = compare(&mut primary, vec![1, 2, 4]);
+        
+        assert_eq!(comparison.differing_bytes, 1);
+        assert_eq!(comparison.next_difference(&mut primary, 0).unwrap(), Some(2));
+        // starting inside the last run finds nothing after it
+        assert_eq!(comparison.next_difference(&mut primary, 2).unwrap(), None);
+        assert_eq!(comparison.prev_difference(&mut primary, 3).unwrap(), Some(2));
+        assert_eq!(comparison.prev_difference(&mut primary, 2).unwrap(), None);
+        assert_eq!(comparison.row_differences(0, &[1, 2, 3]).unwrap()[..3], [false, false, true]);
+    }
+    
+    #[test]
+    fn bytes_past_the_end_of_the_shorter_file_differ() {
+        let mut primary = buffer(vec![1, 2, 3, 4, 5]);
+        let mut comparison = compare(&mut primary, vec![1, 2, 3]);
+        
+        assert_eq!(comparison.differing_bytes, 2);
+        assert_eq!(comparison.next_difference(&mut primary, 0).unwrap(), Some(3));
+        assert_eq!(comparison.prev_difference(&mut primary, 5).unwrap(), Some(3));
+        assert_eq!(comparison.row_differences(0, &[1, 2, 3, 4, 5]).unwrap()[..6], [false, false, false, true, true, true]);
+        
+        // only offsets in the edited file can be jumped to
+        let mut primary = buffer(vec![1, 2]);
+        let mut comparison = compare(&mut primary, vec![1, 2, 3, 4]);
+        
+        assert_eq!(comparison.differing_bytes, 2);
+        assert_eq!(comparison.next_difference(&mut primary, 0).unwrap(), None);
+        assert_eq!(comparison.prev_difference(&mut primary, 2).unwrap(), None);
+    }
+    
+    #[test]
+    fn count_follows_edits() {
+        let mut primary = buffer(vec![1, 2, 3]);
+        let mut comparison = compare(&mut primary, vec![1, 2, 4, 5]);
+        assert_eq!(comparison.differing_bytes, 2);
+        
+        comparison.update_count(2, 3, 4).unwrap();
+        assert_eq!(comparison.differing_bytes, 1);
+        comparison.update_count(0, 1, 9).unwrap();
+        assert_eq!(comparison.differing_bytes, 2);
+        
+        // appending the byte that the other file has there makes it equal
+        comparison.byte_appended(3, 5).unwrap();
+        assert_eq!(comparison.differing_bytes, 1);
+        comparison.byte_appended(4, 0).unwrap();
+        assert_eq!(comparison.differing_bytes, 2);
+    }
+    
+    #[test]
+    fn runs_crossing_chunks_are_found_once() {
+        let len = 2 * DIFF_CHUNK_SIZE;
+        let mut other = vec![0; len];
+        other[DIFF_CHUNK_SIZE - 1..DIFF_CHUNK_SIZE + 1].fill(1);
+        other[len - 1] = 1;
+        
+        let mut primary = buffer(vec![0; len]);
+        let mut comparison = compare(&mut primary, other);
+        
+        assert_eq!(comparison.differing_bytes, 3);
+        assert_eq!(comparison.next_difference(&mut primary, 0).unwrap(), Some(DIFF_CHUNK_SIZE - 1));
+        assert_eq!(comparison.next_difference(&mut primary, DIFF_CHUNK_SIZE - 1).unwrap(), Some(len - 1));
+        assert_eq!(comparison.prev_difference(&mut primary, len - 1).unwrap(), Some(DIFF_CHUNK_SIZE - 1));
+    }
+}
@@ -0,0 +1,124 @@
+use std::io::{ErrorKind, Write};
+
+use anyhow::{Result, bail};
+
+use crate::{buffer::FileBuffer, encoding::TextEncoding, ui::format_row, util::{LineColor, TextWriter}};
+
+/// Amount of rows that get formatted before being written out
+const ROWS_PER_WRITE: usize = 256;
+
+#[derive(Debug, Default)]
+pub struct DumpOptions {
+    pub colored: bool,
+    pub offset: usize,
+    pub length: Option<usize>,
+    pub text_encoding: TextEncoding,
+    pub control_pictures: bool,
+}
+
+/// Prints the buffer to `out` (usually stdout) in the same layout as the hex view, without starting the TUI
+pub fn dump(buffer: &mut dyn FileBuffer, options: &DumpOptions, out: &mut impl Write) -> Result<()> {
+    if options.offset > buffer.len() {
+        bail!("Offset 0x{:x} is past the end of the file (0x{:x} bytes)", options.offset, buffer.len());
+    }
+    
+    let end = match options.length {
+        Some(length) => usize::min(options.offset.saturating_add(length), buffer.len()),
+        None => buffer.len(),
+    };
+    
+    let mut writer = TextWriter::new(options.colored);
+    
+    for (i, row_start) in (options.offset..end).step_by(0x10).enumerate() {
+        let row_bytes = buffer.read(row_start..usize::min(row_start + 0x10, end))?;
+        
+        format_row(&mut writer, row_start, row_bytes, [None; 0x10], LineColor::Address, options.text_encoding, options.control_pictures);
+        writer.end_line();
+        
+        if (i + 1) % ROWS_PER_WRITE == 0 && !write_out(out, &mut writer.buffer)? {
+            return Ok(());
+        }
+    }
+    
+    write_out(out, &mut writer.buffer)?;
+    Ok(())
+}
+
+/// Returns false if nothing is reading the output anymore
+fn write_out(out: &mut impl Write, buffer: &mut Vec<u8>) -> Result<bool> {
+    let result = out.write_all(buffer).and_then(|()| out.flush());
+    buffer.clear();
+    
+    match result {
+        Ok(()) => Ok(true),
+        // whatever is reading the output (e.g. `head`) has seen enough, which isn't an error
+        Err(err) if err.kind() == ErrorKind::BrokenPipe => Ok(false),
+        Err(err) => Err(err.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    
+    use super::*;
+    use crate::buffer::InMemoryBuffer;
+    
+    fn dump_text(bytes: &[u8], options: DumpOptions) -> Result<String> {
+        let mut out = Vec::new();
+        dump(&mut InMemoryBuffer(Arc::new(bytes.to_vec())), &options, &mut out)?;
+        Ok(String::from_utf8(out).unwrap())
+    }
+    
+    #[test]
+    fn rows_match_the_hex_view() {
+        let bytes: Vec<u8> = (0x40..0x62).collect();
+        
+        assert_eq!(dump_text(&bytes, DumpOptions::default()).unwrap(), concat!(
+            "0000 0000:  40 41 42 43 44 45 46 47  48 49 4a 4b 4c 4d 4e 4f  @ABCDEFG HIJKLMNO\n",
+            "0000 0010:  50 51 52 53 54 55 56 57  58 59 5a 5b 5c 5d 5e 5f  PQRSTUVW XYZ[\\]^_\n",
+            "0000 0020:  60 61                                             `a \n",
+        ));
+    }
+    
+    #[test]
+    fn offset_and_length() {
+        let bytes: Vec<u8> = (0x40..0x62).collect();
+        
+        let options = DumpOptions { offset: 5, length: Some(3), ..Default::default() };
+        assert_eq!(dump_text(&bytes, options).unwrap(), "0000 0005:  45 46 47                                          EFG \n");
+        
+        let options = DumpOptions { offset: 0x1e, ..Default::default() };
+        assert_eq!(dump_text(&bytes, options).unwrap(), "0000 001e:  5e 5f 60 61                                       ^_`a \n");
+        
+        let options = DumpOptions { offset: 5, length: Some(0), ..Default::default() };
+        assert_eq!(dump_text(&bytes, options).unwrap(), "");
+    }
+    
+    #[test]
+    fn range_past_eof() {
+        let bytes: Vec<u8> = (0x40..0x62).collect();
+        
+        let options = DumpOptions { offset: 0x20, length: Some(0x100), ..Default::default() };
+        assert_eq!(dump_text(&bytes, options).unwrap(), "0000 0020:  60 61                                             `a \n");
+        
+        let options = DumpOptions { offset: 0x20, length: Some(usize::MAX), ..Default::default() };
+        assert_eq!(dump_text(&bytes, options).unwrap(), "0000 0020:  60 61                                             `a \n");
+        
+        let options = DumpOptions { offset: 0x22, ..Default::default() };
+        assert_eq!(dump_text(&bytes, options).unwrap(), "");
+        
+        let options = DumpOptions { offset: 0x23, ..Default::default() };
+        let err = dump_text(&bytes, options).unwrap_err();
+        assert_eq!(err.to_string(), "Offset 0x23 is past the end of the file (0x22 bytes)");
+    }
+    
+    #[test]
+    fn many_rows() {
+        let bytes = vec![0u8; (ROWS_PER_WRITE + 1) * 16 + 4];
+        let text = dump_text(&bytes, DumpOptions::default()).unwrap();
+        
+        assert_eq!(text.lines().count(), ROWS_PER_WRITE + 2);
+        assert_eq!(text.lines().last().unwrap(), "0000 1010:  00 00 00 00                                       .... ");
+    }
+}
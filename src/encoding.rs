@@ -0,0 +1,223 @@
+use encoding_rs::SHIFT_JIS;
+use serde::Deserialize;
+use unicode_width::UnicodeWidthChar;
+
+/// Bytes in each half of a row, the halves are separated by a space in the text column
+pub const HALF_ROW: usize = 0x8;
+
+/// How bytes get turned into characters in the text column
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TextEncoding {
+    /// Only printable 7-bit ASCII
+    #[default]
+    Ascii,
+    Latin1,
+    #[serde(rename = "utf16le")]
+    Utf16Le,
+    #[serde(rename = "utf16be")]
+    Utf16Be,
+    ShiftJis,
+    /// Code page 037 (US/Canada)
+    Ebcdic,
+}
+
+/// What gets shown under one byte in the text column
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextCell {
+    Char(char),
+    /// Symbol standing in for a control character or space, which gets dimmed
+    Picture(char),
+    /// Taken up by the second half of the wide character before it, so nothing gets written
+    Covered,
+}
+
+impl TextEncoding {
+    const ALL: [Self; 6] = [
+        Self::Ascii,
+        Self::Latin1,
+        Self::Utf16Le,
+        Self::Utf16Be,
+        Self::ShiftJis,
+        Self::Ebcdic,
+    ];
+    
+    /// The encoding after this one, going back to the first one after the last
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|&encoding| encoding == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+    
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Ascii => "ASCII",
+            Self::Latin1 => "Latin-1",
+            Self::Utf16Le => "UTF-16LE",
+            Self::Utf16Be => "UTF-16BE",
+            Self::ShiftJis => "Shift-JIS",
+            Self::Ebcdic => "EBCDIC",
+        }
+    }
+    
+    /// Decodes a row of the hex view into exactly one cell per byte, so the text column always has the same width.
+    /// Characters made of several bytes are shown under their first byte and the cells of the other bytes are left blank.
+    /// Bytes that can't be decoded or displayed are shown as '.', except for control characters and spaces
+    /// which get shown as their symbol from the Unicode control pictures block if `control_pictures` is set.
+    pub fn decode_row(self, bytes: &[u8], control_pictures: bool) -> Vec<TextCell> {
+        let mut cells = vec![TextCell::Char('.'); bytes.len()];
+        
+        match self {
+            Self::Ascii => {
+                for (i, &byte) in bytes.iter().enumerate() {
+                    if byte.is_ascii() {
+                        place_char(&mut cells, i, 1, byte as char, control_pictures);
+                    }
+                }
+            },
+            Self::Latin1 | Self::Ebcdic => {
+                for (i, &byte) in bytes.iter().enumerate() {
+                    let byte = if self == Self::Ebcdic { EBCDIC_TO_LATIN1[byte as usize] } else { byte };
+                    // Latin-1 is the first 256 code points of Unicode
+                    place_char(&mut cells, i, 1, byte as char, control_pictures);
+                }
+            },
+            Self::Utf16Le | Self::Utf16Be => {
+                let units = bytes.chunks_exact(2).map(|pair| {
+                    let pair = [pair[0], pair[1]];
+                    if self == Self::Utf16Le { u16::from_le_bytes(pair) } else { u16::from_be_bytes(pair) }
+                });
+                
+                let mut i = 0;
+                for result in char::decode_utf16(units) {
+                    match result {
+                        Ok(char) => {
+                            let len = char.len_utf16() * 2;
+                            place_char(&mut cells, i, len, char, control_pictures);
+                            i += len;
+                        },
+                        // unpaired surrogate
+                        Err(_) => {
+                            cells[i + 1] = TextCell::Char(' ');
+                            i += 2;
+                        },
+                    }
+                }
+            },
+            Self::ShiftJis => {
+                let mut i = 0;
+                while i < bytes.len() {
+                    let len = if is_shift_jis_lead(bytes[i]) && i + 1 < bytes.len() { 2 } else { 1 };
+                    
+                    let decoded = SHIFT_JIS.decode_without_bom_handling_and_without_replacement(&bytes[i..i + len]);
+                    match decoded.and_then(|text| text.chars().next()) {
+                        Some(char) => {
+                            place_char(&mut cells, i, len, char, control_pictures);
+                            i += len;
+                        },
+                        // try again with the next byte, which might not belong to this one after all
+                        None => i += 1,
+                    }
+                }
+            },
+        }
+        
+        cells
+    }
+}
+
+/// Puts a character decoded from `len` bytes starting at `start` into their cells.
+/// Wide characters also take up the cell after the first one, which only works
+/// if the character is made of several bytes and doesn't cross the middle of the row.
+fn place_char(cells: &mut [TextCell], start: usize, len: usize, char: char, control_pictures: bool) {
+    let end = usize::min(start + len, cells.len());
+    cells[start..end].fill(TextCell::Char(' '));
+    
+    if control_pictures && let Some(picture) = control_picture(char) {
+        cells[start] = TextCell::Picture(picture);
+        return;
+    }
+    
+    // private use characters (e.g. user-defined ones in Shift-JIS) have no glyph in most fonts
+    let displayable = !char.is_whitespace() && !char.is_control() && !matches!(char, '\u{e000}'..='\u{f8ff}');
+    
+    cells[start] = match char.width() {
+        Some(1) if displayable => TextCell::Char(char),
+        Some(2) if displayable && end - start >= 2 && !(start + 1).is_multiple_of(HALF_ROW) => {
+            cells[start + 1] = TextCell::Covered;
+            TextCell::Char(char)
+        },
+        _ => TextCell::Char('.'),
+    };
+}
+
+/// Symbol for a C0 control character, space or delete, like ␀ or ␊
+fn control_picture(char: char) -> Option<char> {
+    match char {
+        '\0'..='\u{1f}' => char::from_u32(0x2400 + char as u32),
+        ' ' => Some('␣'),
+        '\u{7f}' => Some('␡'),
+        _ => None,
+    }
+}
+
+fn is_shift_jis_lead(byte: u8) -> bool {
+    matches!(byte, 0x81..=0x9f | 0xe0..=0xfc)
+}
+
+/// Characters of EBCDIC code page 037, which all happen to be in Latin-1
+const EBCDIC_TO_LATIN1: [u8; 0x100] = [
+    0x00, 0x01, 0x02, 0x03, 0x9c, 0x09, 0x86, 0x7f, 0x97, 0x8d, 0x8e, 0x0b, 0x0c, 0x0d, 0x0e, 0x0f,
+    0x10, 0x11, 0x12, 0x13, 0x9d, 0x85, 0x08, 0x87, 0x18, 0x19, 0x92, 0x8f, 0x1c, 0x1d, 0x1e, 0x1f,
+    0x80, 0x81, 0x82, 0x83, 0x84, 0x0a, 0x17, 0x1b, 0x88, 0x89, 0x8a, 0x8b, 0x8c, 0x05, 0x06, 0x07,
+    0x90, 0x91, 0x16, 0x93, 0x94, 0x95, 0x96, 0x04, 0x98, 0x99, 0x9a, 0x9b, 0x14, 0x15, 0x9e, 0x1a,
+    0x20, 0xa0, 0xe2, 0xe4, 0xe0, 0xe1, 0xe3, 0xe5, 0xe7, 0xf1, 0xa2, 0x2e, 0x3c, 0x28, 0x2b, 0x7c,
+    0x26, 0xe9, 0xea, 0xeb, 0xe8, 0xed, 0xee, 0xef, 0xec, 0xdf, 0x21, 0x24, 0x2a, 0x29, 0x3b, 0xac,
+    0x2d, 0x2f, 0xc2, 0xc4, 0xc0, 0xc1, 0xc3, 0xc5, 0xc7, 0xd1, 0xa6, 0x2c, 0x25, 0x5f, 0x3e, 0x3f,
+    0xf8, 0xc9, 0xca, 0xcb, 0xc8, 0xcd, 0xce, 0xcf, 0xcc, 0x60, 0x3a, 0x23, 0x40, 0x27, 0x3d, 0x22,
+    0xd8, 0x61, 0x62, 0x63, 0x64, 0x65, 0x66, 0x67, 0x68, 0x69, 0xab, 0xbb, 0xf0, 0xfd, 0xfe, 0xb1,
+    0xb0, 0x6a, 0x6b, 0x6c, 0x6d, 0x6e, 0x6f, 0x70, 0x71, 0x72, 0xaa, 0xba, 0xe6, 0xb8, 0xc6, 0xa4,
+    0xb5, 0x7e, 0x73, 0x74, 0x75, 0x76, 0x77, 0x78, 0x79, 0x7a, 0xa1, 0xbf, 0xd0, 0xdd, 0xde, 0xae,
+    0x5e, 0xa3, 0xa5, 0xb7, 0xa9, 0xa7, 0xb6, 0xbc, 0xbd, 0xbe, 0x5b, 0x5d, 0xaf, 0xa8, 0xb4, 0xd7,
+    0x7b, 0x41, 0x42, 0x43, 0x44, 0x45, 0x46, 0x47, 0x48, 0x49, 0xad, 0xf4, 0xf6, 0xf2, 0xf3, 0xf5,
+    0x7d, 0x4a, 0x4b, 0x4c, 0x4d, 0x4e, 0x4f, 0x50, 0x51, 0x52, 0xb9, 0xfb, 0xfc, 0xf9, 0xfa, 0xff,
+    0x5c, 0xf7, 0x53, 0x54, 0x55, 0x56, 0x57, 0x58, 0x59, 0x5a, 0xb2, 0xd4, 0xd6, 0xd2, 0xd3, 0xd5,
+    0x30, 0x31, 0x32, 0x33, 0x34, 0x35, 0x36, 0x37, 0x38, 0x39, 0xb3, 0xdb, 0xdc, 0xd9, 0xda, 0x9f,
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn chars(cells: &[TextCell]) -> String {
+        cells.iter().map(|cell| match cell {
+            TextCell::Char(char) | TextCell::Picture(char) => *char,
+            TextCell::Covered => '_',
+        }).collect()
+    }
+    
+    #[test]
+    fn ascii_hides_bytes_with_the_high_bit_set() {
+        let cells = TextEncoding::Ascii.decode_row(&[b'A', 0x80, 0xC1, 0xFF, b'z'], false);
+        
+        assert_eq!(chars(&cells), "A...z");
+    }
+    
+    #[test]
+    fn latin1_shows_bytes_with_the_high_bit_set() {
+        let cells = TextEncoding::Latin1.decode_row(&[b'A', 0x80, 0xC1, 0xFF], false);
+        
+        // 0x80 is a C1 control character
+        assert_eq!(chars(&cells), "A.Áÿ");
+    }
+    
+    #[test]
+    fn control_characters_become_pictures() {
+        let bytes = [0x00, b'\n', b' ', 0x7F, 0x80];
+        
+        assert_eq!(chars(&TextEncoding::Ascii.decode_row(&bytes, false)), ".....");
+        
+        let cells = TextEncoding::Ascii.decode_row(&bytes, true);
+        assert_eq!(&cells[..4], &[TextCell::Picture('␀'), TextCell::Picture('␊'), TextCell::Picture('␣'), TextCell::Picture('␡')]);
+        assert_eq!(cells[4], TextCell::Char('.'));
+    }
+}
@@ -0,0 +1,151 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+
+use crate::buffer::FileBuffer;
+
+/// Amount of bytes that the entropy is calculated over
+pub const BLOCK_SIZE: usize = 0x100;
+
+/// Shannon entropy of `bytes` in bits per byte, from 0 (all bytes the same) to 8 (every value equally common)
+pub fn shannon_entropy(bytes: &[u8]) -> f32 {
+    if bytes.is_empty() {
+        return 0.0;
+    }
+    
+    let mut counts = [0usize; 0x100];
+    for &byte in bytes {
+        counts[byte as usize] += 1;
+    }
+    
+    let len = bytes.len() as f32;
+    let entropy: f32 = counts.iter()
+        .filter(|&&count| count > 0)
+        .map(|&count| {
+            let probability = count as f32 / len;
+            -probability * probability.log2()
+        })
+        .sum();
+    
+    // avoid showing -0 for uniform blocks
+    entropy.max(0.0)
+}
+
+/// Entropy of the blocks that have been looked at so far, so scrolling doesn't recalculate it all the time
+#[derive(Default)]
+pub struct EntropyCache {
+    blocks: HashMap<usize, f32>,
+}
+
+impl EntropyCache {
+    /// Returns the entropy of the block containing `offset`, calculating it if necessary
+    pub fn entropy_at(&mut self, buffer: &mut dyn FileBuffer, offset: usize) -> Result<f32> {
+        let block = offset / BLOCK_SIZE;
+        
+        if let Some(&entropy) = self.blocks.get(&block) {
+            return Ok(entropy);
+        }
+        
+        let start = block * BLOCK_SIZE;
+        let end = usize::min(start + BLOCK_SIZE, buffer.len());
+        let entropy = shannon_entropy(buffer.read(start..end)?);
+        
+        self.blocks.insert(block, entropy);
+        Ok(entropy)
+    }
+    
+    /// Forgets the entropy of the block containing `offset` after it has been changed
+    pub fn invalidate(&mut self, offset: usize) {
+        self.blocks.remove(&(offset / BLOCK_SIZE));
+    }
+    
+    /// Forgets the entropy of the block containing `offset` and all blocks after it, e.g. after the file has grown
+    pub fn invalidate_from(&mut self, offset: usize) {
+        self.blocks.retain(|&block, _| block < offset / BLOCK_SIZE);
+    }
+    
+    pub fn clear(&mut self) {
+        self.blocks.clear();
+    }
+}
+
+/// Maps an entropy from 0 to 8 bits onto one of `levels` steps of a color ramp
+pub fn entropy_level(entropy: f32, levels: usize) -> usize {
+    let level = (entropy / 8.0 * levels as f32) as usize;
+    level.min(levels - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    
+    use super::*;
+    use crate::buffer::InMemoryBuffer;
+    
+    /// Every byte value once per block
+    fn uniform(blocks: usize) -> Vec<u8> {
+        (0..blocks * BLOCK_SIZE).map(|i| i as u8).collect()
+    }
+    
+    #[test]
+    fn all_zeros_have_no_entropy() {
+        assert_eq!(shannon_entropy(&[0; BLOCK_SIZE]), 0.0);
+        assert_eq!(shannon_entropy(&[0xff; 3]), 0.0);
+        assert_eq!(shannon_entropy(&[]), 0.0);
+    }
+    
+    #[test]
+    fn uniform_bytes_have_8_bits() {
+        assert!((shannon_entropy(&uniform(1)) - 8.0).abs() < 1e-4);
+        assert!((shannon_entropy(&uniform(4)) - 8.0).abs() < 1e-4);
+    }
+    
+    #[test]
+    fn two_values_have_1_bit() {
+        let bytes: Vec<u8> = (0..BLOCK_SIZE).map(|i| (i % 2) as u8).collect();
+        
+        assert!((shannon_entropy(&bytes) - 1.0).abs() < 1e-4);
+    }
+    
+    #[test]
+    fn last_block_can_be_partial() {
+        let mut buffer = InMemoryBuffer(Arc::new(uniform(1)[..0x10].to_vec()));
+        let mut cache = EntropyCache::default();
+        
+        assert!((cache.entropy_at(&mut buffer, 0xf).unwrap() - 4.0).abs() < 1e-4);
+    }
+    
+    #[test]
+    fn invalidating_recalculates_the_right_blocks() {
+        let mut zeros = InMemoryBuffer(Arc::new(vec![0; 4 * BLOCK_SIZE]));
+        let mut changed = InMemoryBuffer(Arc::new(uniform(4)));
+        
+        let mut cache = EntropyCache::default();
+        for block in 0..4 {
+            assert_eq!(cache.entropy_at(&mut zeros, block * BLOCK_SIZE).unwrap(), 0.0);
+        }
+        
+        // blocks that are still cached keep their entropy even though the data has changed
+        let mut entropies = |cache: &mut EntropyCache| {
+            (0..4).map(|block| cache.entropy_at(&mut changed, block * BLOCK_SIZE + 0x80).unwrap().round()).collect::<Vec<_>>()
+        };
+        assert_eq!(entropies(&mut cache), [0.0, 0.0, 0.0, 0.0]);
+        
+        cache.invalidate(BLOCK_SIZE + 0x50);
+        assert_eq!(entropies(&mut cache), [0.0, 8.0, 0.0, 0.0]);
+        
+        cache.invalidate_from(2 * BLOCK_SIZE + 0x80);
+        assert_eq!(entropies(&mut cache), [0.0, 8.0, 8.0, 8.0]);
+        
+        cache.clear();
+        assert_eq!(entropies(&mut cache), [8.0, 8.0, 8.0, 8.0]);
+    }
+    
+    #[test]
+    fn levels_cover_the_whole_range() {
+        assert_eq!(entropy_level(0.0, 8), 0);
+        assert_eq!(entropy_level(4.0, 8), 4);
+        assert_eq!(entropy_level(7.99, 8), 7);
+        assert_eq!(entropy_level(8.0, 8), 7);
+    }
+}
@@ -0,0 +1,193 @@
+use std::{ops::Range, sync::mpsc::{self, Receiver, TryRecvError}, thread};
+
+use anyhow::{Result, anyhow};
+use md5::{Digest, Md5};
+use sha1::Sha1;
+use sha2::Sha256;
+
+use crate::buffer::FileBuffer;
+
+/// Amount of data hashed at once, between progress updates
+const HASH_CHUNK_SIZE: usize = 1024 * 1024;
+
+/// Names of the hashes, in the same order as their digests
+pub const HASH_NAMES: [&str; 4] = ["CRC-32", "MD5", "SHA-1", "SHA-256"];
+
+/// Digests of a range of the file, as hex strings in the order of [`HASH_NAMES`]
+#[derive(Debug)]
+pub struct Digests {
+    pub range: Range<usize>,
+    pub values: [String; 4],
+}
+
+enum HashMessage {
+    Progress(usize),
+    Done(Result<Digests>),
+}
+
+/// Hashing running on a background thread.
+/// Dropping it cancels the hashing, since the thread stops once nobody is listening anymore.
+pub struct HashJob {
+    range: Range<usize>,
+    position: usize,
+    
+    receiver: Receiver<HashMessage>,
+}
+
+impl HashJob {
+    pub fn start(buffer: &dyn FileBuffer, range: Range<usize>) -> Result<Self> {
+        let mut snapshot = buffer.snapshot()?;
+        
+        let (sender, receiver) = mpsc::channel();
+        let thread_range = range.clone();
+        
+        thread::spawn(move || {
+            let result = hash(&mut *snapshot, thread_range, |position| {
+                sender.send(HashMessage::Progress(position)).is_ok()
+            });
+            
+            // nothing gets sent if the hashing has been cancelled
+            if let Some(result) = result.transpose() {
+                let _ = sender.send(HashMessage::Done(result));
+            }
+        });
+        
+        Ok(Self {
+            position: range.start,
+            range,
+            receiver,
+        })
+    }
+    
+    pub fn progress_percentage(&self) -> usize {
+        if self.range.is_empty() {
+            return 100;
+        }
+        
+        (self.position - self.range.start) * 100 / self.range.len()
+    }
+    
+    /// Processes the messages sent by the hashing thread so far.
+    /// Returns the digests once they're done.
+    pub fn poll(&mut self) -> Option<Result<Digests>> {
+        loop {
+            match self.receiver.try_recv() {
+                Ok(HashMessage::Progress(position)) => self.position = position,
+                Ok(HashMessage::Done(result)) => return Some(result),
+                Err(TryRecvError::Empty) => return None,
+                Err(TryRecvError::Disconnected) => return Some(Err(anyhow!("Hashing stopped unexpectedly"))),
+            }
+        }
+    }
+}
+
+/// Calculates all hashes over `range` in one pass.
+/// Returns None if `on_progress` returns false, which cancels the hashing.
+fn hash(buffer: &mut dyn FileBuffer, range: Range<usize>, mut on_progress: impl FnMut(usize) -> bool) -> Result<Option<Digests>> {
+    let mut crc32 = crc32fast::Hasher::new();
+    let mut md5 = Md5::new();
+    let mut sha1 = Sha1::new();
+    let mut sha256 = Sha256::new();
+    
+    for chunk_start in range.clone().step_by(HASH_CHUNK_SIZE) {
+        if !on_progress(chunk_start) {
+            return Ok(None);
+        }
+        
+        let chunk = buffer.read(chunk_start..usize::min(chunk_start + HASH_CHUNK_SIZE, range.end))?;
+        
+        crc32.update(chunk);
+        md5.update(chunk);
+        sha1.update(chunk);
+        sha256.update(chunk);
+    }
+    
+    Ok(Some(Digests {
+        range,
+        values: [
+            format!("{:08x}", crc32.finalize()),
+            hex::encode(md5.finalize()),
+            hex::encode(sha1.finalize()),
+            hex::encode(sha256.finalize()),
+        ],
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    
+    use super::*;
+    use crate::buffer::InMemoryBuffer;
+    
+    const EMPTY_DIGESTS: [&str; 4] = [
+        "00000000",
+        "d41d8cd98f00b204e9800998ecf8427e",
+        "da39a3ee5e6b4b0d3255bfef95601890afd80709",
+        "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855",
+    ];
+    
+    const ABC_DIGESTS: [&str; 4] = [
+        "352441c2",
+        "900150983cd24fb0d6963f7d28e17f72",
+        "a9993e364706816aba3e25717850c26c9cd0d89d",
+        "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+    ];
+    
+    fn digests(bytes: &[u8], range: Range<usize>) -> [String; 4] {
+        let mut buffer = InMemoryBuffer(Arc::new(bytes.to_vec()));
+        hash(&mut buffer, range, |_| true).unwrap().unwrap().values
+    }
+    
+    #[test]
+    fn known_digests() {
+        assert_eq!(digests(b"", 0..0), EMPTY_DIGESTS);
+        assert_eq!(digests(b"abc", 0..3), ABC_DIGESTS);
+        
+        // only the range gets hashed
+        assert_eq!(digests(b"xxabcx", 2..5), ABC_DIGESTS);
+        assert_eq!(digests(b"abc", 1..1), EMPTY_DIGESTS);
+    }
+    
+    #[test]
+    fn hashing_in_chunks_gives_the_same_digests() {
+        let bytes: Vec<u8> = (0..HASH_CHUNK_SIZE * 2 + 3).map(|i| i as u8).collect();
+        
+        let mut crc32 = crc32fast::Hasher::new();
+        crc32.update(&bytes);
+        let expected = [format!("{:08x}", crc32.finalize()), hex::encode(Sha256::digest(&bytes))];
+        
+        let values = digests(&bytes, 0..bytes.len());
+        assert_eq!([values[0].clone(), values[3].clone()], expected);
+    }
+    
+    #[test]
+    fn hashing_stops_when_cancelled() {
+        let mut buffer = InMemoryBuffer(Arc::new(vec![0; HASH_CHUNK_SIZE * 3]));
+        let mut positions = Vec::new();
+        
+        let result = hash(&mut buffer, 0..HASH_CHUNK_SIZE * 3, |position| {
+            positions.push(position);
+            positions.len() < 2
+        });
+        
+        assert!(result.unwrap().is_none());
+        assert_eq!(positions, [0, HASH_CHUNK_SIZE]);
+    }
+    
+    #[test]
+    fn job_hashes_on_another_thread() {
+        let buffer = InMemoryBuffer(Arc::new(b"abc".to_vec()));
+        let mut job = HashJob::start(&buffer, 0..3).unwrap();
+        
+        let digests = loop {
+            if let Some(result) = job.poll() {
+                break result.unwrap();
+            }
+            thread::yield_now();
+        };
+        
+        assert_eq!(digests.range, 0..3);
+        assert_eq!(digests.values, ABC_DIGESTS);
+    }
+}
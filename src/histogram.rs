@@ -0,0 +1,90 @@
+use std::ops::Range;
+
+use anyhow::Result;
+
+use crate::buffer::FileBuffer;
+
+/// Amount of data counted at once
+const COUNT_CHUNK_SIZE: usize = 1024 * 1024;
+
+/// How often each byte value occurs in a range of the file
+pub struct Histogram {
+    pub range: Range<usize>,
+    pub counts: [usize; 0x100],
+}
+
+impl Histogram {
+    pub fn count(buffer: &mut dyn FileBuffer, range: Range<usize>) -> Result<Self> {
+        let mut counts = [0; 0x100];
+        
+        for chunk_start in range.clone().step_by(COUNT_CHUNK_SIZE) {
+            let chunk_end = usize::min(chunk_start + COUNT_CHUNK_SIZE, range.end);
+            
+            for &byte in buffer.read(chunk_start..chunk_end)? {
+                counts[byte as usize] += 1;
+            }
+        }
+        
+        Ok(Self { range, counts })
+    }
+    
+    /// The value that occurs the most, preferring lower values when there's a tie
+    pub fn most_frequent(&self) -> u8 {
+        let max_count = self.counts.iter().copied().max().unwrap_or(0);
+        self.counts.iter().position(|&count| count == max_count).unwrap_or(0) as u8
+    }
+    
+    /// Occurrences of all values in `values` together
+    pub fn class_count(&self, values: impl IntoIterator<Item = u8>) -> usize {
+        values.into_iter().map(|value| self.counts[value as usize]).sum()
+    }
+    
+    /// Share of the counted bytes that have `value`, in percent
+    pub fn percentage(&self, value: u8) -> f64 {
+        if self.range.is_empty() {
+            return 0.0;
+        }
+        
+        self.counts[value as usize] as f64 * 100.0 / self.range.len() as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    
+    use super::*;
+    use crate::buffer::InMemoryBuffer;
+    
+    #[test]
+    fn counts_bytes_in_the_range() {
+        let mut buffer = InMemoryBuffer(Arc::new(b"\0aab\xff\xffb\xff".to_vec()));
+        
+        let histogram = Histogram::count(&mut buffer, 0..8).unwrap();
+        assert_eq!((histogram.counts[0], histogram.counts[b'a' as usize]), (1, 2));
+        assert_eq!((histogram.counts[b'b' as usize], histogram.counts[0xff]), (2, 3));
+        assert_eq!(histogram.counts.iter().sum::<usize>(), 8);
+        assert_eq!(histogram.most_frequent(), 0xff);
+        assert_eq!(histogram.class_count(b'a'..=b'z'), 4);
+        assert_eq!(histogram.percentage(0xff), 37.5);
+        assert_eq!(histogram.percentage(0x10), 0.0);
+        
+        // only the selected part gets counted, and ties go to the lower value
+        let histogram = Histogram::count(&mut buffer, 1..5).unwrap();
+        assert_eq!(histogram.counts.iter().sum::<usize>(), 4);
+        assert_eq!(histogram.counts[0], 0);
+        assert_eq!(histogram.most_frequent(), b'a');
+        assert_eq!(histogram.percentage(b'a'), 50.0);
+    }
+    
+    #[test]
+    fn empty_range_counts_nothing() {
+        let mut buffer = InMemoryBuffer(Arc::new(Vec::new()));
+        
+        let histogram = Histogram::count(&mut buffer, 0..0).unwrap();
+        assert_eq!(histogram.counts, [0; 0x100]);
+        assert_eq!(histogram.most_frequent(), 0);
+        assert_eq!(histogram.class_count(0..=0xff), 0);
+        assert_eq!(histogram.percentage(0), 0.0);
+    }
+}
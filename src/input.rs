@@ -1,31 +1,74 @@
+use std::{cell::Cell, mem, time::{Duration, Instant}};
+
 use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
 
-use crate::{InputState, State, cfg::Keybinds};
+use crate::{InputState, Pane, PendingAction, State, cfg::{Bindings, Keybinds, NavigationConfig}, complete::PathInput, hash::HASH_NAMES, max_col_for_row, max_row, prompt::PromptBuffer, ui::{RowPosition, row_position_at}, util::copy_to_clipboard};
+
+/// Amount of strings that Page Up and Page Down move by in the strings list
+const STRINGS_PAGE_SIZE: usize = 10;
+
+/// Longest time between the clicks of a double or triple click
+const MULTI_CLICK_TIME: Duration = Duration::from_millis(400);
+
+/// Bytes selected by a double click on the hex view, which is always aligned to this size
+const CLICK_WORD_SIZE: usize = 4;
+
+/// Left click on the hex view, remembered to notice double and triple clicks
+#[derive(Debug, Clone, Copy)]
+pub struct Click {
+    time: Instant,
+    column: u16,
+    row: u16,
+    /// 1 for a single click, 2 for a double click and 3 for a triple click
+    count: usize,
+}
+
+/// What handling an event has done, which tells the main loop whether the screen has to be drawn again
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Handled {
+    /// Nothing has changed, like for keys that aren't bound to anything
+    Nothing,
+    Changed,
+    /// The program should exit
+    Quit,
+}
+
+impl Handled {
+    /// Turns the result of [`State::request_quit`] and [`State::request_save`] into what has been done,
+    /// which is showing a prompt or a message whenever the program keeps running
+    fn keep_running(keep_running: bool) -> Self {
+        if keep_running { Handled::Changed } else { Handled::Quit }
+    }
+}
 
-pub fn handle_input(event: Event, keybinds: &Keybinds, state: &mut State) -> bool {
+pub fn handle_input(event: Event, keybinds: &Keybinds, state: &mut State) -> Handled {
     match event {
         Event::Key(key_event) => {
             // special case for Ctrl C
             if let KeyCode::Char('c') = key_event.code && key_event.modifiers.contains(KeyModifiers::CONTROL) {
-                return false;
+                return Handled::Quit;
             }
             
+            // messages only stay until the next key press
+            let cleared_message = state.bottom_text.take().is_some();
+            
             match &mut state.input_state {
                 InputState::Regular => {
-                    return handle_key_main(key_event, keybinds, state);
+                    return match handle_key_main(key_event, keybinds, state) {
+                        Handled::Nothing if cleared_message => Handled::Changed,
+                        handled => handled,
+                    };
                 },
                 InputState::Edit { prev_in_pager } => {
                     match key_event.code {
-                        KeyCode::Char(c) => {
-                            if c.is_ascii_hexdigit() {
-                                handle_edit_input(c, state);
-                            }
+                        KeyCode::Char(c) if c.is_ascii_hexdigit() => {
+                            handle_edit_input(c, state);
                         },
                         KeyCode::Esc => {
                             state.queued_input_state = Some(InputState::Regular);
                             
                             if *prev_in_pager {
-                                state.selection = None;
+                                state.pane_mut().hide_cursor();
                             }
                         },
                         _ => {},
@@ -35,92 +78,425 @@ pub fn handle_input(event: Event, keybinds: &Keybinds, state: &mut State) -> boo
                     
                     // Quit
                     if keybinds.quit.matches(key_event) {
-                        return false;
+                        return Handled::keep_running(state.request_quit());
                     }
                     
                     // Save
                     if keybinds.save.matches(key_event) {
-                        if let Err(err) = state.save_file() {
-                            state.bottom_text = Some(format!("Error: {err}"));
-                        }
+                        return Handled::keep_running(state.request_save(false));
                     }
                 },
                 InputState::Goto(buffer) | InputState::FindBytes(buffer) => {
                     match key_event.code {
-                        KeyCode::Backspace => {
-                            buffer.pop();
+                        KeyCode::Enter => {
+                            state.commit_input_state();
                         },
-                        KeyCode::Char(c) => {
-                            if c.is_ascii_hexdigit() {
-                                buffer.push(c);
-                            }
+                        KeyCode::Esc => {
+                            state.queued_input_state = Some(InputState::Regular);
+                        },
+                        _ => {
+                            buffer.handle_key(key_event, |c| c.is_ascii_hexdigit());
                         },
+                    }
+                    
+                    if keybinds.quit.matches(key_event) {
+                        return Handled::keep_running(state.request_quit());
+                    }
+                },
+                InputState::FindString(buffer) => {
+                    match key_event.code {
                         KeyCode::Enter => {
                             state.commit_input_state();
                         },
                         KeyCode::Esc => {
                             state.queued_input_state = Some(InputState::Regular);
                         },
+                        _ => {
+                            buffer.handle_key(key_event, |_| true);
+                        },
+                    }
+                    
+                    if keybinds.quit.matches(key_event) {
+                        return Handled::keep_running(state.request_quit());
+                    }
+                },
+                InputState::SwitchFile(buffer) => {
+                    match key_event.code {
+                        KeyCode::Enter => {
+                            state.commit_input_state();
+                        },
+                        KeyCode::Esc => {
+                            state.queued_input_state = Some(InputState::Regular);
+                        },
+                        _ => {
+                            buffer.handle_key(key_event, |_| true);
+                        },
+                    }
+                },
+                InputState::SaveAs(path_input) | InputState::ExportPatch(path_input) | InputState::ApplyPatch(path_input) => {
+                    match key_event.code {
+                        KeyCode::Tab => {
+                            path_input.tab();
+                        },
+                        KeyCode::Enter => {
+                            state.commit_input_state();
+                        },
+                        KeyCode::Esc => {
+                            state.queued_input_state = Some(InputState::Regular);
+                        },
+                        _ => {
+                            path_input.handle_key(key_event);
+                        },
+                    }
+                },
+                InputState::Find => {
+                    if key_event.code == KeyCode::Esc {
+                        state.queued_input_state = Some(InputState::Regular);
+                    }
+                    
+                    if keybinds.find_binary.matches(key_event) {
+                        state.queued_input_state = Some(InputState::FindBytes(PromptBuffer::default()));
+                    }
+                    
+                    if keybinds.find_text.matches(key_event) {
+                        state.queued_input_state = Some(InputState::FindString(PromptBuffer::default()));
+                    }
+                    
+                    if keybinds.find_next.matches(key_event) {
+                        state.queued_input_state = Some(InputState::Regular);
+                        state.find_next();
+                    }
+                },
+                InputState::UnsavedChanges(PendingAction::Quit) => {
+                    match key_event.code {
+                        KeyCode::Char('y' | 'Y') => {
+                            state.queued_input_state = Some(InputState::Regular);
+                            return Handled::keep_running(state.request_save(true));
+                        },
+                        KeyCode::Char('n' | 'N') => {
+                            state.file.discard_on_quit = true;
+                            return Handled::keep_running(state.request_quit());
+                        },
+                        KeyCode::Esc => {
+                            state.queued_input_state = Some(InputState::Regular);
+                            state.cancel_quit();
+                        },
+                        _ => {},
+                    }
+                },
+                InputState::UnsavedChanges(PendingAction::Reload) => {
+                    match key_event.code {
+                        KeyCode::Char('y' | 'Y') => {
+                            state.queued_input_state = Some(InputState::Regular);
+                            state.reload();
+                        },
+                        KeyCode::Char('n' | 'N') => {
+                            state.queued_input_state = Some(InputState::Regular);
+                        },
+                        KeyCode::Esc => {
+                            state.queued_input_state = Some(InputState::Regular);
+                        },
+                        _ => {},
+                    }
+                },
+                InputState::PatchPastEnd(_) => {
+                    match key_event.code {
+                        KeyCode::Char('y' | 'Y') => {
+                            state.queued_input_state = Some(InputState::Regular);
+                            
+                            if let InputState::PatchPastEnd(records) = mem::replace(&mut state.input_state, InputState::Regular) {
+                                state.write_patch(records);
+                            }
+                        },
+                        KeyCode::Char('n' | 'N') | KeyCode::Esc => {
+                            state.queued_input_state = Some(InputState::Regular);
+                            state.bottom_text = Some("The patch has not been applied".to_owned());
+                        },
+                        _ => {},
+                    }
+                },
+                InputState::RecoverEdits | InputState::RecoveredEdits { .. } => {
+                    match key_event.code {
+                        KeyCode::Char('a' | 'A') => {
+                            state.queued_input_state = Some(InputState::Regular);
+                            state.apply_recovered_edits();
+                        },
+                        KeyCode::Char('d' | 'D') => {
+                            state.queued_input_state = Some(InputState::Regular);
+                            state.discard_recovered_edits();
+                        },
+                        KeyCode::Char('v' | 'V') => {
+                            state.queued_input_state = Some(InputState::RecoveredEdits { scroll: 0 });
+                        },
+                        KeyCode::Esc => {
+                            state.queued_input_state = Some(InputState::RecoverEdits);
+                        },
+                        _ => {},
+                    }
+                    
+                    // the list gets clamped to the edits when it's drawn
+                    if let InputState::RecoveredEdits { scroll } = &mut state.input_state {
+                        if key_event.code == KeyCode::Up || keybinds.up.matches(key_event) {
+                            *scroll = scroll.saturating_sub(1);
+                        }
+                        if key_event.code == KeyCode::Down || keybinds.down.matches(key_event) {
+                            *scroll += 1;
+                        }
+                    }
+                },
+                InputState::Histogram { selected } => {
+                    if key_event.code == KeyCode::Left || keybinds.left.matches(key_event) {
+                        *selected = selected.wrapping_sub(1);
+                    }
+                    if key_event.code == KeyCode::Right || keybinds.right.matches(key_event) {
+                        *selected = selected.wrapping_add(1);
+                    }
+                    if key_event.code == KeyCode::Up || keybinds.up.matches(key_event) {
+                        *selected = selected.wrapping_sub(0x10);
+                    }
+                    if key_event.code == KeyCode::Down || keybinds.down.matches(key_event) {
+                        *selected = selected.wrapping_add(0x10);
+                    }
+                    
+                    let selected = *selected;
+                    
+                    match key_event.code {
+                        KeyCode::Enter => state.jump_to_value(selected),
+                        KeyCode::Esc => state.queued_input_state = Some(InputState::Regular),
                         _ => {},
                     }
                     
+                    if keybinds.histogram.matches(key_event) {
+                        state.queued_input_state = Some(InputState::Regular);
+                    }
                     if keybinds.quit.matches(key_event) {
-                        return false;
+                        return Handled::keep_running(state.request_quit());
                     }
                 },
-                InputState::FindString(buffer) => {
+                InputState::Strings(panel) => {
+                    let match_count = panel.matches().len();
+                    
                     match key_event.code {
                         KeyCode::Backspace => {
-                            buffer.pop();
+                            let mut filter = mem::take(&mut panel.filter);
+                            filter.pop();
+                            panel.set_filter(filter);
                         },
                         KeyCode::Char(c) => {
-                            buffer.push(c);
+                            let filter = format!("{}{c}", panel.filter);
+                            panel.set_filter(filter);
+                        },
+                        KeyCode::Up => {
+                            panel.selected = panel.selected.saturating_sub(1);
+                        },
+                        KeyCode::Down => {
+                            panel.selected = usize::min(panel.selected + 1, match_count.saturating_sub(1));
+                        },
+                        KeyCode::PageUp => {
+                            panel.selected = panel.selected.saturating_sub(STRINGS_PAGE_SIZE);
+                        },
+                        KeyCode::PageDown => {
+                            panel.selected = usize::min(panel.selected + STRINGS_PAGE_SIZE, match_count.saturating_sub(1));
                         },
                         KeyCode::Enter => {
-                            state.commit_input_state();
+                            if let Some(offset) = panel.selected_string().map(|string| string.offset) {
+                                state.queued_input_state = Some(InputState::Regular);
+                                state.jump_to(offset);
+                            }
                         },
                         KeyCode::Esc => {
                             state.queued_input_state = Some(InputState::Regular);
                         },
                         _ => {},
                     }
+                },
+                InputState::Sections(panel) => {
+                    let match_count = panel.matches().len();
                     
-                    if keybinds.quit.matches(key_event) {
-                        return false;
+                    match key_event.code {
+                        KeyCode::Backspace => {
+                            let mut filter = mem::take(&mut panel.filter);
+                            filter.pop();
+                            panel.set_filter(filter);
+                        },
+                        KeyCode::Char(c) => {
+                            let filter = format!("{}{c}", panel.filter);
+                            panel.set_filter(filter);
+                        },
+                        KeyCode::Up => {
+                            panel.selected = panel.selected.saturating_sub(1);
+                        },
+                        KeyCode::Down => {
+                            panel.selected = usize::min(panel.selected + 1, match_count.saturating_sub(1));
+                        },
+                        KeyCode::Enter => {
+                            if let Some(offset) = panel.selected_section().map(|section| section.offset) {
+                                if offset < state.file.bytes.len() {
+                                    state.queued_input_state = Some(InputState::Regular);
+                                    state.jump_to(offset);
+                                } else {
+                                    state.bottom_text = Some(format!("The section starts past the end of the file (0x{offset:x})"));
+                                }
+                            }
+                        },
+                        KeyCode::Esc => {
+                            state.queued_input_state = Some(InputState::Regular);
+                        },
+                        _ => {},
                     }
                 },
-                InputState::Find => {
-                    if key_event.code == KeyCode::Esc {
+                InputState::Matches(panel) => {
+                    let filtered = state.file.matches.as_ref().map(|matches| panel.filtered(matches)).unwrap_or_default();
+                    
+                    match key_event.code {
+                        KeyCode::Backspace => {
+                            panel.filter.pop();
+                            panel.selected = 0;
+                        },
+                        KeyCode::Char(c) if c.is_ascii_hexdigit() && key_event.modifiers.difference(KeyModifiers::SHIFT).is_empty() => {
+                            panel.filter.push(c);
+                            panel.selected = 0;
+                        },
+                        KeyCode::Up => {
+                            panel.selected = panel.selected.saturating_sub(1);
+                        },
+                        KeyCode::Down => {
+                            panel.selected = usize::min(panel.selected + 1, filtered.len().saturating_sub(1));
+                        },
+                        KeyCode::Enter => {
+                            // the panel stays open, so the matches can be looked at one after another
+                            if let Some(&offset) = filtered.get(panel.selected) {
+                                state.jump_to(offset);
+                            }
+                        },
+                        KeyCode::Esc => {
+                            state.queued_input_state = Some(InputState::Regular);
+                        },
+                        _ => {},
+                    }
+                    
+                    if keybinds.list_matches.matches(key_event)
+                        && let Some(needle) = state.file.matches.as_ref().map(|matches| matches.needle.clone())
+                    {
+                        // Find the matches again
+                        state.collect_matches(needle);
+                    }
+                },
+                InputState::Hashes { digests, selected } => {
+                    if key_event.code == KeyCode::Up || keybinds.up.matches(key_event) {
+                        *selected = selected.saturating_sub(1);
+                    }
+                    if key_event.code == KeyCode::Down || keybinds.down.matches(key_event) {
+                        *selected = usize::min(*selected + 1, HASH_NAMES.len() - 1);
+                    }
+                    
+                    match key_event.code {
+                        KeyCode::Enter => {
+                            let name = HASH_NAMES[*selected];
+                            
+                            state.bottom_text = Some(match copy_to_clipboard(&digests.values[*selected]) {
+                                Ok(()) => format!("Copied the {name} digest to the clipboard"),
+                                Err(err) => format!("Error: {err}"),
+                            });
+                        },
+                        KeyCode::Esc => state.queued_input_state = Some(InputState::Regular),
+                        _ => {},
+                    }
+                    
+                    if keybinds.hash.matches(key_event) {
                         state.queued_input_state = Some(InputState::Regular);
                     }
+                    if keybinds.quit.matches(key_event) {
+                        return Handled::keep_running(state.request_quit());
+                    }
+                },
+                InputState::Decoded { interpretations, selected, .. } => {
+                    if key_event.code == KeyCode::Up || keybinds.up.matches(key_event) {
+                        *selected = selected.saturating_sub(1);
+                    }
+                    if key_event.code == KeyCode::Down || keybinds.down.matches(key_event) {
+                        *selected = usize::min(*selected + 1, interpretations.len() - 1);
+                    }
                     
-                    if keybinds.find_binary.matches(key_event) {
-                        state.queued_input_state = Some(InputState::FindBytes(String::new()));
+                    match key_event.code {
+                        KeyCode::Enter => {
+                            let interpretation = &interpretations[*selected];
+                            
+                            state.bottom_text = Some(match copy_to_clipboard(&interpretation.text) {
+                                Ok(()) => format!("Copied the {} text to the clipboard", interpretation.name),
+                                Err(err) => format!("Error: {err}"),
+                            });
+                        },
+                        KeyCode::Esc => state.queued_input_state = Some(InputState::Regular),
+                        _ => {},
                     }
                     
-                    if keybinds.find_text.matches(key_event) {
-                        state.queued_input_state = Some(InputState::FindString(String::new()));
+                    if keybinds.decode.matches(key_event) {
+                        state.queued_input_state = Some(InputState::Regular);
+                    }
+                    if keybinds.quit.matches(key_event) {
+                        return Handled::keep_running(state.request_quit());
+                    }
+                },
+                InputState::ExternallyModified { quit_after_save } => {
+                    let quit_after_save = *quit_after_save;
+                    
+                    match key_event.code {
+                        KeyCode::Char('o' | 'O') => {
+                            state.queued_input_state = Some(InputState::Regular);
+                            
+                            if let Err(err) = state.save_file() {
+                                state.bottom_text = Some(format!("Error: {err}"));
+                            } else if quit_after_save {
+                                return Handled::keep_running(state.request_quit());
+                            }
+                        },
+                        KeyCode::Char('r' | 'R') => {
+                            state.queued_input_state = Some(InputState::Regular);
+                            
+                            state.reload();
+                        },
+                        KeyCode::Char('c' | 'C') | KeyCode::Esc => {
+                            state.queued_input_state = Some(InputState::Regular);
+                        },
+                        _ => {},
                     }
                 },
             }
+            
+            // the other input states are prompts and panels, which take every key
+            Handled::Changed
         },
-        Event::Mouse(mouse_event) => {
-            handle_mouse(mouse_event, state);
-        },
-        _ => {},
+        Event::Mouse(mouse_event) => handle_mouse(mouse_event, keybinds, state),
+        Event::Resize(..) => Handled::Changed,
+        Event::FocusGained | Event::FocusLost | Event::Paste(_) => Handled::Nothing,
     }
-    
-    true
 }
 
-fn handle_edit_input(c: char, state: &mut State<'_>) {
-    if let Some((row, col)) = &mut state.selection
+fn handle_edit_input(c: char, state: &mut State) {
+    if let Some((row, col)) = state.pane().selection
         && let Some(digit) = c.to_digit(16)
     {
-        let offset = *col / 2 + *row * 0x10;
-        let prev_byte = state.bytes[offset];
+        let offset = col / 2 + row * 0x10;
+        if offset > state.file.bytes.len() {
+            return;
+        }
+        
+        // typing right after the last byte makes the file longer
+        if offset == state.file.bytes.len() && let Err(err) = state.append_byte() {
+            state.bottom_text = Some(format!("Error: {err}"));
+            return;
+        }
         
-        let new_byte = if *col % 2 == 0 {
+        let prev_byte = match state.file.bytes.read_byte(offset) {
+            Ok(prev_byte) => prev_byte,
+            Err(err) => {
+                state.bottom_text = Some(format!("Error: {err}"));
+                return;
+            },
+        };
+        
+        let new_byte = if col % 2 == 0 {
             // Modify upper half of byte
             (prev_byte & 0xF) | ((digit as u8) << 4)
         } else {
@@ -128,66 +504,218 @@ fn handle_edit_input(c: char, state: &mut State<'_>) {
             (prev_byte & 0xF0) | (digit as u8)
         };
         
-        if prev_byte != new_byte {
-            state.bytes[offset] = new_byte;
-            state.modified_bytes.entry(*row).or_default()[*col / 2] = true;
+        if let Err(err) = state.write_byte(offset, new_byte) {
+            state.bottom_text = Some(format!("Error: {err}"));
+            return;
         }
         
-        *col += 1;
-        if *col >= 0x20 {
-            *col = 0;
-            *row += 1;
-        }
+        state.pane_mut().anchor = None;
+        state.pane_mut().selection = Some(if col + 1 >= 0x20 {
+            (row + 1, 0)
+        } else {
+            (row, col + 1)
+        });
     }
 }
 
-fn handle_key_main(event: KeyEvent, keybinds: &Keybinds, state: &mut State<'_>) -> bool {
-    handle_navigation(event, keybinds, state);
+fn handle_key_main(event: KeyEvent, keybinds: &Keybinds, state: &mut State) -> Handled {
+    // whether the key has done anything, which is the case for every key that triggers an action
+    let matched = Cell::new(handle_navigation(event, keybinds, state));
+    let pressed = |bindings: &Bindings| {
+        let pressed = bindings.matches(event);
+        matched.set(matched.get() || pressed);
+        pressed
+    };
     
-    if keybinds.toggle_cursor.matches(event) {
+    if pressed(&keybinds.toggle_cursor) {
         // Toggle pager and selection mode
-        if state.selection.is_some() {
-            state.selection = None;
+        let file_len = state.file.bytes.len();
+        let pane = state.pane_mut();
+        if pane.selection.is_some() {
+            pane.hide_cursor();
         } else {
-            state.selection = Some((state.scroll_pos, 0));
+            pane.show_cursor(file_len);
         }
     }
-    if keybinds.edit.matches(event) {
+    if pressed(&keybinds.edit) && state.check_writable() {
         // Enable edit mode
         state.queued_input_state = Some(InputState::Edit {
-            prev_in_pager: state.selection.is_none(),
+            prev_in_pager: state.pane().selection.is_none(),
         });
         
-        if state.selection.is_none() {
-            state.selection = Some((state.scroll_pos, 0));
+        let file_len = state.file.bytes.len();
+        let pane = state.pane_mut();
+        if pane.selection.is_none() {
+            pane.show_cursor(file_len);
         }
     }
-    if keybinds.go_to.matches(event) {
+    if pressed(&keybinds.split) {
+        // Toggle split view
+        state.toggle_split();
+    }
+    if pressed(&keybinds.switch_pane) {
+        // Focus other pane
+        state.focus_next_pane();
+    }
+    if pressed(&keybinds.next_file) {
+        // Switch to next file
+        state.cycle_file(true);
+    }
+    if pressed(&keybinds.prev_file) {
+        // Switch to previous file
+        state.cycle_file(false);
+    }
+    if pressed(&keybinds.switch_file) {
+        // Switch to file by number or name
+        state.queued_input_state = Some(InputState::SwitchFile(PromptBuffer::default()));
+    }
+    if pressed(&keybinds.go_to) {
         // Go to
-        state.queued_input_state = Some(InputState::Goto(String::new()));
+        state.queued_input_state = Some(InputState::Goto(PromptBuffer::default()));
     }
-    if keybinds.find.matches(event) {
+    if pressed(&keybinds.export_patch) {
+        // Export patch
+        if state.file.modified_bytes.is_empty() {
+            state.bottom_text = Some("There are no changes to export".to_owned());
+        } else {
+            state.queued_input_state = Some(InputState::ExportPatch(PathInput::default()));
+        }
+    }
+    if pressed(&keybinds.apply_patch) && state.check_writable() {
+        // Apply patch
+        state.queued_input_state = Some(InputState::ApplyPatch(PathInput::default()));
+    }
+    if pressed(&keybinds.find) {
         // Find
         state.queued_input_state = Some(InputState::Find);
     }
-    if keybinds.save.matches(event) {
-        // TODO: Save as
-        if let Err(err) = state.save_file() {
-            state.bottom_text = Some(format!("Error: {err}"));
-        }
+    if pressed(&keybinds.find_next) {
+        // Find next
+        state.find_next();
+    }
+    if pressed(&keybinds.list_matches) {
+        // List all matches
+        state.open_matches();
+    }
+    if pressed(&keybinds.next_difference) {
+        // Jump to next difference
+        state.jump_to_difference(true);
+    }
+    if pressed(&keybinds.prev_difference) {
+        // Jump to previous difference
+        state.jump_to_difference(false);
+    }
+    if pressed(&keybinds.next_change) {
+        // Jump to next unsaved change
+        state.jump_to_change(true);
+    }
+    if pressed(&keybinds.prev_change) {
+        // Jump to previous unsaved change
+        state.jump_to_change(false);
+    }
+    if pressed(&keybinds.jump_back) {
+        // Go back to where the cursor was before the last jump
+        state.jump_in_history(false);
+    }
+    if pressed(&keybinds.jump_forward) {
+        // Undo going back
+        state.jump_in_history(true);
+    }
+    if pressed(&keybinds.next_run) {
+        // Skip the run of equal bytes
+        state.skip_run(true, None);
+    }
+    if pressed(&keybinds.prev_run) {
+        // Skip the run of equal bytes backwards
+        state.skip_run(false, None);
+    }
+    if pressed(&keybinds.next_nonzero) {
+        // Skip to the next non-zero byte
+        state.skip_run(true, Some(0));
+    }
+    if pressed(&keybinds.prev_nonzero) {
+        // Skip to the previous non-zero byte
+        state.skip_run(false, Some(0));
+    }
+    if pressed(&keybinds.toggle_entropy) {
+        // Toggle entropy colors
+        state.show_entropy = !state.show_entropy;
+    }
+    if pressed(&keybinds.histogram) {
+        // Show histogram
+        state.open_histogram();
+    }
+    if pressed(&keybinds.strings) {
+        // List strings
+        state.open_strings();
+    }
+    if pressed(&keybinds.hash) {
+        // Calculate hashes
+        state.start_hashing();
+    }
+    if pressed(&keybinds.decode) {
+        // Decode selection
+        state.decode_selection();
+    }
+    if pressed(&keybinds.go_to_signature) {
+        // Jump to the magic bytes
+        state.jump_to_signature();
+    }
+    if pressed(&keybinds.sections) {
+        // List sections
+        state.open_sections();
     }
-    if keybinds.quit.matches(event) {
+    if pressed(&keybinds.cycle_encoding) {
+        // Switch text encoding
+        state.text_encoding = state.text_encoding.next();
+        state.bottom_text = Some(format!("Text encoding: {}", state.text_encoding.name()));
+    }
+    if pressed(&keybinds.toggle_control_pictures) {
+        // Toggle control character symbols
+        state.control_pictures = !state.control_pictures;
+        state.bottom_text = Some(if state.control_pictures {
+            "Showing control characters as symbols".to_owned()
+        } else {
+            "Showing control characters as dots".to_owned()
+        });
+    }
+    if pressed(&keybinds.follow) {
+        // Toggle follow mode
+        state.toggle_follow();
+    }
+    if pressed(&keybinds.reload) {
+        // Reload from disk
+        state.request_reload();
+    }
+    if pressed(&keybinds.save) && state.check_writable() {
+        // Save
+        return Handled::keep_running(state.request_save(false));
+    }
+    if pressed(&keybinds.save_as) && state.check_writable() {
+        // Save as
+        let file_name = state.file.file_name.as_ref()
+            .map(|file_name| file_name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        state.queued_input_state = Some(InputState::SaveAs(PathInput::new(file_name)));
+    }
+    if pressed(&keybinds.quit) {
         // Quit
-        return false;
+        return Handled::keep_running(state.request_quit());
     }
     
+    let file_len = state.file.bytes.len();
+    let max_rows = state.file.max_rows;
+    let appending = matches!(state.input_state, InputState::Edit { .. });
+    let pane = state.pane_mut();
+    let prev_selection = pane.selection;
+    
     match event.code {
         KeyCode::Home => {
             if event.modifiers.contains(KeyModifiers::CONTROL) {
-                state.scroll_pos = 0;
+                pane.scroll_pos = 0;
             }
             
-            if let Some((row, col)) = &mut state.selection {
+            if let Some((row, col)) = &mut pane.selection {
                 if event.modifiers.contains(KeyModifiers::CONTROL) {
                     *row = 0;
                     *col = 0;
@@ -198,78 +726,121 @@ fn handle_key_main(event: KeyEvent, keybinds: &Keybinds, state: &mut State<'_>)
         },
         KeyCode::End => {
             if event.modifiers.contains(KeyModifiers::CONTROL) {
-                state.scroll_pos = usize::max(
-                    state.scroll_pos,
-                    state.bytes.len() / 0x10 - (state.screen_height as usize - 4) + 1,
+                pane.scroll_pos = usize::max(
+                    pane.scroll_pos,
+                    max_rows.saturating_sub(pane.visible_content_rows()),
                 );
             }
             
-            if let Some((row, col)) = &mut state.selection {
+            if let Some((row, col)) = &mut pane.selection {
                 if event.modifiers.contains(KeyModifiers::CONTROL) {
-                    *row = state.bytes.len() / 0x10;
-                    *col = (state.bytes.len() % 0x10) * 2 - 1;
+                    // the last byte, even when it's possible to type past it
+                    *row = max_row(file_len, false);
+                    *col = max_col_for_row(file_len, *row, false);
                 } else {
-                    *col = 0x1f;
+                    *col = max_col_for_row(file_len, *row, appending);
                 }
                 
                 if !event.modifiers.contains(KeyModifiers::ALT) {
-                    *col -= 1;
+                    *col = *col / 2 * 2;
                 }
             }
+            
+            update_range(pane, prev_selection, event.modifiers.contains(KeyModifiers::SHIFT));
         },
         KeyCode::Esc => {
-            if state.selection.is_some() {
+            if state.file.search.is_some() {
+                // Cancel running search
+                state.file.search = None;
+                state.bottom_text = Some("Search cancelled".to_owned());
+            } else if state.file.hashing.is_some() {
+                // Cancel running hashing
+                state.file.hashing = None;
+                state.bottom_text = Some("Hashing cancelled".to_owned());
+            } else if state.pane().selection.is_some() {
                 // Go back to pager if in cursor mode
-                state.selection = None;
+                state.pane_mut().hide_cursor();
             } else {
                 // Quit if in pager mode
-                return false;
+                return Handled::keep_running(state.request_quit());
             }
         },
-        _ => {},
+        _ => return if matched.get() { Handled::Changed } else { Handled::Nothing },
     }
     
-    true
+    Handled::Changed
 }
 
-fn handle_navigation(event: KeyEvent, keybinds: &Keybinds, state: &mut State<'_>) {
+/// Moves the cursor or scrolls, returning whether the key is one that does that
+fn handle_navigation(event: KeyEvent, keybinds: &Keybinds, state: &mut State) -> bool {
+    let file_len = state.file.bytes.len();
+    let max_rows = state.file.max_rows;
+    let content_rows = state.content_rows();
+    let navigation = state.navigation;
+    let appending = matches!(state.input_state, InputState::Edit { .. });
+    let pane = state.pane_mut();
+    let prev_selection = pane.selection;
+    
+    let mut handled = false;
+    
+    if keybinds.center_cursor.matches(event) {
+        pane.center_cursor(content_rows);
+        handled = true;
+    }
+    
+    // Ctrl moves by words and several rows at once
+    if event.modifiers.contains(KeyModifiers::CONTROL) && matches!(event.code, KeyCode::Up | KeyCode::Down | KeyCode::Left | KeyCode::Right) {
+        move_by_steps(event.code, navigation, pane, file_len, max_rows, appending);
+        update_range(pane, prev_selection, event.modifiers.contains(KeyModifiers::SHIFT));
+        return true;
+    }
+    
     if event.code == KeyCode::Up || keybinds.up.matches(event) {
         // Up
-        if let Some((row, _)) = &mut state.selection {
+        handled = true;
+        if let Some((row, _)) = &mut pane.selection {
             // Move cursor up if it's not at maximum height
             *row = row.saturating_sub(1);
             
             // Scroll up if cursor goes out of bounds
-            if *row < state.scroll_pos {
-                state.scroll_pos = state.scroll_pos.saturating_sub(1);
+            if *row < pane.scroll_pos {
+                pane.scroll_pos = pane.scroll_pos.saturating_sub(1);
             }
         } else {
             // Scroll up if it's not at maximum height
-            state.scroll_pos = state.scroll_pos.saturating_sub(1);
+            pane.scroll_pos = pane.scroll_pos.saturating_sub(1);
         }
     }
     if event.code == KeyCode::Down || keybinds.down.matches(event) {
         // Down
-        if let Some((row, _)) = &mut state.selection {
+        handled = true;
+        if let Some((row, col)) = &mut pane.selection {
             // Move cursor down if it's not at maximum height
-            if *row < state.max_rows - 1 {
+            if *row < max_row(file_len, appending) {
                 *row += 1;
+                
+                // the last row might end before the column of the cursor, in which case it moves to the last byte
+                let max_col = max_col_for_row(file_len, *row, appending);
+                if *col > max_col {
+                    *col = usize::min(max_col / 2 * 2 + *col % 2, max_col);
+                }
             }
             
             // Scroll down if cursor goes out of bounds
-            if *row >= state.scroll_pos + state.visible_content_rows() {
-                state.scroll_pos += 1;
+            if *row >= pane.scroll_pos + pane.visible_content_rows() {
+                pane.scroll_pos += 1;
             }
         } else {
-            // Scroll down if it's not at maximum height
-            if state.scroll_pos < state.max_rows {
-                state.scroll_pos += 1;
+            // Scroll down until the last row is at the bottom
+            if pane.scroll_pos < pane.max_scroll_pos(max_rows) {
+                pane.scroll_pos += 1;
             }
         }
     }
     if event.code == KeyCode::Left || keybinds.left.matches(event) {
         // Left
-        if let Some((_, col)) = &mut state.selection {
+        handled = true;
+        if let Some((_, col)) = &mut pane.selection {
             if !event.modifiers.contains(KeyModifiers::ALT) {
                 // Move cursor left in byte-increments (stop at left edge)
                 *col = col.saturating_sub(2);
@@ -282,55 +853,292 @@ fn handle_navigation(event: KeyEvent, keybinds: &Keybinds, state: &mut State<'_>
     }
     if event.code == KeyCode::Right || keybinds.right.matches(event) {
         // Right
-        if let Some((_, col)) = &mut state.selection {
+        handled = true;
+        if let Some((row, col)) = &mut pane.selection {
+            let max_col = max_col_for_row(file_len, *row, appending);
+            
             if !event.modifiers.contains(KeyModifiers::ALT) {
-                // Move cursor right in byte-increments (stop at right edge)
-                if *col < 0x1e {
-                    *col += 2;
-                    *col = *col / 2 * 2;
+                // Move cursor right in byte-increments (stop at right edge or the last byte)
+                let next_col = (*col + 2) / 2 * 2;
+                if next_col <= max_col {
+                    *col = next_col;
                 }
             } else {
-                // Move cursor right in digit-increments (stop at right edge)
-                if *col < 0x1f {
+                // Move cursor right in digit-increments (stop at right edge or the last byte)
+                if *col < max_col {
                     *col += 1;
                 }
             }
         }
     }
+    
+    update_range(pane, prev_selection, event.modifiers.contains(KeyModifiers::SHIFT));
+    handled
 }
 
-fn handle_mouse(event: MouseEvent, state: &mut State<'_>) {
+/// Moves the cursor by a word or by several rows, or scrolls by several rows in pager mode
+fn move_by_steps(code: KeyCode, navigation: NavigationConfig, pane: &mut Pane, file_len: usize, max_rows: usize, appending: bool) {
+    let Some((row, col)) = pane.selection else {
+        match code {
+            KeyCode::Up => pane.scroll_pos = pane.scroll_pos.saturating_sub(navigation.row_step),
+            KeyCode::Down => pane.scroll_pos = usize::min(pane.scroll_pos + navigation.row_step, pane.max_scroll_pos(max_rows)),
+            _ => {},
+        }
+        return;
+    };
+    
+    let last_row = max_row(file_len, appending);
+    let last_offset = last_row * 0x10 + max_col_for_row(file_len, last_row, appending) / 2;
+    let offset = row * 0x10 + col / 2;
+    let word_size = usize::max(navigation.word_size, 1);
+    
+    pane.selection = Some(match code {
+        // to the start of the word, or of the previous one if already there
+        KeyCode::Left => {
+            let new_offset = offset.saturating_sub(1) / word_size * word_size;
+            (new_offset / 0x10, new_offset % 0x10 * 2)
+        },
+        KeyCode::Right => {
+            let new_offset = usize::min((offset / word_size + 1) * word_size, last_offset);
+            (new_offset / 0x10, new_offset % 0x10 * 2)
+        },
+        KeyCode::Up => (row.saturating_sub(navigation.row_step), col),
+        _ => {
+            let new_row = usize::min(row + navigation.row_step, last_row);
+            
+            // the last row might end before the column of the cursor, in which case it moves to the last byte
+            let max_col = max_col_for_row(file_len, new_row, appending);
+            (new_row, if col > max_col { usize::min(max_col / 2 * 2 + col % 2, max_col) } else { col })
+        },
+    });
+    
+    pane.scroll_to_cursor();
+}
+
+/// Extends the selected range if the cursor has been moved while holding Shift, and collapses it otherwise
+fn update_range(pane: &mut Pane, prev_selection: Option<(usize, usize)>, extend: bool) {
+    if pane.selection == prev_selection {
+        return;
+    }
+    
+    pane.anchor = match prev_selection {
+        Some((row, col)) if extend => Some(pane.anchor.unwrap_or(row * 0x10 + col / 2)),
+        _ => None,
+    };
+}
+
+/// Selects the aligned word (double click) or the row (triple click) around a clicked byte.
+/// Double clicks on the text column select the printable characters around it instead.
+fn select_around(state: &mut State, position: RowPosition, offset: usize, click_count: usize) {
+    let range = match (click_count, position) {
+        (2, RowPosition::Text(_)) => match state.printable_run_around(offset) {
+            Ok(Some(range)) => range,
+            Ok(None) => return,
+            Err(err) => {
+                state.bottom_text = Some(format!("Error: {err}"));
+                return;
+            },
+        },
+        (2, RowPosition::Hex { .. }) => {
+            let start = offset - offset % CLICK_WORD_SIZE;
+            start..start + CLICK_WORD_SIZE
+        },
+        (3, _) => {
+            let start = offset - offset % 0x10;
+            start..start + 0x10
+        },
+        _ => return,
+    };
+    
+    state.select_range(range.start..usize::min(range.end, state.file.bytes.len()));
+}
+
+fn handle_mouse(event: MouseEvent, keybinds: &Keybinds, state: &mut State) -> Handled {
+    if event.kind == MouseEventKind::Down(MouseButton::Left) {
+        // clicking a hint in the bottom bar presses its key
+        if let Some(key_event) = state.hint_areas.key_at(event.column, event.row) {
+            return handle_input(Event::Key(key_event), keybinds, state);
+        }
+        
+        if state.input_state.is_prompt() {
+            return handle_input(Event::Key(KeyEvent::from(KeyCode::Esc)), keybinds, state);
+        }
+    }
+    
     match state.input_state {
         InputState::Regular | InputState::Edit { .. } => {},
-        _ => return,
+        _ => return Handled::Nothing,
     }
     
-    match event.kind {
-        MouseEventKind::Down(MouseButton::Left) => {
-            let mut row = (event.row as usize).saturating_sub(2);
-            if row >= state.visible_content_rows() {
-                row = state.visible_content_rows() - 1;
-            }
-            
-            if event.column >= 0x27 {
-                let raw_col = (event.column as usize).saturating_sub(0x27);
-                let mut col = raw_col / 3 * 2;
-                if event.modifiers.contains(KeyModifiers::ALT) {
-                    col += raw_col % 3;
-                }
-                if col >= 0x10 {
-                    col = 0xf;
-                }
-                state.selection = Some((row + state.scroll_pos, col + 0x10));
-            } else {
-                let raw_col = (event.column as usize).saturating_sub(0xe);
-                let mut col = raw_col / 3 * 2;
-                if event.modifiers.contains(KeyModifiers::ALT) {
-                    col += raw_col % 3;
-                }
-                state.selection = Some((row + state.scroll_pos, col));
-            }
-        },
-        _ => {},
+    // only the left button does anything
+    let dragging = event.kind == MouseEventKind::Drag(MouseButton::Left);
+    if event.kind != MouseEventKind::Down(MouseButton::Left) && !dragging {
+        return Handled::Nothing;
+    }
+    
+    // clicks on the addresses or between the columns don't select anything
+    let Some(position) = row_position_at(state.margin_horizontal, event.column) else {
+        return Handled::Nothing;
+    };
+    
+    // focus the pane that has been clicked on
+    if !dragging && let Some(pane_idx) = state.file.panes.iter().position(|pane| event.row < pane.y + pane.height) {
+        state.file.focused_pane = pane_idx;
+    }
+    
+    let pane = state.pane_mut();
+    let prev_selection = pane.selection;
+    
+    let mut row = (event.row as usize).saturating_sub(pane.y as usize);
+    if row >= pane.visible_content_rows() {
+        row = pane.visible_content_rows().saturating_sub(1);
+    }
+    
+    let col = match position {
+        // Alt selects the digit that has been clicked on instead of the whole byte
+        RowPosition::Hex { byte, digit } if event.modifiers.contains(KeyModifiers::ALT) => byte * 2 + digit,
+        RowPosition::Hex { byte, .. } | RowPosition::Text(byte) => byte * 2,
+    };
+    pane.selection = Some((row + pane.scroll_pos, col));
+    
+    // clicks below the end of the file or after the last byte select the last byte
+    state.clamp_to_content();
+    
+    // dragging or clicking with Shift held selects everything from where the cursor was
+    update_range(state.pane_mut(), prev_selection, dragging || event.modifiers.contains(KeyModifiers::SHIFT));
+    
+    if !dragging && !event.modifiers.contains(KeyModifiers::SHIFT) {
+        let count = match state.last_click {
+            Some(last) if last.column == event.column && last.row == event.row && last.time.elapsed() < MULTI_CLICK_TIME => usize::min(last.count + 1, 3),
+            _ => 1,
+        };
+        state.last_click = Some(Click { time: Instant::now(), column: event.column, row: event.row, count });
+        
+        if let Some(offset) = state.pane().cursor_offset() && offset < state.file.bytes.len() {
+            select_around(state, position, offset, count);
+        }
+    }
+    
+    Handled::Changed
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    
+    use crossterm::event::{KeyEventKind, KeyEventState};
+    
+    use super::*;
+    use crate::{OpenFile, buffer::InMemoryBuffer, cfg::Config};
+    
+    fn state() -> State {
+        let file = OpenFile::new(None, Box::new(InMemoryBuffer(Arc::new(vec![0; 0x100]))));
+        let mut state = State::new(&Config::default_config(), vec![file], false, false);
+        state.pane_mut().height = 8;
+        state
+    }
+    
+    fn key(code: KeyCode) -> Event {
+        Event::Key(KeyEvent::new(code, KeyModifiers::NONE))
+    }
+    
+    /// Mouse event on the first byte of the row
+    fn mouse(kind: MouseEventKind, state: &State) -> Event {
+        let column = (0..u16::MAX)
+            .find(|&column| matches!(row_position_at(state.margin_horizontal, column), Some(RowPosition::Hex { byte: 0, digit: 0 })))
+            .unwrap();
+        
+        Event::Mouse(MouseEvent { kind, column, row: 5, modifiers: KeyModifiers::NONE })
+    }
+    
+    #[test]
+    fn unbound_keys_change_nothing() {
+        let keybinds = Config::default_config().keybinds;
+        let mut state = state();
+        
+        assert_eq!(handle_input(key(KeyCode::Char('9')), &keybinds, &mut state), Handled::Nothing);
+        assert_eq!(handle_input(key(KeyCode::F(7)), &keybinds, &mut state), Handled::Nothing);
+        
+        let release = KeyEvent { kind: KeyEventKind::Release, state: KeyEventState::NONE, ..KeyEvent::from(KeyCode::Char('9')) };
+        assert_eq!(handle_input(Event::Key(release), &keybinds, &mut state), Handled::Nothing);
+    }
+    
+    #[test]
+    fn unbound_keys_clear_the_message() {
+        let keybinds = Config::default_config().keybinds;
+        let mut state = state();
+        state.bottom_text = Some("Saved".to_owned());
+        
+        assert_eq!(handle_input(key(KeyCode::Char('9')), &keybinds, &mut state), Handled::Changed);
+        assert_eq!(state.bottom_text, None);
+        assert_eq!(handle_input(key(KeyCode::Char('9')), &keybinds, &mut state), Handled::Nothing);
+    }
+    
+    #[test]
+    fn prompts_take_every_key() {
+        let keybinds = Config::default_config().keybinds;
+        let mut state = state();
+        state.input_state = InputState::Goto(PromptBuffer::default());
+        
+        assert_eq!(handle_input(key(KeyCode::Char('9')), &keybinds, &mut state), Handled::Changed);
+        assert_eq!(handle_input(key(KeyCode::F(7)), &keybinds, &mut state), Handled::Changed);
+    }
+    
+    #[test]
+    fn bound_and_builtin_keys_change_something() {
+        let keybinds = Config::default_config().keybinds;
+        let mut state = state();
+        state.pane_mut().selection = Some((2, 4));
+        
+        assert_eq!(handle_input(Event::Key(keybinds.go_to.key_event()), &keybinds, &mut state), Handled::Changed);
+        assert_eq!(handle_input(Event::Key(keybinds.toggle_entropy.key_event()), &keybinds, &mut state), Handled::Changed);
+        assert!(state.show_entropy);
+        
+        for code in [KeyCode::Up, KeyCode::Down, KeyCode::Left, KeyCode::Right, KeyCode::Home, KeyCode::End, KeyCode::Esc] {
+            assert_eq!(handle_input(key(code), &keybinds, &mut state), Handled::Changed, "{code:?}");
+        }
+    }
+    
+    #[test]
+    fn quitting() {
+        let keybinds = Config::default_config().keybinds;
+        let mut state = state();
+        
+        assert_eq!(handle_input(Event::Key(keybinds.quit.key_event()), &keybinds, &mut state), Handled::Quit);
+        
+        let ctrl_c = KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL);
+        assert_eq!(handle_input(Event::Key(ctrl_c), &keybinds, &mut state), Handled::Quit);
+        
+        // with the cursor hidden, Esc quits as well
+        assert_eq!(handle_input(key(KeyCode::Esc), &keybinds, &mut state), Handled::Quit);
+    }
+    
+    #[test]
+    fn only_left_clicks_and_drags_change_something() {
+        let keybinds = Config::default_config().keybinds;
+        let mut state = state();
+        
+        for kind in [MouseEventKind::Moved, MouseEventKind::ScrollDown, MouseEventKind::Up(MouseButton::Left), MouseEventKind::Down(MouseButton::Right)] {
+            let event = mouse(kind, &state);
+            assert_eq!(handle_input(event, &keybinds, &mut state), Handled::Nothing, "{kind:?}");
+        }
+        assert_eq!(state.pane().selection, None);
+        
+        let event = mouse(MouseEventKind::Down(MouseButton::Left), &state);
+        assert_eq!(handle_input(event, &keybinds, &mut state), Handled::Changed);
+        assert_eq!(state.pane().selection, Some((5, 0)));
+        
+        let event = mouse(MouseEventKind::Drag(MouseButton::Left), &state);
+        assert_eq!(handle_input(event, &keybinds, &mut state), Handled::Changed);
+    }
+    
+    #[test]
+    fn resizing_changes_something() {
+        let keybinds = Config::default_config().keybinds;
+        let mut state = state();
+        
+        assert_eq!(handle_input(Event::Resize(80, 24), &keybinds, &mut state), Handled::Changed);
+        assert_eq!(handle_input(Event::FocusGained, &keybinds, &mut state), Handled::Nothing);
+        assert_eq!(handle_input(Event::Paste("text".to_owned()), &keybinds, &mut state), Handled::Nothing);
     }
 }
@@ -0,0 +1,288 @@
+use anyhow::{Result, bail};
+
+use crate::{buffer::FileBuffer, patch::Record};
+
+const HEADER: &[u8] = b"PATCH";
+const FOOTER: &[u8] = b"EOF";
+
+/// Largest offset a record can start at, since offsets are stored in 3 bytes
+const MAX_OFFSET: usize = 0xFF_FFFF;
+const MAX_RECORD_SIZE: usize = 0xFFFF;
+
+/// Offset that would be confused with the footer when written as a record offset
+const FOOTER_OFFSET: usize = 0x45_4F46;
+
+/// Encodes the current values of all `modified` offsets as an IPS patch,
+/// combining adjacent offsets into one record.
+/// Returns the patch and the number of records in it.
+pub fn encode(buffer: &mut dyn FileBuffer, modified: impl IntoIterator<Item = usize>) -> Result<(Vec<u8>, usize)> {
+    let mut patch = HEADER.to_vec();
+    let mut record_count = 0;
+    
+    for (start, end) in coalesce(modified) {
+        // records are limited in size, so split long runs
+        let mut record_start = start;
+        while record_start < end {
+            // a record at this offset would be read as the end of the patch, so start one byte earlier
+            if record_start == FOOTER_OFFSET {
+                record_start -= 1;
+            }
+            
+            let record_end = usize::min(record_start + MAX_RECORD_SIZE, end);
+            
+            if record_start > MAX_OFFSET {
+                bail!("Offset 0x{record_start:x} is too large for an IPS patch, which can only patch the first 16 MiB");
+            }
+            
+            patch.extend_from_slice(&(record_start as u32).to_be_bytes()[1..]);
+            patch.extend_from_slice(&((record_end - record_start) as u16).to_be_bytes());
+            patch.extend_from_slice(buffer.read(record_start..record_end)?);
+            record_count += 1;
+            
+            record_start = record_end;
+        }
+    }
+    
+    patch.extend_from_slice(FOOTER);
+    Ok((patch, record_count))
+}
+
+pub fn is_ips(data: &[u8]) -> bool {
+    data.starts_with(HEADER)
+}
+
+/// Reads all records of an IPS patch, failing if any of them is malformed
+pub fn decode(data: &[u8]) -> Result<Vec<Record>> {
+    let Some(mut rest) = data.strip_prefix(HEADER) else {
+        bail!("Not an IPS patch");
+    };
+    
+    let mut records = Vec::new();
+    
+    loop {
+        let position = data.len() - rest.len();
+        
+        let Some((offset, after_offset)) = rest.split_first_chunk::<3>() else {
+            bail!("Patch ends at 0x{position:x} without an EOF marker");
+        };
+        
+        if offset == FOOTER {
+            // anything after the footer (like the truncation extension) isn't supported
+            break;
+        }
+        
+        let offset = u32::from_be_bytes([0, offset[0], offset[1], offset[2]]) as usize;
+        
+        let Some((size, after_size)) = after_offset.split_first_chunk::<2>() else {
+            bail!("Record at 0x{position:x} is cut off");
+        };
+        
+        let size = u16::from_be_bytes(*size) as usize;
+        
+        let (data, after_record) = if size == 0 {
+            // run-length encoded record, which repeats a single byte
+            let Some((&[len_high, len_low, value], after_record)) = after_size.split_first_chunk::<3>() else {
+                bail!("Record at 0x{position:x} is cut off");
+            };
+            
+            (vec![value; u16::from_be_bytes([len_high, len_low]) as usize], after_record)
+        } else {
+            if after_size.len() < size {
+                bail!("Record at 0x{position:x} is cut off");
+            }
+            
+            let (data, after_record) = after_size.split_at(size);
+            (data.to_vec(), after_record)
+        };
+        
+        records.push(Record { offset, data, original: None });
+        rest = after_record;
+    }
+    
+    Ok(records)
+}
+
+/// Turns a sorted list of offsets into ranges of adjacent offsets
+fn coalesce(offsets: impl IntoIterator<Item = usize>) -> Vec<(usize, usize)> {
+    let mut runs: Vec<(usize, usize)> = Vec::new();
+    
+    for offset in offsets {
+        match runs.last_mut() {
+            Some((_, end)) if *end == offset => *end += 1,
+            _ => runs.push((offset, offset + 1)),
+        }
+    }
+    
+    runs
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    
+    use super::*;
+    use crate::buffer::InMemoryBuffer;
+    
+    fn buffer(data: Vec<u8>) -> InMemoryBuffer {
+        InMemoryBuffer(Arc::new(data))
+    }
+    
+    /// Applies an IPS patch the straightforward way, independently of [`decode`]
+    fn reference_apply(patch: &[u8], target: &mut Vec<u8>) {
+        assert!(patch.starts_with(b"PATCH"));
+        let mut pos = 5;
+        
+        while &patch[pos..pos + 3] != b"EOF" {
+            let offset = (patch[pos] as usize) << 16 | (patch[pos + 1] as usize) << 8 | patch[pos + 2] as usize;
+            let size = (patch[pos + 3] as usize) << 8 | patch[pos + 4] as usize;
+            pos += 5;
+            
+            let data = if size == 0 {
+                let len = (patch[pos] as usize) << 8 | patch[pos + 1] as usize;
+                pos += 3;
+                vec![patch[pos - 1]; len]
+            } else {
+                pos += size;
+                patch[pos - size..pos].to_vec()
+            };
+            
+            if target.len() < offset + data.len() {
+                target.resize(offset + data.len(), 0);
+            }
+            target[offset..offset + data.len()].copy_from_slice(&data);
+        }
+        
+        assert_eq!(pos + 3, patch.len(), "nothing should follow the footer");
+    }
+    
+    /// Encodes the `modified` offsets of `edited`, checks that applying the patch to `original` gives `edited`
+    /// with both [`decode`] and the reference, and returns the decoded records
+    fn round_trip(original: &[u8], edited: Vec<u8>, modified: &[usize]) -> Vec<Record> {
+        let (patch, record_count) = encode(&mut buffer(edited.clone()), modified.iter().copied()).unwrap();
+        
+        let mut reference = original.to_vec();
+        reference_apply(&patch, &mut reference);
+        assert_eq!(reference, edited);
+        
+        let records = decode(&patch).unwrap();
+        assert_eq!(records.len(), record_count);
+        
+        let mut decoded = original.to_vec();
+        for record in &records {
+            decoded[record.offset..record.offset + record.data.len()].copy_from_slice(&record.data);
+        }
+        assert_eq!(decoded, edited);
+        
+        records
+    }
+    
+    #[test]
+    fn empty_patch_is_only_header_and_footer() {
+        let (patch, record_count) = encode(&mut buffer(vec![1, 2, 3]), []).unwrap();
+        
+        assert_eq!(patch, b"PATCHEOF");
+        assert_eq!(record_count, 0);
+        assert!(decode(&patch).unwrap().is_empty());
+    }
+    
+    #[test]
+    fn adjacent_offsets_are_coalesced() {
+        let original = vec![0; 0x20];
+        let mut edited = original.clone();
+        for offset in [2, 3, 4, 9, 0x1f] {
+            edited[offset] = 0xaa;
+        }
+        
+        let records = round_trip(&original, edited, &[2, 3, 4, 9, 0x1f]);
+        
+        let ranges: Vec<_> = records.iter().map(|record| (record.offset, record.data.len())).collect();
+        assert_eq!(ranges, [(2, 3), (9, 1), (0x1f, 1)]);
+    }
+    
+    #[test]
+    fn long_runs_are_split() {
+        let len = MAX_RECORD_SIZE + 5;
+        let original = vec![0; len];
+        let edited: Vec<u8> = (0..len).map(|i| i as u8 | 1).collect();
+        let modified: Vec<usize> = (0..len).collect();
+        
+        let records = round_trip(&original, edited, &modified);
+        
+        let ranges: Vec<_> = records.iter().map(|record| (record.offset, record.data.len())).collect();
+        assert_eq!(ranges, [(0, MAX_RECORD_SIZE), (MAX_RECORD_SIZE, 5)]);
+    }
+    
+    #[test]
+    fn record_never_starts_at_footer_offset() {
+        let original = vec![0; FOOTER_OFFSET + 0x10];
+        let mut edited = original.clone();
+        edited[FOOTER_OFFSET] = 1;
+        
+        let records = round_trip(&original, edited, &[FOOTER_OFFSET]);
+        
+        assert_eq!(records[0].offset, FOOTER_OFFSET - 1);
+        assert_eq!(records[0].data, [0, 1]);
+    }
+    
+    #[test]
+    fn split_record_never_starts_at_footer_offset() {
+        // the second record of this run would start right at the footer offset
+        let start = FOOTER_OFFSET - MAX_RECORD_SIZE;
+        let end = FOOTER_OFFSET + 0x10;
+        
+        let original = vec![0; end + 0x10];
+        let mut edited = original.clone();
+        for byte in &mut edited[start..end] {
+            *byte = 0x5a;
+        }
+        let modified: Vec<usize> = (start..end).collect();
+        
+        let records = round_trip(&original, edited, &modified);
+        
+        assert_eq!(records.len(), 2);
+        assert!(records.iter().all(|record| record.offset != FOOTER_OFFSET));
+        assert_eq!(records[1].offset, FOOTER_OFFSET - 1);
+    }
+    
+    #[test]
+    fn offsets_past_16_mib_are_an_error() {
+        let mut buffer = buffer(vec![0; MAX_OFFSET + 2]);
+        
+        assert!(encode(&mut buffer, [MAX_OFFSET]).is_ok());
+        assert!(encode(&mut buffer, [MAX_OFFSET + 1]).is_err());
+    }
+    
+    #[test]
+    fn rle_records_are_expanded() {
+        let mut patch = b"PATCH".to_vec();
+        patch.extend_from_slice(&[0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x04, 0xcc]);
+        patch.extend_from_slice(&[0x00, 0x00, 0x10, 0x00, 0x02, 0x12, 0x34]);
+        patch.extend_from_slice(b"EOF");
+        
+        let records = decode(&patch).unwrap();
+        
+        assert_eq!(records.len(), 2);
+        assert_eq!((records[0].offset, records[0].data.as_slice()), (0x100, &[0xcc; 4][..]));
+        assert_eq!((records[1].offset, records[1].data.as_slice()), (0x10, &[0x12, 0x34][..]));
+    }
+    
+    #[test]
+    fn truncated_patches_are_rejected() {
+        let mut patch = b"PATCH".to_vec();
+        patch.extend_from_slice(&[0x00, 0x00, 0x10, 0x00, 0x02, 0x12, 0x34]);
+        patch.extend_from_slice(&[0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x04, 0xcc]);
+        patch.extend_from_slice(b"EOF");
+        assert!(decode(&patch).is_ok());
+        
+        // every cut before the end of the footer leaves something incomplete
+        for len in 0..patch.len() {
+            assert!(decode(&patch[..len]).is_err(), "cut off after {len} bytes");
+        }
+    }
+    
+    #[test]
+    fn wrong_header_is_rejected() {
+        assert!(!is_ips(b"PATCx"));
+        assert!(decode(b"PATCXEOF").is_err());
+    }
+}
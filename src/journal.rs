@@ -0,0 +1,184 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{BufWriter, ErrorKind, Write},
+    path::{self, Path, PathBuf},
+};
+
+use anyhow::{Result, anyhow, bail};
+use sha1::{Digest, Sha1};
+
+use crate::{buffer::FileBuffer, session::data_dir};
+
+/// Start of every journal, followed by the version of the format
+const MAGIC: &[u8] = b"LESBINJ";
+const VERSION: u8 = 1;
+const HEADER_SIZE: usize = MAGIC.len() + 1;
+
+/// Offset as a little endian u64, the old value and the new value
+const RECORD_SIZE: usize = 10;
+
+/// Change of a single byte, as recorded in the journal
+#[derive(Debug, Clone, Copy)]
+pub struct Edit {
+    pub offset: usize,
+    pub old: u8,
+    pub new: u8,
+}
+
+/// Log of the unsaved edits of a file, written as they happen so they can be restored after a crash.
+/// Every edit is written to disk right away, so nothing is lost if lesbin gets killed.
+#[derive(Debug)]
+pub struct Journal {
+    file: File,
+}
+
+impl Journal {
+    /// Starts a new journal for the file at `file_path`, replacing any old one
+    pub fn create(file_path: &Path) -> Result<Self> {
+        let path = journal_path(file_path)?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        
+        let mut file = File::create(path)?;
+        file.write_all(MAGIC)?;
+        file.write_all(&[VERSION])?;
+        
+        Ok(Self { file })
+    }
+    
+    /// Writes the edits to the end of the journal, all at once so large batches stay fast
+    pub fn record(&mut self, edits: impl IntoIterator<Item = Edit>) -> Result<()> {
+        let mut writer = BufWriter::new(&mut self.file);
+        
+        for edit in edits {
+            let mut record = [0; RECORD_SIZE];
+            record[..8].copy_from_slice(&(edit.offset as u64).to_le_bytes());
+            record[8] = edit.old;
+            record[9] = edit.new;
+            
+            writer.write_all(&record)?;
+        }
+        
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Reads the edits left behind for the file at `file_path` by a session that didn't end cleanly.
+/// Returns None if there is no journal. A record that has only been written partially is dropped.
+pub fn load(file_path: &Path) -> Result<Option<Vec<Edit>>> {
+    let content = match fs::read(journal_path(file_path)?) {
+        Ok(content) => content,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err.into()),
+    };
+    
+    if !content.starts_with(MAGIC) || content.len() < HEADER_SIZE {
+        bail!("not a lesbin journal");
+    }
+    if content[MAGIC.len()] != VERSION {
+        bail!("unsupported journal version {}", content[MAGIC.len()]);
+    }
+    
+    let edits = content[HEADER_SIZE..].chunks_exact(RECORD_SIZE)
+        .map(|record| Edit {
+            offset: u64::from_le_bytes(record[..8].try_into().expect("Length should match")) as usize,
+            old: record[8],
+            new: record[9],
+        })
+        .collect();
+    
+    Ok(Some(edits))
+}
+
+/// Checks that `edits` have been made to the data in `buffer`, so replaying them doesn't overwrite anything else.
+/// Every edit has to replace the value the file has at its offset, or the value an earlier edit has written there.
+pub fn check_edits(buffer: &mut dyn FileBuffer, edits: &[Edit]) -> Result<()> {
+    let mut written = HashMap::new();
+    let mut len = buffer.len();
+    
+    for edit in edits {
+        let current = match written.get(&edit.offset) {
+            Some(&value) => value,
+            None if edit.offset < buffer.len() => buffer.read_byte(edit.offset)?,
+            // the file only ever grows by one byte at a time, which starts out as zero
+            None if edit.offset == len => {
+                len += 1;
+                0
+            },
+            None => bail!("the edit at 0x{:x} is past the end of the file", edit.offset),
+        };
+        
+        if current != edit.old {
+            bail!("the byte at 0x{:x} is 0x{current:02x} instead of 0x{:02x}, so the file has changed since", edit.offset, edit.old);
+        }
+        
+        written.insert(edit.offset, edit.new);
+    }
+    
+    Ok(())
+}
+
+/// Deletes the journal of the file at `file_path`, once its edits have been saved or discarded
+pub fn delete(file_path: &Path) -> Result<()> {
+    match fs::remove_file(journal_path(file_path)?) {
+        Err(err) if err.kind() != ErrorKind::NotFound => Err(err.into()),
+        _ => Ok(()),
+    }
+}
+
+/// Journals are named after the hash of the file's full path, since they all live in the same directory
+fn journal_path(file_path: &Path) -> Result<PathBuf> {
+    // files that don't exist yet can't be canonicalized
+    let full_path = fs::canonicalize(file_path).or_else(|_| path::absolute(file_path))?;
+    let name = hex::encode(Sha1::digest(full_path.as_os_str().as_encoded_bytes()));
+    
+    let data_dir = data_dir().ok_or_else(|| anyhow!("Could not find the data directory"))?;
+    Ok(data_dir.join("journals").join(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    
+    use super::*;
+    use crate::buffer::InMemoryBuffer;
+    
+    fn edit(offset: usize, old: u8, new: u8) -> Edit {
+        Edit { offset, old, new }
+    }
+    
+    #[test]
+    fn edits_of_unchanged_file_pass() {
+        let mut buffer = InMemoryBuffer(Arc::new(vec![1, 2, 3]));
+        
+        // the second edit of the same byte replaces what the first one has written
+        let edits = [edit(0, 1, 5), edit(0, 5, 6), edit(2, 3, 0)];
+        assert!(check_edits(&mut buffer, &edits).is_ok());
+    }
+    
+    #[test]
+    fn appended_bytes_start_out_as_zero() {
+        let mut buffer = InMemoryBuffer(Arc::new(vec![1]));
+        
+        let edits = [edit(1, 0, 0), edit(1, 0, 7), edit(2, 0, 0), edit(2, 0, 8)];
+        assert!(check_edits(&mut buffer, &edits).is_ok());
+    }
+    
+    #[test]
+    fn changed_file_is_rejected() {
+        let mut buffer = InMemoryBuffer(Arc::new(vec![1, 2, 3]));
+        
+        assert!(check_edits(&mut buffer, &[edit(1, 9, 4)]).is_err());
+        assert!(check_edits(&mut buffer, &[edit(0, 1, 5), edit(0, 1, 6)]).is_err());
+    }
+    
+    #[test]
+    fn edits_past_the_end_are_rejected() {
+        let mut buffer = InMemoryBuffer(Arc::new(vec![1, 2, 3]));
+        
+        assert!(check_edits(&mut buffer, &[edit(4, 0, 1)]).is_err());
+    }
+}
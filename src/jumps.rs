@@ -0,0 +1,147 @@
+use std::{collections::VecDeque, iter};
+
+/// Most offsets that are remembered, the oldest ones get dropped first
+const MAX_JUMPS: usize = 100;
+
+/// Offsets the cursor has jumped away from, to get back to them like with Ctrl+O in vim
+#[derive(Debug, Default)]
+pub struct JumpList {
+    /// Offsets to go back to, the most recent one last
+    back: VecDeque<usize>,
+    /// Offsets that have been gone back from, the most recent one last
+    forward: Vec<usize>,
+}
+
+impl JumpList {
+    /// Remembers the offset that is being jumped away from.
+    /// Jumping somewhere new forgets about the offsets that could be gone forward to.
+    pub fn push(&mut self, offset: usize) {
+        self.forward.clear();
+        self.push_back(offset);
+    }
+    
+    /// Returns the offset to go back to from `current`, which can be gone forward to again afterwards.
+    /// Offsets that are the same as `current` are skipped, since going back to them wouldn't do anything.
+    pub fn back(&mut self, current: usize) -> Option<usize> {
+        let offset = iter::from_fn(|| self.back.pop_back()).find(|&offset| offset != current)?;
+        self.forward.push(current);
+        Some(offset)
+    }
+    
+    /// Returns the offset that has last been gone back from, undoing [`JumpList::back`]
+    pub fn forward(&mut self, current: usize) -> Option<usize> {
+        let offset = iter::from_fn(|| self.forward.pop()).find(|&offset| offset != current)?;
+        self.push_back(current);
+        Some(offset)
+    }
+    
+    /// Amount of offsets that can still be gone back to
+    pub fn back_len(&self) -> usize {
+        self.back.len()
+    }
+    
+    /// Amount of offsets that can still be gone forward to
+    pub fn forward_len(&self) -> usize {
+        self.forward.len()
+    }
+    
+    /// Adds an offset to go back to, unless it's the same as the most recent one
+    fn push_back(&mut self, offset: usize) {
+        if self.back.back() == Some(&offset) {
+            return;
+        }
+        
+        self.back.push_back(offset);
+        if self.back.len() > MAX_JUMPS {
+            self.back.pop_front();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn oldest_jumps_get_dropped() {
+        let mut jumps = JumpList::default();
+        for offset in 0..MAX_JUMPS + 10 {
+            jumps.push(offset);
+        }
+        assert_eq!(jumps.back_len(), MAX_JUMPS);
+        
+        let mut offsets = Vec::new();
+        while let Some(offset) = jumps.back(usize::MAX) {
+            offsets.push(offset);
+        }
+        assert_eq!(offsets.first(), Some(&(MAX_JUMPS + 9)));
+        assert_eq!(offsets.last(), Some(&10));
+    }
+    
+    #[test]
+    fn repeated_offsets_are_only_remembered_once() {
+        let mut jumps = JumpList::default();
+        jumps.push(5);
+        jumps.push(5);
+        jumps.push(8);
+        jumps.push(5);
+        
+        assert_eq!(jumps.back_len(), 3);
+    }
+    
+    #[test]
+    fn back_skips_the_current_offset() {
+        let mut jumps = JumpList::default();
+        jumps.push(1);
+        jumps.push(2);
+        
+        assert_eq!(jumps.back(2), Some(1));
+        assert_eq!(jumps.back(1), None);
+        assert_eq!(jumps.back_len(), 0);
+    }
+    
+    #[test]
+    fn forward_undoes_back() {
+        let mut jumps = JumpList::default();
+        jumps.push(1);
+        jumps.push(2);
+        
+        assert_eq!(jumps.back(3), Some(2));
+        assert_eq!(jumps.back(2), Some(1));
+        assert_eq!(jumps.forward_len(), 2);
+        
+        assert_eq!(jumps.forward(1), Some(2));
+        assert_eq!(jumps.forward(2), Some(3));
+        assert_eq!(jumps.forward(3), None);
+        
+        assert_eq!(jumps.back(3), Some(2));
+        assert_eq!(jumps.back(2), Some(1));
+    }
+    
+    #[test]
+    fn forward_doesnt_remember_an_offset_twice() {
+        let mut jumps = JumpList::default();
+        jumps.push(1);
+        jumps.push(2);
+        assert_eq!(jumps.back(3), Some(2));
+        
+        // moved back to 1 by hand, which is already the most recent offset to go back to
+        assert_eq!(jumps.forward(1), Some(3));
+        assert_eq!(jumps.back_len(), 1);
+        assert_eq!(jumps.back(3), Some(1));
+    }
+    
+    #[test]
+    fn jumping_somewhere_new_clears_forward() {
+        let mut jumps = JumpList::default();
+        jumps.push(1);
+        jumps.push(2);
+        jumps.back(3);
+        assert_eq!(jumps.forward_len(), 1);
+        
+        jumps.push(7);
+        assert_eq!(jumps.forward_len(), 0);
+        assert_eq!(jumps.forward(7), None);
+        assert_eq!(jumps.back(8), Some(7));
+    }
+}
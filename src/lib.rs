@@ -0,0 +1,1856 @@
+use std::{
+    borrow::Cow,
+    collections::BTreeMap,
+    env,
+    fs,
+    io::{self, ErrorKind, IsTerminal, Read, Write, stdout},
+    iter,
+    mem,
+    ops::Range,
+    path::{Path, PathBuf},
+    process::exit,
+    sync::Arc,
+    time::{Duration, Instant, SystemTime},
+};
+
+use anyhow::{Result, bail};
+use crossterm::{
+    event::{self, DisableMouseCapture}, execute, queue, terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode}
+};
+
+use crate::{args::{Command, Options, USAGE}, buffer::{FileBuffer, InMemoryBuffer}, cfg::{Config, NavigationConfig, StringsConfig}, complete::PathInput, decode::{Interpretation, MAX_DECODE_LEN}, diff::Comparison, dump::DumpOptions, encoding::TextEncoding, entropy::EntropyCache, hash::{Digests, HashJob}, histogram::Histogram, input::{Click, Handled, handle_input}, journal::{Edit, Journal}, jumps::JumpList, magic::{SIGNATURE_AREA, Signature}, patch::Record, prompt::PromptBuffer, search::{MatchList, MatchesPanel, Search}, sections::SectionsPanel, session::{FilePosition, SessionStore}, strings::StringsPanel, theme::ColorMode, ui::{HintAreas, PADDING_BOTTOM, PADDING_TOP, draw}};
+
+mod args;
+mod buffer;
+mod cfg;
+mod complete;
+mod decode;
+mod diff;
+mod dump;
+pub mod encoding;
+mod entropy;
+mod hash;
+mod histogram;
+mod input;
+mod ips;
+mod journal;
+mod jumps;
+mod magic;
+mod patch;
+mod prompt;
+mod recent;
+mod search;
+mod sections;
+mod session;
+mod strings;
+mod theme;
+mod ui;
+pub mod util;
+
+pub use ui::format_row;
+
+const ENABLE_MOUSE_CAPTURE: &[u8] = b"\x1B[?1000h\x1B[?1002h";
+
+/// How often the file gets checked for new data in follow mode
+const FOLLOW_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// How often the progress of a background search gets updated
+const SEARCH_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Most strings that get listed, so files full of text don't take forever to scan
+const MAX_STRINGS: usize = 10_000;
+
+/// Furthest a double click on the text column looks for the end of a string in each direction
+const MAX_RUN_SELECTION: usize = 0x1000;
+
+/// Runs lesbin with the arguments it has been started with, see [`USAGE`]
+pub fn main() -> Result<()> {
+    // Parse args
+    let options = match args::parse(env::args_os().skip(1), io::stdout().is_terminal()) {
+        Ok(Command::Run(options)) => options,
+        Ok(Command::Help) => {
+            println!("{USAGE}");
+            return Ok(());
+        },
+        Ok(Command::Version) => {
+            println!("lesbin {}", env!("CARGO_PKG_VERSION"));
+            return Ok(());
+        },
+        Err(err) => {
+            eprintln!("Error: {err}");
+            exit(1);
+        },
+    };
+    
+    let Options { mut input_files, compare_file, patch_file, config_file, goto, readonly, create_new, force_mmap, no_session, dump_mode, dump_options } = options;
+    
+    let (config, config_warnings) = Config::load(config_file.as_deref());
+    
+    let color_mode = config.appearance.color_mode.unwrap_or_else(ColorMode::detect);
+    theme::init(config.theme.build().degrade(color_mode));
+    
+    if input_files.is_empty() && !io::stdin().is_terminal() {
+        input_files.push(PathBuf::from("-"));
+    }
+    
+    let mut session = (config.session.restore_position && !no_session).then(SessionStore::load);
+    
+    // without a path, offer the files that have been opened before
+    if input_files.is_empty() && !dump_mode && io::stdout().is_terminal()
+        && let Some(session) = &mut session && !session.is_empty()
+    {
+        match recent::pick(&config, session)? {
+            Some(path) => input_files.push(path),
+            None => return Ok(()),
+        }
+    }
+    
+    if input_files.is_empty() {
+        eprintln!("Error: No input file has been passed\n\n{USAGE}");
+        exit(1);
+    }
+    
+    let mut files = Vec::new();
+    let mut warnings = config_warnings;
+    
+    for input_file in input_files {
+        if input_file.as_os_str() == "-" {
+            // the TUI still works with the data coming through a pipe,
+            // because crossterm reads input from /dev/tty when stdin isn't a terminal
+            let mut input_bytes = Vec::new();
+            io::stdin().read_to_end(&mut input_bytes)?;
+            
+            files.push(OpenFile::new(None, Box::new(InMemoryBuffer(Arc::new(input_bytes)))));
+            continue;
+        }
+        
+        // Open input file
+        match buffer::open(&input_file, force_mmap) {
+            Ok((input_buffer, open_warning)) => {
+                files.push(OpenFile::new(Some(input_file), input_buffer));
+                warnings.extend(open_warning);
+            },
+            Err(err) => match err.kind() {
+                // the file gets created on the first save
+                ErrorKind::NotFound if create_new => {
+                    files.push(OpenFile::new(Some(input_file), Box::new(InMemoryBuffer(Arc::default()))));
+                },
+                ErrorKind::NotFound => {
+                    eprintln!("Error: Could not find file '{}' (use --new to create it)", input_file.display());
+                    exit(1);
+                },
+                ErrorKind::IsADirectory => {
+                    eprintln!("Error: Could not find file '{}'", input_file.display());
+                    exit(1);
+                },
+                _ => return Err(err.into()),
+            },
+        }
+    }
+    
+    // Print the file without starting the TUI if requested or if the output isn't going to a terminal
+    if dump_mode || !io::stdout().is_terminal() {
+        if files.len() > 1 {
+            eprintln!("Error: Only one file can be dumped at a time");
+            exit(1);
+        }
+        
+        for warning in &warnings {
+            eprintln!("{warning}");
+        }
+        
+        let dump_options = DumpOptions {
+            text_encoding: config.appearance.text_encoding,
+            control_pictures: config.appearance.control_pictures,
+            ..dump_options
+        };
+        if let Err(err) = dump::dump(&mut *files[0].bytes, &dump_options, &mut stdout().lock()) {
+            eprintln!("Error: {err}");
+            exit(1);
+        }
+        
+        return Ok(());
+    }
+    
+    if let Some(session) = &session {
+        for file in &mut files {
+            if let Some(position) = file.file_name.as_deref().and_then(|file_name| session.get(file_name)) {
+                file.restore_position(position);
+            }
+        }
+    }
+    
+    // Add panic hook to disable mouse capture
+    let hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        if let Err(err) = execute!(stdout(), DisableMouseCapture, LeaveAlternateScreen) {
+            eprintln!("Error: {err:?}");
+        }
+        
+        if let Err(err) = disable_raw_mode() {
+            eprintln!("Error: {err:?}");
+        }
+        
+        hook(info);
+    }));
+    
+    // Run TUI
+    let mut state = State::new(&config, files, force_mmap, readonly);
+    if !warnings.is_empty() {
+        state.bottom_text = Some(warnings.join(", "));
+    }
+    
+    if let Some(compare_file) = compare_file {
+        match Comparison::open(compare_file.clone(), &mut *state.file.bytes) {
+            Ok(comparison) => state.file.comparison = Some(comparison),
+            Err(err) => {
+                eprintln!("Error: Could not open '{}' for comparing: {err}", compare_file.display());
+                exit(1);
+            },
+        }
+    }
+    
+    if let Some(patch_file) = patch_file {
+        match patch::load(&patch_file, &mut *state.file.bytes) {
+            Ok(records) => state.apply_patch(records),
+            Err(err) => {
+                eprintln!("Error: Could not read patch '{}': {err}", patch_file.display());
+                exit(1);
+            },
+        }
+        
+        // the patch might need confirmation before the first key press
+        if let Some(queued_input_state) = state.queued_input_state.take() {
+            state.input_state = queued_input_state;
+        }
+    }
+    
+    // the patch asks its question first if it needs to
+    if matches!(state.input_state, InputState::Regular) {
+        state.prompt_recovery();
+        if let Some(queued_input_state) = state.queued_input_state.take() {
+            state.input_state = queued_input_state;
+        }
+    }
+    
+    if let Some(offset) = goto {
+        if offset >= state.file.bytes.len() {
+            eprintln!("Error: Offset 0x{offset:x} is past the end of '{}'", state.display_name());
+            exit(1);
+        }
+        
+        state.place_cursor(offset);
+    }
+    
+    enable_raw_mode()?;
+    
+    let mut stdout = stdout();
+    queue!(stdout, EnterAlternateScreen)?;
+    // crossterm's builtin solution also enables mouse move events, which i don't need
+    stdout.write_all(ENABLE_MOUSE_CAPTURE)?;
+    stdout.flush()?;
+    
+    let result = run(&config, &mut state);
+    let result2 = execute!(stdout, DisableMouseCapture, LeaveAlternateScreen);
+    disable_raw_mode()?;
+    
+    if let Err(err) = result2 {
+        eprintln!("Error: {err:?}");
+    }
+    
+    if result.is_ok() {
+        state.discard_finished_journals();
+    }
+    
+    // only remember the positions after a clean exit
+    if let Some(session) = &mut session && result.is_ok() {
+        for file in state.files() {
+            if let Some(file_name) = &file.file_name {
+                let pane = &file.panes[file.focused_pane];
+                session.update(file_name, pane.scroll_pos, pane.cursor_offset(), pane.anchor);
+            }
+        }
+        
+        if let Err(err) = session.save() {
+            eprintln!("Warning: Could not save the position in the file: {err}");
+        }
+    }
+    
+    result
+}
+
+#[derive(Debug)]
+enum InputState {
+    Regular,
+    Edit { prev_in_pager: bool },
+    Goto(PromptBuffer),
+    Find,
+    FindBytes(PromptBuffer),
+    FindString(PromptBuffer),
+    UnsavedChanges(PendingAction),
+    ExternallyModified { quit_after_save: bool },
+    SaveAs(PathInput),
+    ExportPatch(PathInput),
+    /// Switches to the open file with the given number or name
+    SwitchFile(PromptBuffer),
+    ApplyPatch(PathInput),
+    /// Asks whether a patch going past the end of the file should grow it
+    PatchPastEnd(Vec<Record>),
+    /// Shows how often each byte value occurs, with one of the values selected
+    Histogram { selected: u8 },
+    /// Lists the strings in the file
+    Strings(StringsPanel),
+    /// Lists the sections of an executable to jump to
+    Sections(SectionsPanel),
+    /// Lists all matches of the last search below the hex view
+    Matches(MatchesPanel),
+    /// Shows the hashes of a range of the file, with one of them selected for copying
+    Hashes { digests: Digests, selected: usize },
+    /// Shows the ways the selected range (of which only the start may have been decoded) could be read
+    Decoded { range: Range<usize>, interpretations: Vec<Interpretation>, selected: usize },
+    /// Asks whether the edits left behind by a previous session should be restored
+    RecoverEdits,
+    /// Lists the edits left behind by a previous session, scrolled down by some amount
+    RecoveredEdits { scroll: usize },
+}
+
+impl InputState {
+    /// Whether the bottom bar is asking something, which clicking anywhere else cancels like Esc
+    fn is_prompt(&self) -> bool {
+        matches!(self, Self::Goto(_) | Self::Find | Self::FindBytes(_) | Self::FindString(_)
+            | Self::UnsavedChanges(_) | Self::ExternallyModified { .. } | Self::SaveAs(_)
+            | Self::ExportPatch(_) | Self::SwitchFile(_) | Self::ApplyPatch(_) | Self::PatchPastEnd(_))
+    }
+}
+
+/// Action that is waiting for the user to decide what happens to unsaved changes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PendingAction {
+    Quit,
+    Reload,
+}
+
+/// Modification time and size of a file on disk, used to notice when
+/// another program has changed the file since it was loaded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct DiskMetadata {
+    modified: Option<SystemTime>,
+    len: u64,
+}
+
+impl DiskMetadata {
+    fn read(path: &Path) -> io::Result<Self> {
+        let metadata = fs::metadata(path)?;
+        
+        Ok(Self {
+            modified: metadata.modified().ok(),
+            len: metadata.len(),
+        })
+    }
+}
+
+/// Scrollable view of the file. The screen is either showing one pane or two of them above each other.
+#[derive(Debug, Clone, Default)]
+struct Pane {
+    scroll_pos: usize,
+    selection: Option<(usize, usize)>,
+    /// Offset where the selected range starts, with the cursor being the other end of it
+    anchor: Option<usize>,
+    /// Where the cursor was before switching to pager mode, to bring it back there when switching back
+    last_selection: Option<(usize, usize)>,
+    
+    /// Screen row of the first line of content, updated when drawing
+    y: u16,
+    /// Amount of rows of content that fit into the pane, updated when drawing
+    height: u16,
+}
+
+impl Pane {
+    fn visible_content_rows(&self) -> usize {
+        self.height as usize
+    }
+    
+    /// Offset of the byte under the cursor
+    fn cursor_offset(&self) -> Option<usize> {
+        self.selection.map(|(row, col)| row * 0x10 + col / 2)
+    }
+    
+    /// Bytes from the anchor to the cursor (including both), if more than one byte is selected
+    fn selected_range(&self, file_len: usize) -> Option<Range<usize>> {
+        let (anchor, cursor) = (self.anchor?, self.cursor_offset()?);
+        
+        // the cursor can be right after the last byte when typing past the end
+        let range = usize::min(anchor, cursor)..usize::min(usize::max(anchor, cursor) + 1, file_len);
+        (range.len() > 1).then_some(range)
+    }
+    
+    /// Switches to pager mode, remembering where the cursor was
+    fn hide_cursor(&mut self) {
+        if self.selection.is_some() {
+            self.last_selection = self.selection.take();
+        }
+        self.anchor = None;
+    }
+    
+    /// Switches to cursor mode, with the cursor back where it was before switching to pager mode
+    /// (scrolling there if needed) as long as that's still within the file.
+    /// Otherwise, the cursor starts at the top of the view.
+    fn show_cursor(&mut self, file_len: usize) {
+        let last_selection = self.last_selection.take()
+            .filter(|&(row, col)| row * 0x10 + col / 2 < file_len);
+        
+        match last_selection {
+            Some(selection) => {
+                self.selection = Some(selection);
+                self.scroll_to_cursor();
+            },
+            None => self.selection = Some((self.scroll_pos, 0)),
+        }
+        self.anchor = None;
+    }
+    
+    /// Scrolls as little as possible to make the row of the cursor visible
+    fn scroll_to_cursor(&mut self) {
+        let Some((row, _)) = self.selection else {
+            return;
+        };
+        
+        if row < self.scroll_pos {
+            self.scroll_pos = row;
+        } else if row >= self.scroll_pos + self.visible_content_rows() {
+            self.scroll_pos = (row + 1).saturating_sub(self.visible_content_rows());
+        }
+    }
+    
+    /// Scrolls so that the row of the cursor is in the middle of the pane, as far as the content allows
+    fn center_cursor(&mut self, content_rows: usize) {
+        if let Some((row, _)) = self.selection {
+            let scroll_pos = row.saturating_sub(self.visible_content_rows() / 2);
+            self.scroll_pos = usize::min(scroll_pos, self.max_scroll_pos(content_rows));
+        }
+    }
+    
+    /// Furthest the pane can be scrolled down, which puts the last row at the bottom.
+    /// Content shorter than the pane can't be scrolled at all.
+    fn max_scroll_pos(&self, content_rows: usize) -> usize {
+        content_rows.saturating_sub(self.visible_content_rows())
+    }
+}
+
+/// Number of hex digits the cursor can be on, which is every digit of the file.
+/// When `appending`, the cursor can also be on the first digit after the end, since typing there makes the file longer.
+fn selectable_digits(file_len: usize, appending: bool) -> usize {
+    file_len * 2 + appending as usize
+}
+
+/// Last row the cursor can be on
+fn max_row(file_len: usize, appending: bool) -> usize {
+    selectable_digits(file_len, appending).saturating_sub(1) / 0x20
+}
+
+/// Last column (in hex digits) the cursor can be on in a row, which is only less than 0x1f on the last row
+fn max_col_for_row(file_len: usize, row: usize, appending: bool) -> usize {
+    let row_digits = selectable_digits(file_len, appending).saturating_sub(row * 0x20);
+    row_digits.clamp(1, 0x20) - 1
+}
+
+/// Everything belonging to one of the open files
+struct OpenFile {
+    /// None if the data has been read from stdin
+    file_name: Option<PathBuf>,
+    bytes: Box<dyn FileBuffer>,
+    max_rows: usize,
+    
+    panes: Vec<Pane>,
+    focused_pane: usize,
+    /// Offsets the cursor has jumped away from, for going back to them
+    jumps: JumpList,
+    
+    /// Original values of all bytes that have been edited since the last save, by offset
+    modified_bytes: BTreeMap<usize, u8>,
+    disk_metadata: Option<DiskMetadata>,
+    following: bool,
+    /// Set once the user has agreed to quit without saving this file
+    discard_on_quit: bool,
+    
+    search: Option<Search>,
+    /// Needle and position of the last search result, for finding the next one
+    last_match: Option<(Vec<u8>, usize)>,
+    /// All matches of the last search, once they have been listed
+    matches: Option<MatchList>,
+    hashing: Option<HashJob>,
+    
+    comparison: Option<Comparison>,
+    entropy: EntropyCache,
+    /// Byte value counts of the range that has been looked at last
+    histogram: Option<Histogram>,
+    /// Format recognized from the first bytes of the file
+    file_type: Option<&'static Signature>,
+    
+    /// Record of the unsaved edits for restoring them after a crash, started on the first edit
+    journal: Option<Journal>,
+    /// Edits left behind by a previous session that didn't end cleanly, until the user decides what to do with them
+    recovered_edits: Option<Vec<Edit>>,
+}
+
+impl OpenFile {
+    fn new(file_name: Option<PathBuf>, bytes: Box<dyn FileBuffer>) -> Self {
+        let disk_metadata = file_name.as_deref().and_then(|file_name| DiskMetadata::read(file_name).ok());
+        
+        // a journal that can't be read is of no use, and gets replaced on the next edit
+        let recovered_edits = file_name.as_deref()
+            .and_then(|file_name| journal::load(file_name).ok().flatten())
+            .filter(|edits| !edits.is_empty());
+        
+        let mut file = Self {
+            file_name,
+            max_rows: bytes.len().div_ceil(16),
+            bytes,
+            panes: vec![Pane::default()],
+            focused_pane: 0,
+            jumps: JumpList::default(),
+            modified_bytes: BTreeMap::new(),
+            disk_metadata,
+            following: false,
+            discard_on_quit: false,
+            search: None,
+            last_match: None,
+            matches: None,
+            hashing: None,
+            comparison: None,
+            entropy: EntropyCache::default(),
+            histogram: None,
+            file_type: None,
+            journal: None,
+            recovered_edits,
+        };
+        
+        file.detect_file_type();
+        file
+    }
+    
+    /// Goes back to where the file was left at last time, as far as it still fits into the file
+    fn restore_position(&mut self, position: &FilePosition) {
+        let file_len = self.bytes.len();
+        let pane = &mut self.panes[0];
+        
+        pane.scroll_pos = usize::min(position.scroll_pos, self.max_rows.saturating_sub(1));
+        if file_len == 0 {
+            return;
+        }
+        
+        pane.selection = position.cursor.map(|offset| {
+            let offset = usize::min(offset, file_len - 1);
+            (offset / 0x10, (offset % 0x10) * 2)
+        });
+        pane.anchor = position.anchor.filter(|_| pane.selection.is_some()).map(|anchor| usize::min(anchor, file_len - 1));
+    }
+    
+    /// Records edits in the journal, starting a new one on the first edit.
+    /// Nothing gets recorded while the edits of a previous session haven't been restored yet, so their journal stays intact.
+    fn journal_edits(&mut self, edits: impl IntoIterator<Item = Edit>) -> Result<()> {
+        let Some(file_name) = &self.file_name else {
+            return Ok(());
+        };
+        if self.recovered_edits.is_some() {
+            return Ok(());
+        }
+        
+        let journal = match &mut self.journal {
+            Some(journal) => journal,
+            None => self.journal.insert(Journal::create(file_name)?),
+        };
+        journal.record(edits)
+    }
+    
+    /// Deletes the journal after the edits have been saved or discarded
+    fn discard_journal(&mut self) {
+        if self.journal.take().is_some() && let Some(file_name) = &self.file_name {
+            // a journal that is left over only means being asked about it the next time
+            let _ = journal::delete(file_name);
+        }
+    }
+    
+    /// Marks the listed matches as possibly outdated after the data has changed
+    fn mark_matches_stale(&mut self) {
+        if let Some(matches) = &mut self.matches {
+            matches.stale = true;
+        }
+    }
+    
+    /// Recognizes the format of the file again, after its first bytes have changed
+    fn detect_file_type(&mut self) {
+        // a file that can't be read has bigger problems than not being recognized
+        self.file_type = magic::detect(&mut *self.bytes).ok().flatten();
+    }
+    
+    /// Name for showing to the user, which isn't necessarily the exact path if it's not valid UTF-8
+    fn display_name(&self) -> Cow<'_, str> {
+        match &self.file_name {
+            Some(file_name) => file_name.to_string_lossy(),
+            None => Cow::Borrowed("(stdin)"),
+        }
+    }
+}
+
+struct State {
+    /// The file that is currently shown
+    file: OpenFile,
+    /// All other open files, in the order they were opened in except for the active one
+    other_files: Vec<OpenFile>,
+    /// Position of the active file among all open files
+    active_file: usize,
+    
+    input_state: InputState,
+    queued_input_state: Option<InputState>,
+    
+    total_vertical_padding: u16,
+    /// Columns left empty on the left of the hex view
+    margin_horizontal: u16,
+    force_mmap: bool,
+    /// Set if the files may only be viewed
+    readonly: bool,
+    /// Whether the addresses are colored by the entropy of the data
+    show_entropy: bool,
+    text_encoding: TextEncoding,
+    /// Whether control characters are shown as symbols in the text column
+    control_pictures: bool,
+    strings_config: StringsConfig,
+    navigation: NavigationConfig,
+    
+    bottom_text: Option<String>,
+    /// Hints in the bottom bar that can be clicked, as drawn in the last frame
+    hint_areas: HintAreas,
+    last_click: Option<Click>,
+}
+
+impl State {
+    fn new(config: &Config, mut files: Vec<OpenFile>, force_mmap: bool, readonly: bool) -> Self {
+        let file = files.remove(0);
+        
+        Self {
+            file,
+            other_files: files,
+            active_file: 0,
+            input_state: InputState::Regular,
+            queued_input_state: None,
+            total_vertical_padding: config.appearance.margin_vertical * 2 + PADDING_BOTTOM + PADDING_TOP,
+            margin_horizontal: config.appearance.margin_horizontal,
+            force_mmap,
+            readonly,
+            show_entropy: false,
+            text_encoding: config.appearance.text_encoding,
+            control_pictures: config.appearance.control_pictures,
+            strings_config: config.strings,
+            navigation: config.navigation,
+            bottom_text: None,
+            hint_areas: HintAreas::default(),
+            last_click: None,
+        }
+    }
+    
+    /// Tells the user that the files can't be changed if they have been opened read-only.
+    /// Returns whether changing them is allowed.
+    fn check_writable(&mut self) -> bool {
+        if self.readonly {
+            self.bottom_text = Some("The file has been opened read-only".to_owned());
+        }
+        
+        !self.readonly
+    }
+    
+    fn file_count(&self) -> usize {
+        self.other_files.len() + 1
+    }
+    
+    /// Iterates over all open files in order, including the active one
+    fn files(&self) -> impl Iterator<Item = &OpenFile> {
+        let (before, after) = self.other_files.split_at(self.active_file);
+        before.iter().chain([&self.file]).chain(after)
+    }
+    
+    /// Makes the file at `index` (among all open files) the active one
+    fn switch_to_file(&mut self, index: usize) {
+        if index == self.active_file || index >= self.file_count() {
+            return;
+        }
+        
+        let next_file = self.other_files.remove(if index < self.active_file { index } else { index - 1 });
+        let prev_file = mem::replace(&mut self.file, next_file);
+        
+        self.other_files.insert(if self.active_file < index { self.active_file } else { self.active_file - 1 }, prev_file);
+        self.active_file = index;
+        self.prompt_recovery();
+    }
+    
+    fn cycle_file(&mut self, forward: bool) {
+        let count = self.file_count();
+        
+        if forward {
+            self.switch_to_file((self.active_file + 1) % count);
+        } else {
+            self.switch_to_file((self.active_file + count - 1) % count);
+        }
+    }
+    
+    fn commit_input_state(&mut self) {
+        match &mut self.input_state {
+            InputState::Goto(goto_buffer) => {
+                let Ok(goto_offset) = usize::from_str_radix(goto_buffer.text(), 16) else {
+                    return;
+                };
+                
+                if goto_offset >= self.file.bytes.len() {
+                    return;
+                }
+                
+                self.jump_to(goto_offset);
+                self.queued_input_state = Some(InputState::Regular);
+            },
+            InputState::FindBytes(needle_string) => {
+                let Ok(needle) = hex::decode(needle_string.text()) else {
+                    return;
+                };
+                
+                if needle.is_empty() {
+                    return;
+                }
+                
+                self.start_search(needle, 0);
+                self.queued_input_state = Some(InputState::Regular);
+            },
+            InputState::FindString(needle_string) => {
+                if needle_string.is_empty() {
+                    return;
+                }
+                
+                let needle = needle_string.text().as_bytes().to_vec();
+                self.start_search(needle, 0);
+                self.queued_input_state = Some(InputState::Regular);
+            },
+            InputState::SaveAs(path_input) => {
+                let path = path_input.path();
+                if path.is_empty() {
+                    return;
+                }
+                
+                self.bottom_text = Some(match self.save_file_as(PathBuf::from(path)) {
+                    Ok(()) => format!("Saved as '{}'", self.display_name()),
+                    Err(err) => format!("Error: {err}"),
+                });
+                self.queued_input_state = Some(InputState::Regular);
+            },
+            InputState::ExportPatch(path_input) => {
+                let path = path_input.path();
+                if path.is_empty() {
+                    return;
+                }
+                
+                self.bottom_text = Some(match patch::export(Path::new(&path), &mut *self.file.bytes, &self.file.modified_bytes) {
+                    Ok(record_count) => format!("Wrote {record_count} records to '{path}'"),
+                    Err(err) => format!("Error: {err}"),
+                });
+                self.queued_input_state = Some(InputState::Regular);
+            },
+            InputState::ApplyPatch(path_input) => {
+                let path = path_input.path();
+                if path.is_empty() {
+                    return;
+                }
+                
+                self.queued_input_state = Some(InputState::Regular);
+                match patch::load(Path::new(&path), &mut *self.file.bytes) {
+                    Ok(records) => self.apply_patch(records),
+                    Err(err) => self.bottom_text = Some(format!("Error: Could not read patch '{path}': {err}")),
+                }
+            },
+            InputState::SwitchFile(query) => {
+                let query = query.text().to_owned();
+                
+                let index = match query.parse::<usize>() {
+                    Ok(number) if (1..=self.file_count()).contains(&number) => Some(number - 1),
+                    // prefer exact matches, since one name might be part of another
+                    _ => self.files().position(|file| *file.display_name() == query)
+                        .or_else(|| self.files().position(|file| file.display_name().contains(&query))),
+                };
+                
+                // switching might ask about the edits of a previous session right away
+                self.queued_input_state = Some(InputState::Regular);
+                match index {
+                    Some(index) => self.switch_to_file(index),
+                    None => self.bottom_text = Some(format!("No open file matches '{query}'")),
+                }
+            },
+            _ => panic!("State {:?} cannot be committed", self.input_state),
+        }
+    }
+    
+    /// Changes a byte and keeps track of its original value.
+    /// Returns whether the byte is different from before.
+    fn write_byte(&mut self, offset: usize, value: u8) -> Result<bool> {
+        let prev_byte = self.file.bytes.read_byte(offset)?;
+        if prev_byte == value {
+            return Ok(false);
+        }
+        
+        self.file.bytes.write_byte(offset, value)?;
+        self.file.entropy.invalidate(offset);
+        self.file.histogram = None;
+        self.file.mark_matches_stale();
+        if offset < SIGNATURE_AREA {
+            self.file.detect_file_type();
+        }
+        
+        if let Some(comparison) = &mut self.file.comparison {
+            comparison.update_count(offset, prev_byte, value)?;
+        }
+        
+        // remember the original value, and forget about the change once it's been undone by hand
+        let original_byte = *self.file.modified_bytes.entry(offset).or_insert(prev_byte);
+        if original_byte == value {
+            self.file.modified_bytes.remove(&offset);
+        }
+        
+        self.journal_edits([Edit { offset, old: prev_byte, new: value }]);
+        Ok(true)
+    }
+    
+    /// Adds a zero byte to the end of the file, so bytes can be typed past the end
+    fn append_byte(&mut self) -> Result<()> {
+        let offset = self.file.bytes.len();
+        self.file.bytes.extend(offset + 1)?;
+        self.file.max_rows = self.file.bytes.len().div_ceil(16);
+        self.file.entropy.invalidate(offset);
+        self.file.histogram = None;
+        self.file.mark_matches_stale();
+        if offset < SIGNATURE_AREA {
+            self.file.detect_file_type();
+        }
+        
+        if let Some(comparison) = &mut self.file.comparison {
+            comparison.byte_appended(offset, 0)?;
+        }
+        
+        // the appended byte counts as a change even if it stays zero
+        self.file.modified_bytes.insert(offset, 0);
+        self.journal_edits([Edit { offset, old: 0, new: 0 }]);
+        Ok(())
+    }
+    
+    /// Records edits of the active file for restoring them after a crash
+    fn journal_edits(&mut self, edits: impl IntoIterator<Item = Edit>) {
+        if let Err(err) = self.file.journal_edits(edits) {
+            self.bottom_text = Some(format!("Warning: Could not record the edit for crash recovery: {err}"));
+        }
+    }
+    
+    /// Asks what to do with the edits left behind by a previous session, if the active file has any
+    fn prompt_recovery(&mut self) {
+        // the journal stays around for when the file is opened to be edited again
+        if self.file.recovered_edits.is_some() && !self.readonly {
+            self.queued_input_state = Some(InputState::RecoverEdits);
+        }
+    }
+    
+    /// Replays the edits left behind by a previous session, which become unsaved changes again.
+    /// Nothing gets replayed if the file has been changed since, which keeps asking what to do with them.
+    fn apply_recovered_edits(&mut self) {
+        let Some(edits) = self.file.recovered_edits.take() else {
+            return;
+        };
+        
+        if let Err(err) = journal::check_edits(&mut *self.file.bytes, &edits) {
+            self.bottom_text = Some(format!("Error: Cannot restore the edits, {err}"));
+            self.file.recovered_edits = Some(edits);
+            self.queued_input_state = Some(InputState::RecoverEdits);
+            return;
+        }
+        
+        for edit in &edits {
+            let result = if edit.offset == self.file.bytes.len() { self.append_byte() } else { Ok(()) };
+            if let Err(err) = result.and_then(|()| self.write_byte(edit.offset, edit.new)) {
+                self.bottom_text = Some(format!("Error: Edits were only restored partially: {err}"));
+                return;
+            }
+        }
+        
+        if let Some(first_offset) = edits.iter().map(|edit| edit.offset).min() {
+            self.jump_to(first_offset);
+        }
+        
+        self.bottom_text = Some(format!("Restored {} edits, {} bytes changed", edits.len(), self.file.modified_bytes.len()));
+    }
+    
+    /// Forgets about the edits left behind by a previous session for good
+    fn discard_recovered_edits(&mut self) {
+        if self.file.recovered_edits.take().is_some() && let Some(file_name) = &self.file.file_name {
+            self.bottom_text = match journal::delete(file_name) {
+                Ok(()) => Some("Discarded the edits from the previous session".to_owned()),
+                Err(err) => Some(format!("Error: Could not delete the journal: {err}")),
+            };
+        }
+    }
+    
+    /// Applies a patch, unless it goes past the end of the file,
+    /// in which case the user gets asked whether to grow the file first
+    fn apply_patch(&mut self, records: Vec<Record>) {
+        if patch::end(&records) > self.file.bytes.len() {
+            self.queued_input_state = Some(InputState::PatchPastEnd(records));
+        } else {
+            self.write_patch(records);
+        }
+    }
+    
+    /// Writes all records of a patch into the buffer, growing it if necessary
+    fn write_patch(&mut self, records: Vec<Record>) {
+        let prev_len = self.file.bytes.len();
+        let patch_end = patch::end(&records);
+        
+        if patch_end > prev_len {
+            if let Err(err) = self.file.bytes.extend(patch_end) {
+                self.bottom_text = Some(format!("Error: Could not apply patch: {err}"));
+                return;
+            }
+            
+            self.file.max_rows = self.file.bytes.len().div_ceil(16);
+            self.file.entropy.invalidate_from(prev_len);
+            self.file.histogram = None;
+            self.recount_differences();
+            
+            // the appended bytes count as changes even if the patch leaves them at zero,
+            // and get journaled like bytes appended by hand so the edits after them can be restored
+            self.file.modified_bytes.extend((prev_len..patch_end).map(|offset| (offset, 0)));
+            self.journal_edits((prev_len..patch_end).map(|offset| Edit { offset, old: 0, new: 0 }));
+        }
+        
+        let mut changed_bytes = 0;
+        for record in &records {
+            for (i, &value) in record.data.iter().enumerate() {
+                match self.write_byte(record.offset + i, value) {
+                    Ok(changed) => changed_bytes += changed as usize,
+                    Err(err) => {
+                        self.bottom_text = Some(format!("Error: Patch was only applied partially: {err}"));
+                        return;
+                    },
+                }
+            }
+        }
+        
+        if let Some(first_offset) = records.iter().map(|record| record.offset).min() {
+            self.jump_to(first_offset);
+        }
+        
+        self.bottom_text = Some(format!("Applied {} records, {changed_bytes} bytes changed", records.len()));
+    }
+    
+    /// Jumps to the next or previous run of bytes that differ from the compared file,
+    /// starting from the cursor (or the top of the screen in pager mode)
+    fn jump_to_difference(&mut self, forward: bool) {
+        let Some(comparison) = &mut self.file.comparison else {
+            self.bottom_text = Some("Not comparing with another file, pass a second file to do so".to_owned());
+            return;
+        };
+        
+        let pane = &self.file.panes[self.file.focused_pane];
+        let start = match pane.selection {
+            Some((row, col)) => row * 0x10 + col / 2,
+            None => pane.scroll_pos * 0x10,
+        };
+        
+        let result = if forward {
+            comparison.next_difference(&mut *self.file.bytes, start)
+        } else {
+            comparison.prev_difference(&mut *self.file.bytes, start)
+        };
+        
+        match result {
+            Ok(Some(offset)) => self.jump_to(offset),
+            Ok(None) => self.bottom_text = Some("No more differences found".to_owned()),
+            Err(err) => self.bottom_text = Some(format!("Error: {err}")),
+        }
+    }
+    
+    /// Jumps to the next or previous unsaved change, starting from the cursor (or the top of the screen in pager mode).
+    /// Wraps around at either end of the file.
+    fn jump_to_change(&mut self, forward: bool) {
+        let modified_bytes = &self.file.modified_bytes;
+        let (Some((&first, _)), Some((&last, _))) = (modified_bytes.first_key_value(), modified_bytes.last_key_value()) else {
+            self.bottom_text = Some("No changes".to_owned());
+            return;
+        };
+        
+        let pane = self.pane();
+        let found = match (pane.cursor_offset(), forward) {
+            (Some(offset), true) => modified_bytes.range(offset + 1..).next(),
+            (None, true) => modified_bytes.range(pane.scroll_pos * 0x10..).next(),
+            (Some(offset), false) => modified_bytes.range(..offset).next_back(),
+            (None, false) => modified_bytes.range(..pane.scroll_pos * 0x10).next_back(),
+        };
+        
+        match found {
+            Some((&offset, _)) => self.jump_to(offset),
+            None if forward => {
+                self.jump_to(first);
+                self.bottom_text = Some("Wrapped around to the first change".to_owned());
+            },
+            None => {
+                self.jump_to(last);
+                self.bottom_text = Some("Wrapped around to the last change".to_owned());
+            },
+        }
+    }
+    
+    /// Jumps to the closest byte after or before the cursor (or the top of the screen in pager mode)
+    /// that isn't `value`, which defaults to the byte under the cursor. This skips over runs of padding.
+    fn skip_run(&mut self, forward: bool, value: Option<u8>) {
+        let pane = self.pane();
+        let start = pane.cursor_offset().unwrap_or(pane.scroll_pos * 0x10);
+        
+        let value = match value {
+            Some(value) => value,
+            None if start < self.file.bytes.len() => match self.file.bytes.read_byte(start) {
+                Ok(value) => value,
+                Err(err) => {
+                    self.bottom_text = Some(format!("Error: {err}"));
+                    return;
+                },
+            },
+            None => {
+                self.bottom_text = Some("Reached the end of the file".to_owned());
+                return;
+            },
+        };
+        
+        let result = if forward {
+            buffer::find_other_than(&mut *self.file.bytes, value, start + 1)
+        } else {
+            buffer::rfind_other_than(&mut *self.file.bytes, value, start)
+        };
+        
+        match result {
+            Ok(Some(offset)) => self.jump_to(offset),
+            Ok(None) if forward => self.bottom_text = Some("Reached the end of the file".to_owned()),
+            Ok(None) => self.bottom_text = Some("Reached the start of the file".to_owned()),
+            Err(err) => self.bottom_text = Some(format!("Error: {err}")),
+        }
+    }
+    
+    /// Shows the histogram of the selected range, or of the whole file if nothing is selected.
+    /// The counts are kept until the range or the data changes.
+    fn open_histogram(&mut self) {
+        let file_len = self.file.bytes.len();
+        let range = self.pane().selected_range(file_len).unwrap_or(0..file_len);
+        
+        if self.file.histogram.as_ref().is_none_or(|histogram| histogram.range != range) {
+            match Histogram::count(&mut *self.file.bytes, range) {
+                Ok(histogram) => self.file.histogram = Some(histogram),
+                Err(err) => {
+                    self.bottom_text = Some(format!("Error: {err}"));
+                    return;
+                },
+            }
+        }
+        
+        let selected = self.file.histogram.as_ref().map_or(0, Histogram::most_frequent);
+        self.queued_input_state = Some(InputState::Histogram { selected });
+    }
+    
+    /// Jumps to the first occurrence of `value` in the range the histogram has been counted for
+    fn jump_to_value(&mut self, value: u8) {
+        let Some(histogram) = &self.file.histogram else {
+            return;
+        };
+        let range = histogram.range.clone();
+        
+        match buffer::find(&mut *self.file.bytes, &[value], range.start, |_| true) {
+            Ok(Some(offset)) if offset < range.end => {
+                self.queued_input_state = Some(InputState::Regular);
+                self.jump_to(offset);
+            },
+            Ok(_) => self.bottom_text = Some(format!("0x{value:02x} does not occur here")),
+            Err(err) => self.bottom_text = Some(format!("Error: {err}")),
+        }
+    }
+    
+    /// Tries to decode the selection in several ways, up to [`MAX_DECODE_LEN`] bytes of it
+    fn decode_selection(&mut self) {
+        let Some(range) = self.pane().selected_range(self.file.bytes.len()) else {
+            self.bottom_text = Some("Select the bytes to decode first".to_owned());
+            return;
+        };
+        
+        let decoded_end = usize::min(range.end, range.start + MAX_DECODE_LEN);
+        match self.file.bytes.read(range.start..decoded_end) {
+            Ok(bytes) => {
+                let interpretations = decode::interpret(bytes);
+                self.queued_input_state = Some(InputState::Decoded { range, interpretations, selected: 0 });
+            },
+            Err(err) => self.bottom_text = Some(format!("Error: {err}")),
+        }
+    }
+    
+    /// Lists the sections of the file if it's an ELF or PE executable
+    fn open_sections(&mut self) {
+        match sections::parse(&mut *self.file.bytes) {
+            Ok(sections) if sections.is_empty() => self.bottom_text = Some("The file has no sections".to_owned()),
+            Ok(sections) => self.queued_input_state = Some(InputState::Sections(SectionsPanel::new(sections))),
+            Err(err) => self.bottom_text = Some(format!("Couldn't parse sections: {err}")),
+        }
+    }
+    
+    /// Lists the strings in the file, up to [`MAX_STRINGS`]
+    fn open_strings(&mut self) {
+        let StringsConfig { min_length, utf16 } = self.strings_config;
+        
+        match strings::extract(&mut *self.file.bytes, min_length, utf16, MAX_STRINGS) {
+            Ok(list) if list.strings.is_empty() => self.bottom_text = Some("No strings found".to_owned()),
+            Ok(list) => self.queued_input_state = Some(InputState::Strings(StringsPanel::new(list))),
+            Err(err) => self.bottom_text = Some(format!("Error: {err}")),
+        }
+    }
+    
+    /// Counts the bytes differing from the compared file again after the buffer has changed as a whole
+    fn recount_differences(&mut self) {
+        if let Some(comparison) = &mut self.file.comparison
+            && let Err(err) = comparison.recount(&mut *self.file.bytes)
+        {
+            self.bottom_text = Some(format!("Error: Could not compare files: {err}"));
+        }
+    }
+    
+    /// Jumps to where the signature of the recognized file format starts
+    fn jump_to_signature(&mut self) {
+        match self.file.file_type {
+            Some(signature) => self.jump_to(signature.offset),
+            None => self.bottom_text = Some("The file type has not been recognized".to_owned()),
+        }
+    }
+    
+    /// Scrolls to an offset and places the cursor on it, remembering where it was to go back there later
+    fn jump_to(&mut self, offset: usize) {
+        let current = self.current_offset();
+        self.file.jumps.push(current);
+        self.place_cursor(offset);
+    }
+    
+    /// Goes back to where the cursor was before the last jump, or forward again after going back
+    fn jump_in_history(&mut self, forward: bool) {
+        let current = self.current_offset();
+        let jumps = &mut self.file.jumps;
+        
+        let (offset, remaining) = if forward {
+            (jumps.forward(current), jumps.forward_len())
+        } else {
+            (jumps.back(current), jumps.back_len())
+        };
+        
+        let Some(offset) = offset else {
+            self.bottom_text = Some(if forward { "No jumps to go forward to" } else { "No jumps to go back to" }.to_owned());
+            return;
+        };
+        
+        // the file might have shrunk since
+        let offset = usize::min(offset, self.file.bytes.len().saturating_sub(1));
+        self.place_cursor(offset);
+        
+        let direction = if forward { "Forward" } else { "Back" };
+        self.bottom_text = Some(format!("{direction} to 0x{offset:x} ({remaining} more)"));
+    }
+    
+    /// Offset of the cursor, or of the top of the screen in pager mode
+    fn current_offset(&self) -> usize {
+        let pane = self.pane();
+        pane.cursor_offset().unwrap_or(pane.scroll_pos * 0x10)
+    }
+    
+    /// Scrolls to an offset and places the cursor on it.
+    /// This takes precedence over where the cursor was before switching to pager mode, which gets forgotten.
+    fn place_cursor(&mut self, offset: usize) {
+        let pane = self.pane_mut();
+        pane.last_selection = None;
+        pane.scroll_pos = offset / 0x10;
+        pane.selection = Some((offset / 0x10, (offset % 0x10) * 2));
+        pane.anchor = None;
+    }
+    
+    /// Selects the bytes in `range` with the cursor on the last one
+    fn select_range(&mut self, range: Range<usize>) {
+        let last = range.end - 1;
+        let pane = self.pane_mut();
+        pane.selection = Some((last / 0x10, (last % 0x10) * 2));
+        pane.anchor = Some(range.start);
+    }
+    
+    /// Finds the printable ASCII characters around `offset`, like a string embedded in binary data.
+    /// Returns None if the byte at `offset` isn't printable.
+    fn printable_run_around(&mut self, offset: usize) -> Result<Option<Range<usize>>> {
+        let window_start = offset.saturating_sub(MAX_RUN_SELECTION);
+        let window_end = usize::min(offset + MAX_RUN_SELECTION, self.file.bytes.len());
+        let window = self.file.bytes.read(window_start..window_end)?;
+        
+        let is_printable = |byte: &u8| byte.is_ascii_graphic() || *byte == b' ';
+        let position = offset - window_start;
+        
+        if !is_printable(&window[position]) {
+            return Ok(None);
+        }
+        
+        let start = window[..position].iter().rposition(|byte| !is_printable(byte)).map_or(0, |i| i + 1);
+        let end = window[position..].iter().position(|byte| !is_printable(byte)).map_or(window.len(), |i| position + i);
+        Ok(Some(window_start + start..window_start + end))
+    }
+    
+    fn pane(&self) -> &Pane {
+        &self.file.panes[self.file.focused_pane]
+    }
+    
+    fn pane_mut(&mut self) -> &mut Pane {
+        &mut self.file.panes[self.file.focused_pane]
+    }
+    
+    /// Splits the screen into two panes showing the same position, or goes back to only showing the focused one
+    fn toggle_split(&mut self) {
+        if self.file.panes.len() > 1 {
+            let pane = self.file.panes.swap_remove(self.file.focused_pane);
+            self.file.panes = vec![pane];
+            self.file.focused_pane = 0;
+        } else {
+            self.file.panes.push(self.pane().clone());
+            self.file.focused_pane = 1;
+        }
+    }
+    
+    fn focus_next_pane(&mut self) {
+        self.file.focused_pane = (self.file.focused_pane + 1) % self.file.panes.len();
+    }
+    
+    /// Starts searching for `needle` in the background, replacing any search that is still running
+    fn start_search(&mut self, needle: Vec<u8>, start: usize) {
+        match Search::start(&*self.file.bytes, needle, start) {
+            Ok(search) => self.file.search = Some(search),
+            Err(err) => self.bottom_text = Some(format!("Error: {err}")),
+        }
+    }
+    
+    /// Searches for the next occurrence of whatever was found last
+    fn find_next(&mut self) {
+        let Some((needle, index)) = &self.file.last_match else {
+            self.bottom_text = Some("Nothing has been searched for yet".to_owned());
+            return;
+        };
+        
+        self.start_search(needle.clone(), index + 1);
+    }
+    
+    /// Lists all matches of the last search, collecting them again if the needle or the file has changed since
+    fn open_matches(&mut self) {
+        let needle = match (&self.file.last_match, &self.file.search) {
+            (_, Some(search)) => search.needle.clone(),
+            (Some((needle, _)), None) => needle.clone(),
+            (None, None) => {
+                self.bottom_text = Some("Nothing has been searched for yet".to_owned());
+                return;
+            },
+        };
+        
+        if self.file.matches.as_ref().is_none_or(|matches| matches.needle != needle || matches.stale) {
+            self.collect_matches(needle);
+        }
+        
+        self.queued_input_state = Some(InputState::Matches(MatchesPanel::default()));
+    }
+    
+    /// Starts finding all matches of `needle` in the background, replacing the ones listed before
+    fn collect_matches(&mut self, needle: Vec<u8>) {
+        match MatchList::start(&*self.file.bytes, needle) {
+            Ok(matches) => self.file.matches = Some(matches),
+            Err(err) => self.bottom_text = Some(format!("Error: {err}")),
+        }
+    }
+    
+    fn poll_matches(&mut self) {
+        if let Some(matches) = &mut self.file.matches
+            && let Err(err) = matches.poll()
+        {
+            self.bottom_text = Some(format!("Error: {err}"));
+        }
+    }
+    
+    /// Jumps to the result of the background search once it's done
+    fn poll_search(&mut self) {
+        let Some(result) = self.file.search.as_mut().and_then(Search::poll) else {
+            return;
+        };
+        
+        let Some(search) = self.file.search.take() else {
+            return;
+        };
+        
+        match result {
+            Ok(Some(index)) => {
+                self.jump_to(index);
+                self.file.last_match = Some((search.needle, index));
+            },
+            Ok(None) => self.bottom_text = Some("No more matches found".to_owned()),
+            Err(err) => self.bottom_text = Some(format!("Error: {err}")),
+        }
+    }
+    
+    /// Starts calculating the hashes of the selection (or the whole file) in the background
+    fn start_hashing(&mut self) {
+        let file_len = self.file.bytes.len();
+        let range = self.pane().selected_range(file_len).unwrap_or(0..file_len);
+        
+        match HashJob::start(&*self.file.bytes, range) {
+            Ok(job) => self.file.hashing = Some(job),
+            Err(err) => self.bottom_text = Some(format!("Error: {err}")),
+        }
+    }
+    
+    /// Shows the hashes once they're done, waiting until nothing else is being typed
+    fn poll_hashing(&mut self) {
+        if !matches!(self.input_state, InputState::Regular) {
+            return;
+        }
+        
+        let Some(result) = self.file.hashing.as_mut().and_then(HashJob::poll) else {
+            return;
+        };
+        self.file.hashing = None;
+        
+        match result {
+            Ok(digests) => self.input_state = InputState::Hashes { digests, selected: 0 },
+            Err(err) => self.bottom_text = Some(format!("Error: {err}")),
+        }
+    }
+    
+    fn display_name(&self) -> Cow<'_, str> {
+        self.file.display_name()
+    }
+    
+    fn save_file(&mut self) -> Result<()> {
+        let Some(file_name) = &self.file.file_name else {
+            bail!("There is no file to save to");
+        };
+        
+        self.file.bytes.save(file_name)?;
+        self.file.modified_bytes.clear();
+        self.file.disk_metadata = DiskMetadata::read(file_name).ok();
+        self.file.discard_journal();
+        Ok(())
+    }
+    
+    /// Writes the buffer to a different path, which becomes the file being edited from then on
+    fn save_file_as(&mut self, path: PathBuf) -> Result<()> {
+        self.file.bytes.save(&path)?;
+        
+        // the journal belongs to the old path
+        self.file.discard_journal();
+        self.file.disk_metadata = DiskMetadata::read(&path).ok();
+        self.file.file_name = Some(path);
+        self.file.modified_bytes.clear();
+        Ok(())
+    }
+    
+    /// Saves the file, unless another program has changed it since it was loaded,
+    /// in which case the user gets asked what to do instead.
+    /// Returns false if the program should quit.
+    fn request_save(&mut self, quit_after_save: bool) -> bool {
+        if self.file.file_name.is_none() {
+            // data from stdin can only be saved somewhere else
+            self.queued_input_state = Some(InputState::SaveAs(PathInput::default()));
+            return true;
+        }
+        
+        if self.changed_on_disk() {
+            self.queued_input_state = Some(InputState::ExternallyModified { quit_after_save });
+            return true;
+        }
+        
+        if let Err(err) = self.save_file() {
+            self.bottom_text = Some(format!("Error: {err}"));
+            return true;
+        }
+        
+        if quit_after_save {
+            // there might be more files with unsaved changes
+            return self.request_quit();
+        }
+        
+        true
+    }
+    
+    /// Asks for confirmation for each file with unsaved changes, switching to it first.
+    /// Returns false if the program should quit.
+    fn request_quit(&mut self) -> bool {
+        let Some(dirty_file) = self.files().position(|file| !file.modified_bytes.is_empty() && !file.discard_on_quit) else {
+            return false;
+        };
+        
+        self.switch_to_file(dirty_file);
+        self.queued_input_state = Some(InputState::UnsavedChanges(PendingAction::Quit));
+        true
+    }
+    
+    /// Forgets which files the user has agreed to discard after deciding not to quit after all
+    fn cancel_quit(&mut self) {
+        self.file.discard_on_quit = false;
+        
+        for file in &mut self.other_files {
+            file.discard_on_quit = false;
+        }
+    }
+    
+    /// Deletes the journals of the files whose edits have been saved or discarded on purpose after a clean exit.
+    /// Quitting with Ctrl+C skips asking about unsaved changes, so their journals are kept to restore them.
+    fn discard_finished_journals(&mut self) {
+        for file in iter::once(&mut self.file).chain(&mut self.other_files) {
+            if file.modified_bytes.is_empty() || file.discard_on_quit {
+                file.discard_journal();
+            }
+        }
+    }
+    
+    /// Reloads the file from disk, asking for confirmation first if there are unsaved changes
+    fn request_reload(&mut self) {
+        if self.file.modified_bytes.is_empty() {
+            self.reload();
+        } else {
+            self.queued_input_state = Some(InputState::UnsavedChanges(PendingAction::Reload));
+        }
+    }
+    
+    /// Reloads the file from disk and reports the outcome in the bottom bar
+    fn reload(&mut self) {
+        self.bottom_text = Some(match self.reload_file() {
+            Ok(Some(warning)) => warning,
+            Ok(None) => format!("Reloaded '{}' (0x{:x} bytes)", self.display_name(), self.file.bytes.len()),
+            Err(err) => format!("Error: {err}"),
+        });
+    }
+    
+    fn changed_on_disk(&self) -> bool {
+        let Some(file_name) = &self.file.file_name else {
+            return false;
+        };
+        
+        // if the file got deleted, saving just creates it again
+        match DiskMetadata::read(file_name) {
+            Ok(disk_metadata) => self.file.disk_metadata != Some(disk_metadata),
+            Err(_) => false,
+        }
+    }
+    
+    /// Replaces the buffer with the current contents of the file, discarding all edits.
+    /// If the file can't be read, the buffer is left untouched.
+    /// Returns a warning if the file couldn't be opened the preferred way.
+    fn reload_file(&mut self) -> Result<Option<String>> {
+        let Some(file_name) = &self.file.file_name else {
+            bail!("Cannot reload data that has been read from stdin");
+        };
+        
+        let (bytes, warning) = buffer::open(file_name, self.force_mmap)?;
+        
+        self.file.disk_metadata = DiskMetadata::read(file_name).ok();
+        self.file.max_rows = bytes.len().div_ceil(16);
+        self.file.bytes = bytes;
+        self.file.modified_bytes.clear();
+        self.file.discard_journal();
+        self.file.entropy.clear();
+        self.file.histogram = None;
+        self.file.mark_matches_stale();
+        self.file.detect_file_type();
+        self.recount_differences();
+        
+        self.clamp_to_content();
+        Ok(warning)
+    }
+    
+    fn toggle_follow(&mut self) {
+        if self.file.following {
+            self.file.following = false;
+            self.bottom_text = Some("Stopped following the file".to_owned());
+        } else if self.file.file_name.is_none() {
+            self.bottom_text = Some("Cannot follow data that has been read from stdin".to_owned());
+        } else if !self.file.modified_bytes.is_empty() {
+            self.bottom_text = Some("Cannot follow the file while there are unsaved changes".to_owned());
+        } else {
+            self.file.following = true;
+            self.update_followed_file();
+        }
+    }
+    
+    /// Whether a search, hashing or collecting matches is still running
+    fn has_background_work(&self) -> bool {
+        let collecting_matches = self.file.matches.as_ref().is_some_and(|matches| !matches.is_done());
+        self.file.search.is_some() || self.file.hashing.is_some() || collecting_matches
+    }
+    
+    /// Appends data that has been written to the end of the file since it was last read.
+    /// Anything other than the file growing ends follow mode.
+    /// Returns whether anything has changed, which is false as long as the file stays the same.
+    fn update_followed_file(&mut self) -> bool {
+        let Some(file_name) = &self.file.file_name else {
+            return false;
+        };
+        
+        let disk_metadata = match DiskMetadata::read(file_name) {
+            Ok(disk_metadata) => disk_metadata,
+            Err(err) => {
+                self.stop_following(&err.to_string());
+                return true;
+            },
+        };
+        
+        if self.file.disk_metadata == Some(disk_metadata) {
+            return false;
+        }
+        
+        let prev_len = self.file.bytes.len() as u64;
+        
+        if disk_metadata.len < prev_len {
+            self.stop_following("the file has shrunk");
+            return true;
+        }
+        
+        if disk_metadata.len == prev_len {
+            self.stop_following("the file has been replaced");
+            return true;
+        }
+        
+        // keep the views pinned to the end if they were showing the end before
+        let pinned = self.file.panes.iter()
+            .map(|pane| pane.scroll_pos + pane.visible_content_rows() >= self.file.max_rows)
+            .collect::<Vec<_>>();
+        
+        if let Err(err) = self.file.bytes.grow(file_name, disk_metadata.len as usize) {
+            self.stop_following(&err.to_string());
+            return true;
+        }
+        
+        self.file.max_rows = self.file.bytes.len().div_ceil(16);
+        self.file.disk_metadata = Some(disk_metadata);
+        self.file.entropy.invalidate_from(prev_len as usize);
+        self.file.histogram = None;
+        self.file.mark_matches_stale();
+        if (prev_len as usize) < SIGNATURE_AREA {
+            self.file.detect_file_type();
+        }
+        self.recount_differences();
+        
+        for (pane, pinned) in self.file.panes.iter_mut().zip(pinned) {
+            if pinned {
+                pane.scroll_pos = usize::max(
+                    pane.scroll_pos,
+                    self.file.max_rows.saturating_sub(pane.visible_content_rows()),
+                );
+            }
+        }
+        
+        true
+    }
+    
+    fn stop_following(&mut self, reason: &str) {
+        self.file.following = false;
+        self.bottom_text = Some(format!("Warning: Stopped following the file, {reason}"));
+    }
+    
+    /// Number of rows that get drawn. While editing, a full last row is followed by an empty one,
+    /// since typing there appends bytes.
+    fn content_rows(&self) -> usize {
+        if matches!(self.input_state, InputState::Edit { .. }) {
+            self.file.bytes.len() / 0x10 + 1
+        } else {
+            self.file.max_rows
+        }
+    }
+    
+    /// Keeps the panes from being scrolled past the end, e.g. after the terminal has been resized
+    fn clamp_scroll(&mut self) {
+        let content_rows = self.content_rows();
+        
+        for pane in &mut self.file.panes {
+            pane.scroll_pos = usize::min(pane.scroll_pos, pane.max_scroll_pos(content_rows));
+        }
+    }
+    
+    /// Moves the scroll position and cursor back inside the file after it has shrunk
+    fn clamp_to_content(&mut self) {
+        let file_len = self.file.bytes.len();
+        let appending = matches!(self.input_state, InputState::Edit { .. });
+        
+        self.clamp_scroll();
+        for pane in &mut self.file.panes {
+            if let Some((row, col)) = &mut pane.selection {
+                *row = usize::min(*row, max_row(file_len, appending));
+                *col = usize::min(*col, max_col_for_row(file_len, *row, appending));
+            }
+        }
+    }
+}
+
+fn run(config: &Config, state: &mut State) -> Result<()> {
+    let keybinds = &config.keybinds;
+    let mut needs_redraw = true;
+    let mut last_follow_check = Instant::now();
+    
+    loop {
+        // background work shows its progress, and jumps to its result once it's done
+        needs_redraw |= state.has_background_work();
+        
+        state.poll_search();
+        state.poll_hashing();
+        state.poll_matches();
+        
+        // in follow mode, check the file for new data, also while input keeps coming in
+        if state.file.following && last_follow_check.elapsed() >= FOLLOW_POLL_INTERVAL {
+            needs_redraw |= state.update_followed_file();
+            last_follow_check = Instant::now();
+        }
+        
+        if needs_redraw {
+            draw(config, state)?;
+            needs_redraw = false;
+        }
+        
+        // keep the background work going whenever there is no input for a while
+        let poll_interval = if state.has_background_work() {
+            Some(SEARCH_POLL_INTERVAL)
+        } else if state.file.following {
+            Some(FOLLOW_POLL_INTERVAL.saturating_sub(last_follow_check.elapsed()))
+        } else {
+            None
+        };
+        
+        if let Some(poll_interval) = poll_interval && !event::poll(poll_interval)? {
+            continue;
+        }
+        
+        // handle everything that came in while drawing before drawing again, so holding down a key doesn't lag behind
+        loop {
+            match handle_input(event::read()?, keybinds, state) {
+                Handled::Nothing => {},
+                Handled::Changed => needs_redraw = true,
+                Handled::Quit => return Ok(()),
+            }
+            
+            if let Some(queued_input_state) = mem::take(&mut state.queued_input_state) {
+                state.input_state = queued_input_state;
+            }
+            
+            if !event::poll(Duration::ZERO)? {
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process;
+    
+    use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+    
+    use super::*;
+    use crate::cfg::Keybinds;
+    
+    /// State with a single file of `len` bytes, shown in a pane `height` rows high
+    fn state_with_file(len: usize, height: u16) -> State {
+        let file = OpenFile::new(None, Box::new(InMemoryBuffer(Arc::new(vec![0; len]))));
+        let mut state = State::new(&Config::default_config(), vec![file], false, false);
+        state.pane_mut().height = height;
+        state
+    }
+    
+    #[test]
+    fn cursor_bounds_of_short_files() {
+        // (file length, last row, last column of the last row)
+        let bounds = [(0, 0, 0), (1, 0, 1), (15, 0, 29), (16, 0, 31), (17, 1, 1)];
+        
+        for (len, row, col) in bounds {
+            assert_eq!(max_row(len, false), row, "{len} bytes");
+            assert_eq!(max_col_for_row(len, row, false), col, "{len} bytes");
+        }
+    }
+    
+    #[test]
+    fn cursor_bounds_while_appending() {
+        // one more digit, right after the end
+        let bounds = [(0, 0, 0), (1, 0, 2), (15, 0, 30), (16, 1, 0), (17, 1, 2)];
+        
+        for (len, row, col) in bounds {
+            assert_eq!(max_row(len, true), row, "{len} bytes");
+            assert_eq!(max_col_for_row(len, row, true), col, "{len} bytes");
+        }
+    }
+    
+    #[test]
+    fn rows_before_the_last_are_full() {
+        for appending in [false, true] {
+            assert_eq!(max_col_for_row(17, 0, appending), 0x1f);
+            assert_eq!(max_col_for_row(0x100, 0xe, appending), 0x1f);
+        }
+        
+        // rows past the end only have the first column
+        assert_eq!(max_col_for_row(17, 5, false), 0);
+        assert_eq!(max_col_for_row(0, 1, true), 0);
+    }
+    
+    #[test]
+    fn short_content_cant_be_scrolled() {
+        let pane = Pane { height: 10, ..Pane::default() };
+        
+        assert_eq!(pane.max_scroll_pos(0), 0);
+        assert_eq!(pane.max_scroll_pos(3), 0);
+        assert_eq!(pane.max_scroll_pos(10), 0);
+        assert_eq!(pane.max_scroll_pos(25), 15);
+    }
+    
+    #[test]
+    fn scrolling_stops_at_the_last_row() {
+        let mut state = state_with_file(17, 10);
+        state.pane_mut().scroll_pos = 5;
+        state.clamp_scroll();
+        assert_eq!(state.pane().scroll_pos, 0);
+        
+        // the partial last row still counts as a row
+        let mut state = state_with_file(0x105, 4);
+        state.pane_mut().scroll_pos = 20;
+        state.clamp_scroll();
+        assert_eq!(state.pane().scroll_pos, 13);
+        
+        state.pane_mut().scroll_pos = 12;
+        state.clamp_scroll();
+        assert_eq!(state.pane().scroll_pos, 12);
+    }
+    
+    #[test]
+    fn editing_adds_a_row_after_full_ones() {
+        let mut state = state_with_file(0x100, 4);
+        state.pane_mut().scroll_pos = 20;
+        state.clamp_scroll();
+        assert_eq!(state.pane().scroll_pos, 12);
+        
+        state.input_state = InputState::Edit { prev_in_pager: false };
+        state.pane_mut().scroll_pos = 20;
+        state.clamp_scroll();
+        assert_eq!(state.pane().scroll_pos, 13);
+    }
+    
+    #[test]
+    fn shrinking_moves_the_cursor_back_into_the_file() {
+        let mut state = state_with_file(0x400, 4);
+        state.pane_mut().scroll_pos = 0x30;
+        state.pane_mut().selection = Some((0x32, 0x1f));
+        
+        state.file.bytes = Box::new(InMemoryBuffer(Arc::new(vec![0; 17])));
+        state.file.max_rows = 2;
+        state.clamp_to_content();
+        assert_eq!(state.pane().scroll_pos, 0);
+        assert_eq!(state.pane().selection, Some((1, 1)));
+        
+        state.input_state = InputState::Edit { prev_in_pager: false };
+        state.pane_mut().selection = Some((0x32, 0x1f));
+        state.clamp_to_content();
+        assert_eq!(state.pane().selection, Some((1, 2)));
+    }
+    
+    /// Handles a key press like [`run`] does
+    fn press(state: &mut State, keybinds: &Keybinds, key_event: KeyEvent) {
+        handle_input(Event::Key(key_event), keybinds, state);
+        
+        if let Some(queued_input_state) = state.queued_input_state.take() {
+            state.input_state = queued_input_state;
+        }
+    }
+    
+    #[test]
+    fn cursor_comes_back_after_pager_mode() {
+        let keybinds = Config::default_config().keybinds;
+        let mut state = state_with_file(0x1000, 4);
+        state.pane_mut().selection = Some((5, 4));
+        
+        press(&mut state, &keybinds, keybinds.toggle_cursor.key_event());
+        assert_eq!(state.pane().selection, None);
+        
+        state.pane_mut().scroll_pos = 0x40;
+        press(&mut state, &keybinds, keybinds.toggle_cursor.key_event());
+        assert_eq!(state.pane().selection, Some((5, 4)));
+        assert_eq!(state.pane().scroll_pos, 5);
+    }
+    
+    #[test]
+    fn going_to_an_offset_wins_over_the_remembered_cursor() {
+        let keybinds = Config::default_config().keybinds;
+        let mut state = state_with_file(0x1000, 4);
+        state.pane_mut().selection = Some((5, 4));
+        press(&mut state, &keybinds, keybinds.toggle_cursor.key_event());
+        
+        press(&mut state, &keybinds, keybinds.go_to.key_event());
+        for c in "812".chars() {
+            press(&mut state, &keybinds, KeyEvent::from(KeyCode::Char(c)));
+        }
+        press(&mut state, &keybinds, KeyEvent::from(KeyCode::Enter));
+        assert_eq!(state.pane().selection, Some((0x81, 4)));
+        assert_eq!(state.pane().last_selection, None);
+        
+        // switching to pager mode and back keeps the cursor at the offset that has been gone to
+        press(&mut state, &keybinds, keybinds.toggle_cursor.key_event());
+        press(&mut state, &keybinds, keybinds.toggle_cursor.key_event());
+        assert_eq!(state.pane().selection, Some((0x81, 4)));
+    }
+    
+    #[test]
+    fn remembered_cursor_past_the_end_is_dropped() {
+        let mut pane = Pane { height: 4, scroll_pos: 2, ..Pane::default() };
+        pane.selection = Some((0x40, 6));
+        pane.hide_cursor();
+        
+        // the file has shrunk in the meantime
+        pane.show_cursor(0x100);
+        assert_eq!(pane.selection, Some((2, 0)));
+    }
+    
+    /// State with a single file at a temporary path, for testing the journal
+    fn state_with_saved_file(name: &str, bytes: &[u8]) -> (State, PathBuf) {
+        let path = env::temp_dir().join(format!("lesbin-{name}-test-{}", process::id()));
+        fs::write(&path, bytes).unwrap();
+        
+        let file = OpenFile::new(Some(path.clone()), Box::new(InMemoryBuffer(Arc::new(bytes.to_vec()))));
+        (State::new(&Config::default_config(), vec![file], false, false), path)
+    }
+    
+    #[test]
+    fn bytes_appended_by_a_patch_are_journaled() {
+        let (mut state, path) = state_with_saved_file("patch-journal", &[1, 2]);
+        
+        // the first appended byte stays zero, so only the patch growing the file writes anything there
+        state.write_patch(vec![Record { offset: 2, data: vec![0, 7], original: None }]);
+        assert_eq!(state.file.modified_bytes, BTreeMap::from([(2, 0), (3, 0)]));
+        
+        let edits = journal::load(&path).unwrap().unwrap();
+        state.file.discard_journal();
+        fs::remove_file(&path).unwrap();
+        
+        let edits: Vec<_> = edits.iter().map(|edit| (edit.offset, edit.old, edit.new)).collect();
+        assert_eq!(edits, [(2, 0, 0), (3, 0, 0), (3, 0, 7)]);
+    }
+    
+    #[test]
+    fn journal_of_unsaved_edits_is_kept_after_ctrl_c() {
+        let keybinds = Config::default_config().keybinds;
+        let (mut state, path) = state_with_saved_file("ctrl-c-journal", &[1, 2]);
+        state.write_byte(0, 5).unwrap();
+        
+        press(&mut state, &keybinds, KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL));
+        state.discard_finished_journals();
+        assert!(journal::load(&path).unwrap().is_some());
+        
+        // discarding the edits when asked about them on quit deletes the journal
+        state.file.discard_on_quit = true;
+        state.discard_finished_journals();
+        assert!(journal::load(&path).unwrap().is_none());
+        
+        fs::remove_file(&path).unwrap();
+    }
+}
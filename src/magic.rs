@@ -0,0 +1,152 @@
+use anyhow::Result;
+
+use crate::buffer::FileBuffer;
+
+/// File format recognized by the bytes at a fixed offset
+#[derive(Debug)]
+pub struct Signature {
+    pub name: &'static str,
+    pub offset: usize,
+    pub magic: &'static [u8],
+    /// Bits of `magic` that have to match, or None if all of them do
+    pub mask: Option<&'static [u8]>,
+}
+
+impl Signature {
+    const fn new(name: &'static str, offset: usize, magic: &'static [u8]) -> Self {
+        Self { name, offset, magic, mask: None }
+    }
+    
+    const fn masked(name: &'static str, offset: usize, magic: &'static [u8], mask: &'static [u8]) -> Self {
+        Self { name, offset, magic, mask: Some(mask) }
+    }
+    
+    fn matches(&self, header: &[u8]) -> bool {
+        let Some(bytes) = header.get(self.offset..self.offset + self.magic.len()) else {
+            return false;
+        };
+        
+        match self.mask {
+            Some(mask) => bytes.iter().zip(self.magic).zip(mask).all(|((byte, magic), mask)| byte & mask == magic & mask),
+            None => bytes == self.magic,
+        }
+    }
+}
+
+/// Known file formats, checked in order so more specific signatures have to come before more general ones
+const SIGNATURES: &[Signature] = &[
+    Signature::new("ELF", 0, b"\x7fELF"),
+    Signature::new("PE/DOS executable", 0, b"MZ"),
+    // 32 and 64 bit, which only differ in the lowest bit
+    Signature::masked("Mach-O", 0, b"\xfe\xed\xfa\xce", b"\xff\xff\xff\xfe"),
+    Signature::masked("Mach-O (little endian)", 0, b"\xce\xfa\xed\xfe", b"\xfe\xff\xff\xff"),
+    Signature::new("Java class or universal Mach-O", 0, b"\xca\xfe\xba\xbe"),
+    Signature::new("WebAssembly", 0, b"\0asm"),
+    Signature::new("Dalvik executable", 0, b"dex\n"),
+    Signature::new("PNG image", 0, b"\x89PNG\r\n\x1a\n"),
+    Signature::new("JPEG image", 0, b"\xff\xd8\xff"),
+    Signature::new("GIF image", 0, b"GIF8"),
+    Signature::new("PDF document", 0, b"%PDF-"),
+    Signature::new("SQLite database", 0, b"SQLite format 3\0"),
+    Signature::new("ZIP archive", 0, b"PK\x03\x04"),
+    Signature::new("ZIP archive (empty)", 0, b"PK\x05\x06"),
+    Signature::new("7-Zip archive", 0, b"7z\xbc\xaf\x27\x1c"),
+    Signature::new("RAR archive", 0, b"Rar!\x1a\x07"),
+    Signature::new("TAR archive", 0x101, b"ustar"),
+    Signature::new("GZIP", 0, b"\x1f\x8b"),
+    Signature::new("bzip2", 0, b"BZh"),
+    Signature::new("XZ", 0, b"\xfd7zXZ\0"),
+    Signature::new("Zstandard", 0, b"\x28\xb5\x2f\xfd"),
+    Signature::new("LZ4", 0, b"\x04\x22\x4d\x18"),
+    Signature::new("Yaz0", 0, b"Yaz0"),
+    Signature::new("SARC archive", 0, b"SARC"),
+    Signature::new("WAVE audio", 8, b"WAVE"),
+    Signature::new("RIFF", 0, b"RIFF"),
+    Signature::new("Ogg", 0, b"OggS"),
+    Signature::new("FLAC audio", 0, b"fLaC"),
+    Signature::new("MP3 audio (ID3)", 0, b"ID3"),
+];
+
+/// Bytes at the start of the file that the signatures look at, changes past these don't affect the file type
+pub const SIGNATURE_AREA: usize = {
+    let mut end = 0;
+    let mut i = 0;
+    while i < SIGNATURES.len() {
+        let signature_end = SIGNATURES[i].offset + SIGNATURES[i].magic.len();
+        if signature_end > end {
+            end = signature_end;
+        }
+        i += 1;
+    }
+    end
+};
+
+/// Finds the first signature matching the start of the buffer
+pub fn detect(buffer: &mut dyn FileBuffer) -> Result<Option<&'static Signature>> {
+    let header = buffer.read(0..usize::min(SIGNATURE_AREA, buffer.len()))?;
+    Ok(SIGNATURES.iter().find(|signature| signature.matches(header)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    
+    use super::*;
+    use crate::buffer::InMemoryBuffer;
+    
+    fn detected(bytes: &[u8]) -> Option<&'static str> {
+        detect(&mut InMemoryBuffer(Arc::new(bytes.to_vec()))).unwrap().map(|signature| signature.name)
+    }
+    
+    /// `magic` at `offset` in an otherwise empty header
+    fn header(offset: usize, magic: &[u8]) -> Vec<u8> {
+        let mut bytes = vec![0; usize::max(SIGNATURE_AREA, offset + magic.len())];
+        bytes[offset..offset + magic.len()].copy_from_slice(magic);
+        bytes
+    }
+    
+    #[test]
+    fn every_signature_is_detected() {
+        for signature in SIGNATURES {
+            assert_eq!(detected(&header(signature.offset, signature.magic)), Some(signature.name));
+        }
+    }
+    
+    #[test]
+    fn tar_is_detected_past_the_start() {
+        assert_eq!(detected(&header(0x101, b"ustar\0")), Some("TAR archive"));
+        assert_eq!(detected(&header(0, b"ustar\0")), None);
+        
+        // cut off in the middle of the magic
+        assert_eq!(detected(&header(0x101, b"ustar")[..0x104]), None);
+        assert_eq!(SIGNATURE_AREA, 0x106);
+    }
+    
+    #[test]
+    fn mach_o_in_both_sizes_and_byte_orders() {
+        assert_eq!(detected(b"\xfe\xed\xfa\xce\x00\x00\x00\x0c"), Some("Mach-O"));
+        assert_eq!(detected(b"\xfe\xed\xfa\xcf\x01\x00\x00\x0c"), Some("Mach-O"));
+        assert_eq!(detected(b"\xce\xfa\xed\xfe\x07\x00\x00\x00"), Some("Mach-O (little endian)"));
+        assert_eq!(detected(b"\xcf\xfa\xed\xfe\x07\x00\x00\x01"), Some("Mach-O (little endian)"));
+        
+        // the mask only covers the bit that tells 32 and 64 bit apart
+        assert_eq!(detected(b"\xfe\xed\xfa\xcc"), None);
+        assert_eq!(detected(b"\xfe\xed\xfa\xcd"), None);
+        assert_eq!(detected(b"\xcd\xfa\xed\xfe"), None);
+        assert_eq!(detected(b"\xce\xfa\xed\xff"), None);
+    }
+    
+    #[test]
+    fn specific_signatures_come_first() {
+        assert_eq!(detected(b"RIFF\x24\x08\0\0WAVEfmt "), Some("WAVE audio"));
+        assert_eq!(detected(b"RIFF\x24\x08\0\0AVI LIST"), Some("RIFF"));
+    }
+    
+    #[test]
+    fn short_and_unknown_files() {
+        assert_eq!(detected(b""), None);
+        assert_eq!(detected(b"\x7fEL"), None);
+        assert_eq!(detected(b"M"), None);
+        assert_eq!(detected(b"plain text file\n"), None);
+    }
+}
@@ -0,0 +1,232 @@
+use std::{collections::BTreeMap, fmt::Write, fs, path::Path};
+
+use anyhow::{Result, anyhow, bail};
+
+use crate::{buffer::FileBuffer, ips};
+
+/// Furthest past the end of the file that a patch may write, which leaves room for any IPS patch
+const MAX_GROWTH: usize = 0x200_0000;
+
+/// Bytes that a patch writes at an offset
+#[derive(Debug)]
+pub struct Record {
+    pub offset: usize,
+    pub data: Vec<u8>,
+    /// Bytes the patch expects to replace, only known for text patches
+    pub original: Option<Vec<u8>>,
+}
+
+impl Record {
+    /// Offset after the last byte written by the record
+    pub fn end(&self) -> usize {
+        self.offset.saturating_add(self.data.len())
+    }
+}
+
+/// Offset after the last byte written by any of the records
+pub fn end(records: &[Record]) -> usize {
+    records.iter().map(Record::end).max().unwrap_or(0)
+}
+
+/// Reads a patch in either of the formats written by [`export`] and checks that it fits `buffer`.
+/// The whole patch gets validated, so nothing is returned if any part of it is malformed,
+/// goes too far past the end of the file or expects different bytes than the file contains.
+pub fn load(path: &Path, buffer: &mut dyn FileBuffer) -> Result<Vec<Record>> {
+    let content = fs::read(path)?;
+    
+    let records = if ips::is_ips(&content) {
+        ips::decode(&content)?
+    } else {
+        parse_text(content)?
+    };
+    
+    validate(&records, buffer)?;
+    Ok(records)
+}
+
+fn parse_text(content: Vec<u8>) -> Result<Vec<Record>> {
+    let content = String::from_utf8(content).map_err(|_| anyhow!("Not an IPS or text patch"))?;
+    
+    content.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| parse_line(line).ok_or_else(|| {
+            anyhow!("Line {} is malformed, expected '<offset>: <old byte> -> <new byte>'", i + 1)
+        }))
+        .collect()
+}
+
+/// Parses a line of a text patch
+fn parse_line(line: &str) -> Option<Record> {
+    let (offset, change) = line.split_once(':')?;
+    let (old_byte, new_byte) = change.split_once("->")?;
+    
+    let offset = offset.trim();
+    let offset = usize::from_str_radix(offset.strip_prefix("0x").unwrap_or(offset), 16).ok()?;
+    let old_byte = u8::from_str_radix(old_byte.trim(), 16).ok()?;
+    let new_byte = u8::from_str_radix(new_byte.trim(), 16).ok()?;
+    
+    Some(Record { offset, data: vec![new_byte], original: Some(vec![old_byte]) })
+}
+
+/// Makes sure that applying the records can't fail halfway through
+fn validate(records: &[Record], buffer: &mut dyn FileBuffer) -> Result<()> {
+    let len = buffer.len();
+    
+    for record in records {
+        let Some(end) = record.offset.checked_add(record.data.len()) else {
+            bail!("Record at 0x{:x} goes past the largest possible offset", record.offset);
+        };
+        
+        if end > len.saturating_add(MAX_GROWTH) {
+            bail!("Record at 0x{:x} is too far past the end of the file (0x{len:x})", record.offset);
+        }
+        
+        let Some(original) = &record.original else {
+            continue;
+        };
+        
+        for (offset, &expected) in (record.offset..).zip(original) {
+            // bytes past the end get appended as zeroes
+            let actual = if offset < len { buffer.read_byte(offset)? } else { 0 };
+            
+            if actual != expected {
+                bail!("Expected 0x{expected:02x} at 0x{offset:x} but found 0x{actual:02x}, the patch might be for a different file");
+            }
+        }
+    }
+    
+    Ok(())
+}
+
+/// Writes all modified bytes to a patch file, as an IPS patch if the path ends in `.ips`
+/// and as text with one `offset: old -> new` line per byte otherwise.
+/// Returns the number of records written.
+pub fn export(path: &Path, buffer: &mut dyn FileBuffer, modified_bytes: &BTreeMap<usize, u8>) -> Result<usize> {
+    let is_ips = path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("ips"));
+    
+    if is_ips {
+        let (patch, record_count) = ips::encode(buffer, modified_bytes.keys().copied())?;
+        fs::write(path, patch)?;
+        return Ok(record_count);
+    }
+    
+    let mut patch = String::new();
+    for (&offset, &original_byte) in modified_bytes {
+        writeln!(patch, "0x{offset:08x}: {original_byte:02x} -> {:02x}", buffer.read_byte(offset)?)?;
+    }
+    
+    fs::write(path, patch)?;
+    Ok(modified_bytes.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, process, sync::Arc};
+    
+    use super::*;
+    use crate::buffer::InMemoryBuffer;
+    
+    fn patch_path(name: &str) -> std::path::PathBuf {
+        env::temp_dir().join(format!("lesbin-patch-{name}-test-{}", process::id()))
+    }
+    
+    /// Loads a text patch with the given content for `original`
+    fn load_text(name: &str, content: &str, original: &[u8]) -> Result<Vec<Record>> {
+        let path = patch_path(name);
+        fs::write(&path, content).unwrap();
+        
+        let result = load(&path, &mut InMemoryBuffer(Arc::new(original.to_vec())));
+        fs::remove_file(&path).unwrap();
+        result
+    }
+    
+    fn apply(records: &[Record], bytes: &mut Vec<u8>) {
+        if end(records) > bytes.len() {
+            bytes.resize(end(records), 0);
+        }
+        
+        for record in records {
+            bytes[record.offset..record.end()].copy_from_slice(&record.data);
+        }
+    }
+    
+    #[test]
+    fn text_patch_round_trip() {
+        let original: Vec<u8> = (0..0x20).collect();
+        let mut edited = original.clone();
+        edited[3] = 0xff;
+        edited[0x1f] = 0;
+        edited.extend([0, 0, 7]);
+        
+        let modified_bytes = BTreeMap::from([(3, 3), (0x1f, 0x1f), (0x20, 0), (0x21, 0), (0x22, 0)]);
+        let path = patch_path("round-trip");
+        
+        let record_count = export(&path, &mut InMemoryBuffer(Arc::new(edited.clone())), &modified_bytes).unwrap();
+        assert_eq!(record_count, 5);
+        assert!(fs::read_to_string(&path).unwrap().starts_with("0x00000003: 03 -> ff\n0x0000001f: 1f -> 00\n"));
+        
+        let records = load(&path, &mut InMemoryBuffer(Arc::new(original.clone()))).unwrap();
+        fs::remove_file(&path).unwrap();
+        
+        let mut patched = original;
+        apply(&records, &mut patched);
+        assert_eq!(patched, edited);
+    }
+    
+    #[test]
+    fn text_patch_format_is_lenient_about_spacing() {
+        let records = load_text("spacing", "\n  10:00->2a  \n\n0x11 :  00 ->  ff\n", &[0; 0x12]).unwrap();
+        let records: Vec<_> = records.iter().map(|record| (record.offset, record.data.clone())).collect();
+        assert_eq!(records, [(0x10, vec![0x2a]), (0x11, vec![0xff])]);
+    }
+    
+    #[test]
+    fn malformed_lines_are_rejected() {
+        for line in ["10: 00", "10 00 -> 01", "xyz: 00 -> 01", "10: 00 -> 100", "10: zz -> 01", "10: 00 -> "] {
+            let err = load_text("malformed", &format!("0: 00 -> 01\n{line}\n"), &[0; 0x20]).unwrap_err();
+            assert_eq!(err.to_string(), "Line 2 is malformed, expected '<offset>: <old byte> -> <new byte>'", "{line}");
+        }
+        
+        let err = load_text("binary", "\u{0}\u{ff}", &[0]).unwrap_err();
+        assert_eq!(err.to_string(), "Line 1 is malformed, expected '<offset>: <old byte> -> <new byte>'");
+    }
+    
+    #[test]
+    fn offsets_too_far_past_the_end_are_rejected() {
+        let err = load_text("overflow", &format!("{:x}: 00 -> 01\n", usize::MAX), &[0; 0x10]).unwrap_err();
+        assert_eq!(err.to_string(), format!("Record at 0x{:x} goes past the largest possible offset", usize::MAX));
+        
+        let err = load_text("far", &format!("{:x}: 00 -> 01\n", usize::MAX / 2), &[0; 0x10]).unwrap_err();
+        assert_eq!(err.to_string(), format!("Record at 0x{:x} is too far past the end of the file (0x10)", usize::MAX / 2));
+        
+        // growing the file a bit is fine
+        let records = load_text("grow", "0x1000: 00 -> 01\n", &[0; 0x10]).unwrap();
+        assert_eq!(end(&records), 0x1001);
+    }
+    
+    #[test]
+    fn patches_for_different_contents_are_rejected() {
+        let err = load_text("mismatch", "0: 00 -> 01\n1: 00 -> 02\n", &[0, 5]).unwrap_err();
+        assert_eq!(err.to_string(), "Expected 0x00 at 0x1 but found 0x05, the patch might be for a different file");
+        
+        // appended bytes start out as zeroes
+        let err = load_text("mismatch-past-end", "2: 05 -> 02\n", &[0, 5]).unwrap_err();
+        assert_eq!(err.to_string(), "Expected 0x05 at 0x2 but found 0x00, the patch might be for a different file");
+    }
+    
+    #[test]
+    fn nothing_is_applied_after_a_failure() {
+        let original = vec![1, 2, 3, 4];
+        let path = patch_path("partial");
+        fs::write(&path, "0: 01 -> aa\n1: 02 -> bb\n3: ff -> cc\n").unwrap();
+        
+        let mut buffer = InMemoryBuffer(Arc::new(original.clone()));
+        assert!(load(&path, &mut buffer).is_err());
+        fs::remove_file(&path).unwrap();
+        
+        // the valid lines before the bad one aren't returned, and the buffer is left alone
+        assert_eq!(buffer.read(0..4).unwrap(), original);
+    }
+}
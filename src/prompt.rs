@@ -0,0 +1,223 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+/// Text typed into a prompt, with a cursor that can be moved around to edit it anywhere
+#[derive(Debug, Clone, Default)]
+pub struct PromptBuffer {
+    text: String,
+    /// Byte offset into `text`, always at a character boundary
+    cursor: usize,
+}
+
+impl PromptBuffer {
+    /// Starts out with `text`, with the cursor after it
+    pub fn new(text: String) -> Self {
+        let cursor = text.len();
+        Self { text, cursor }
+    }
+    
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+    
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+    
+    /// Handles the keys for moving the cursor and editing, typing only the characters that `accepts` allows.
+    /// Ctrl+Left and Ctrl+Right move the cursor by whole words.
+    /// Returns whether the key has been used, otherwise it is left to the prompt to handle.
+    pub fn handle_key(&mut self, event: KeyEvent, accepts: impl Fn(char) -> bool) -> bool {
+        let ctrl = event.modifiers.contains(KeyModifiers::CONTROL);
+        
+        match event.code {
+            KeyCode::Char(c) if accepts(c) => self.insert(c),
+            KeyCode::Backspace => self.backspace(),
+            KeyCode::Delete => self.delete(),
+            KeyCode::Left if ctrl => self.cursor = self.prev_word_start(),
+            KeyCode::Right if ctrl => self.cursor = self.next_word_end(),
+            KeyCode::Left => self.cursor = self.prev_boundary(),
+            KeyCode::Right => self.cursor = self.next_boundary(),
+            KeyCode::Home => self.cursor = 0,
+            KeyCode::End => self.cursor = self.text.len(),
+            _ => return false,
+        }
+        
+        true
+    }
+    
+    fn insert(&mut self, c: char) {
+        self.text.insert(self.cursor, c);
+        self.cursor += c.len_utf8();
+    }
+    
+    /// Removes the character before the cursor
+    fn backspace(&mut self) {
+        let start = self.prev_boundary();
+        self.text.replace_range(start..self.cursor, "");
+        self.cursor = start;
+    }
+    
+    /// Removes the character after the cursor
+    fn delete(&mut self) {
+        let end = self.next_boundary();
+        self.text.replace_range(self.cursor..end, "");
+    }
+    
+    fn prev_boundary(&self) -> usize {
+        self.text[..self.cursor].chars().next_back().map_or(0, |c| self.cursor - c.len_utf8())
+    }
+    
+    fn next_boundary(&self) -> usize {
+        self.text[self.cursor..].chars().next().map_or(self.cursor, |c| self.cursor + c.len_utf8())
+    }
+    
+    /// Start of the word before the cursor, skipping over the separators in between
+    fn prev_word_start(&self) -> usize {
+        let before = self.text[..self.cursor].trim_end_matches(|c| !is_word_char(c));
+        before.trim_end_matches(is_word_char).len()
+    }
+    
+    /// End of the word after the cursor, skipping over the separators in between
+    fn next_word_end(&self) -> usize {
+        let after = self.text[self.cursor..].trim_start_matches(|c| !is_word_char(c));
+        self.text.len() - after.trim_start_matches(is_word_char).len()
+    }
+}
+
+/// Characters that words are made of, anything else (like spaces, slashes or dots) separates them
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn press(buffer: &mut PromptBuffer, code: KeyCode) -> bool {
+        buffer.handle_key(KeyEvent::new(code, KeyModifiers::NONE), |_| true)
+    }
+    
+    fn press_ctrl(buffer: &mut PromptBuffer, code: KeyCode) -> bool {
+        buffer.handle_key(KeyEvent::new(code, KeyModifiers::CONTROL), |_| true)
+    }
+    
+    fn type_text(buffer: &mut PromptBuffer, text: &str) {
+        for c in text.chars() {
+            press(buffer, KeyCode::Char(c));
+        }
+    }
+    
+    #[test]
+    fn typing_in_the_middle() {
+        let mut buffer = PromptBuffer::new("helo".to_owned());
+        assert_eq!(buffer.cursor(), 4);
+        
+        press(&mut buffer, KeyCode::Left);
+        type_text(&mut buffer, "l");
+        assert_eq!((buffer.text(), buffer.cursor()), ("hello", 4));
+        
+        press(&mut buffer, KeyCode::Home);
+        type_text(&mut buffer, "> ");
+        assert_eq!((buffer.text(), buffer.cursor()), ("> hello", 2));
+    }
+    
+    #[test]
+    fn deleting_in_the_middle() {
+        let mut buffer = PromptBuffer::new("abcdef".to_owned());
+        press(&mut buffer, KeyCode::Left);
+        press(&mut buffer, KeyCode::Left);
+        
+        press(&mut buffer, KeyCode::Backspace);
+        assert_eq!((buffer.text(), buffer.cursor()), ("abcef", 3));
+        
+        press(&mut buffer, KeyCode::Delete);
+        assert_eq!((buffer.text(), buffer.cursor()), ("abcf", 3));
+    }
+    
+    #[test]
+    fn deleting_at_the_ends_does_nothing() {
+        let mut buffer = PromptBuffer::new("ab".to_owned());
+        press(&mut buffer, KeyCode::Delete);
+        assert_eq!((buffer.text(), buffer.cursor()), ("ab", 2));
+        
+        press(&mut buffer, KeyCode::Home);
+        press(&mut buffer, KeyCode::Backspace);
+        press(&mut buffer, KeyCode::Left);
+        assert_eq!((buffer.text(), buffer.cursor()), ("ab", 0));
+        
+        press(&mut buffer, KeyCode::End);
+        press(&mut buffer, KeyCode::Right);
+        assert_eq!(buffer.cursor(), 2);
+    }
+    
+    #[test]
+    fn cursor_stays_on_character_boundaries() {
+        let mut buffer = PromptBuffer::new("añ日🦀".to_owned());
+        let mut positions = vec![buffer.cursor()];
+        
+        while buffer.cursor() > 0 {
+            press(&mut buffer, KeyCode::Left);
+            positions.push(buffer.cursor());
+        }
+        assert_eq!(positions, [10, 6, 3, 1, 0]);
+        
+        press(&mut buffer, KeyCode::Right);
+        press(&mut buffer, KeyCode::Right);
+        type_text(&mut buffer, "é");
+        assert_eq!((buffer.text(), buffer.cursor()), ("añé日🦀", 5));
+        
+        press(&mut buffer, KeyCode::Delete);
+        assert_eq!(buffer.text(), "añé🦀");
+        press(&mut buffer, KeyCode::Backspace);
+        press(&mut buffer, KeyCode::Backspace);
+        assert_eq!((buffer.text(), buffer.cursor()), ("a🦀", 1));
+    }
+    
+    #[test]
+    fn ctrl_moves_by_words() {
+        let mut buffer = PromptBuffer::new("out/some_file.bin".to_owned());
+        let mut positions = Vec::new();
+        
+        for _ in 0..4 {
+            press_ctrl(&mut buffer, KeyCode::Left);
+            positions.push(buffer.cursor());
+        }
+        for _ in 0..4 {
+            press_ctrl(&mut buffer, KeyCode::Right);
+            positions.push(buffer.cursor());
+        }
+        
+        assert_eq!(positions, [14, 4, 0, 0, 3, 13, 17, 17]);
+    }
+    
+    #[test]
+    fn ctrl_skips_separators_around_words() {
+        let mut buffer = PromptBuffer::new("  日本  語 ..".to_owned());
+        
+        press_ctrl(&mut buffer, KeyCode::Left);
+        assert_eq!(&buffer.text()[buffer.cursor()..], "語 ..");
+        press_ctrl(&mut buffer, KeyCode::Left);
+        assert_eq!(&buffer.text()[buffer.cursor()..], "日本  語 ..");
+        press_ctrl(&mut buffer, KeyCode::Right);
+        assert_eq!(&buffer.text()[buffer.cursor()..], "  語 ..");
+    }
+    
+    #[test]
+    fn only_accepted_characters_get_typed() {
+        let mut buffer = PromptBuffer::default();
+        
+        for c in "12xyab".chars() {
+            let used = buffer.handle_key(KeyEvent::from(KeyCode::Char(c)), |c| c.is_ascii_hexdigit());
+            assert_eq!(used, c.is_ascii_hexdigit(), "{c}");
+        }
+        assert_eq!(buffer.text(), "12ab");
+        
+        // left to the prompt
+        assert!(!press(&mut buffer, KeyCode::Enter));
+        assert!(!press(&mut buffer, KeyCode::Up));
+    }
+}
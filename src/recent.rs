@@ -0,0 +1,248 @@
+use std::{fs, io::stdout, path::PathBuf};
+
+use anyhow::Result;
+use crossterm::{
+    event::{self, Event, KeyCode, KeyModifiers}, execute, terminal::{self, EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode}
+};
+
+use crate::{cfg::Config, session::SessionStore, ui::{draw_recent_files, recent_files_rows}};
+
+/// File from the session store, as listed in the picker
+#[derive(Debug)]
+pub struct RecentFile {
+    pub path: PathBuf,
+    /// Seconds since the Unix epoch
+    pub last_opened: u64,
+    /// Size of the file on disk, or None if it doesn't exist anymore
+    pub size: Option<u64>,
+}
+
+/// List of recently opened files to pick from when lesbin is started without a path
+#[derive(Debug)]
+pub struct RecentFiles {
+    pub files: Vec<RecentFile>,
+    pub selected: usize,
+    /// Position of the topmost visible file
+    pub scroll: usize,
+    pub message: Option<String>,
+}
+
+impl RecentFiles {
+    fn new(session: &SessionStore) -> Self {
+        let files = session.recent_files().into_iter()
+            .map(|position| RecentFile {
+                path: position.path.clone(),
+                last_opened: position.last_opened,
+                size: fs::metadata(&position.path).ok().filter(|metadata| metadata.is_file()).map(|metadata| metadata.len()),
+            })
+            .collect();
+        
+        Self {
+            files,
+            selected: 0,
+            scroll: 0,
+            message: None,
+        }
+    }
+    
+    /// Moves the selection to the file before or after it, staying within the list
+    fn move_selection(&mut self, down: bool) {
+        self.selected = if down {
+            usize::min(self.selected + 1, self.files.len().saturating_sub(1))
+        } else {
+            self.selected.saturating_sub(1)
+        };
+    }
+    
+    /// Takes the selected file out of the list, selecting the one after it (or the new last one) instead
+    fn remove_selected(&mut self) -> Option<RecentFile> {
+        if self.selected >= self.files.len() {
+            return None;
+        }
+        
+        let file = self.files.remove(self.selected);
+        self.selected = usize::min(self.selected, self.files.len().saturating_sub(1));
+        Some(file)
+    }
+    
+    /// Scrolls just far enough for the selected file to be among the `visible_rows` shown,
+    /// without leaving empty rows at the bottom after files at the end have been removed
+    fn scroll_to_selection(&mut self, visible_rows: usize) {
+        self.scroll = usize::min(self.scroll, self.files.len().saturating_sub(visible_rows));
+        
+        if self.selected < self.scroll {
+            self.scroll = self.selected;
+        } else if self.selected >= self.scroll + visible_rows {
+            self.scroll = (self.selected + 1).saturating_sub(visible_rows);
+        }
+    }
+}
+
+/// Shows the recently opened files until one of them gets picked.
+/// Returns None if the user quits instead. Files removed from the list are forgotten by `session` as well.
+pub fn pick(config: &Config, session: &mut SessionStore) -> Result<Option<PathBuf>> {
+    enable_raw_mode()?;
+    execute!(stdout(), EnterAlternateScreen)?;
+    
+    let result = run(config, session);
+    let result2 = execute!(stdout(), LeaveAlternateScreen);
+    disable_raw_mode()?;
+    
+    result2?;
+    result
+}
+
+fn run(config: &Config, session: &mut SessionStore) -> Result<Option<PathBuf>> {
+    let mut picker = RecentFiles::new(session);
+    
+    loop {
+        let (_, height) = terminal::size()?;
+        picker.scroll_to_selection(recent_files_rows(config, height));
+        draw_recent_files(config, &picker)?;
+        
+        let Event::Key(key_event) = event::read()? else {
+            continue;
+        };
+        
+        // messages only stay until the next key press
+        picker.message = None;
+        
+        match key_event.code {
+            KeyCode::Esc => return Ok(None),
+            KeyCode::Char('c') if key_event.modifiers.contains(KeyModifiers::CONTROL) => return Ok(None),
+            KeyCode::Up => picker.move_selection(false),
+            KeyCode::Down => picker.move_selection(true),
+            KeyCode::Enter => {
+                let Some(file) = picker.files.get(picker.selected) else {
+                    continue;
+                };
+                
+                if file.size.is_some() {
+                    return Ok(Some(file.path.clone()));
+                }
+                picker.message = Some(format!("'{}' doesn't exist anymore", file.path.display()));
+            },
+            KeyCode::Char('d' | 'D') => {
+                let Some(file) = picker.remove_selected() else {
+                    continue;
+                };
+                
+                session.remove(&file.path);
+                if let Err(err) = session.save() {
+                    picker.message = Some(format!("Error: Could not update the recent files: {err}"));
+                }
+            },
+            _ => {},
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, process};
+    
+    use super::*;
+    
+    /// Picker listing `count` files, none of which exist
+    fn picker_with(count: usize) -> RecentFiles {
+        RecentFiles {
+            files: (0..count).map(|i| RecentFile { path: PathBuf::from(format!("file{i}")), last_opened: 0, size: None }).collect(),
+            selected: 0,
+            scroll: 0,
+            message: None,
+        }
+    }
+    
+    fn names(picker: &RecentFiles) -> Vec<String> {
+        picker.files.iter().map(|file| file.path.display().to_string()).collect()
+    }
+    
+    #[test]
+    fn missing_files_have_no_size() {
+        let dir = env::temp_dir();
+        let kept = dir.join(format!("lesbin-recent-kept-test-{}", process::id()));
+        let removed = dir.join(format!("lesbin-recent-removed-test-{}", process::id()));
+        fs::write(&kept, [0; 5]).unwrap();
+        fs::write(&removed, [0; 7]).unwrap();
+        
+        let mut session = SessionStore::default();
+        session.update(&kept, 0, None, None);
+        session.update(&removed, 0, None, None);
+        fs::remove_file(&removed).unwrap();
+        
+        let picker = RecentFiles::new(&session);
+        fs::remove_file(&kept).unwrap();
+        
+        let sizes: Vec<_> = picker.files.iter().map(|file| (file.path.file_name().unwrap().to_owned(), file.size)).collect();
+        assert_eq!(sizes.len(), 2);
+        assert!(sizes.contains(&(kept.file_name().unwrap().to_owned(), Some(5))));
+        assert!(sizes.contains(&(removed.file_name().unwrap().to_owned(), None)));
+        assert_eq!((picker.selected, picker.scroll), (0, 0));
+    }
+    
+    #[test]
+    fn selection_stays_within_the_list() {
+        let mut picker = picker_with(2);
+        picker.move_selection(false);
+        assert_eq!(picker.selected, 0);
+        
+        picker.move_selection(true);
+        picker.move_selection(true);
+        assert_eq!(picker.selected, 1);
+        
+        let mut empty = picker_with(0);
+        empty.move_selection(true);
+        assert_eq!(empty.selected, 0);
+    }
+    
+    #[test]
+    fn removing_selects_the_next_file() {
+        let mut picker = picker_with(3);
+        picker.selected = 1;
+        
+        assert_eq!(picker.remove_selected().unwrap().path, PathBuf::from("file1"));
+        assert_eq!((names(&picker), picker.selected), (vec!["file0".to_owned(), "file2".to_owned()], 1));
+        
+        // the last file is followed by the one before it
+        assert_eq!(picker.remove_selected().unwrap().path, PathBuf::from("file2"));
+        assert_eq!(picker.selected, 0);
+        
+        assert_eq!(picker.remove_selected().unwrap().path, PathBuf::from("file0"));
+        assert_eq!(picker.selected, 0);
+        assert!(picker.remove_selected().is_none());
+    }
+    
+    #[test]
+    fn scrolls_to_keep_the_selection_visible() {
+        let mut picker = picker_with(10);
+        
+        for _ in 0..4 {
+            picker.move_selection(true);
+            picker.scroll_to_selection(3);
+        }
+        assert_eq!((picker.selected, picker.scroll), (4, 2));
+        
+        // scrolling back up only starts once the selection reaches the top
+        picker.move_selection(false);
+        picker.scroll_to_selection(3);
+        assert_eq!((picker.selected, picker.scroll), (3, 2));
+        picker.move_selection(false);
+        picker.move_selection(false);
+        picker.scroll_to_selection(3);
+        assert_eq!((picker.selected, picker.scroll), (1, 1));
+        
+        // removing files at the end of the list scrolls back to fill the rows again
+        picker.selected = 9;
+        picker.scroll_to_selection(3);
+        assert_eq!(picker.scroll, 7);
+        
+        picker.files.truncate(5);
+        picker.selected = 4;
+        picker.scroll_to_selection(3);
+        assert_eq!(picker.scroll, 2);
+        
+        // a terminal too small to show any files doesn't break anything
+        picker.scroll_to_selection(0);
+        assert_eq!(picker.scroll, 5);
+    }
+}
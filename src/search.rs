@@ -0,0 +1,340 @@
+use std::{sync::mpsc::{self, Receiver, TryRecvError}, thread};
+
+use anyhow::{Result, anyhow};
+
+use crate::buffer::{self, FileBuffer};
+
+/// Most matches that get listed, so searching for something common doesn't take forever
+pub const MAX_MATCHES: usize = 5000;
+
+enum SearchMessage {
+    Progress(usize),
+    Done(Result<Option<usize>>),
+}
+
+/// Search running on a background thread.
+/// Dropping it cancels the search, since the thread stops once nobody is listening anymore.
+pub struct Search {
+    pub needle: Vec<u8>,
+    
+    start: usize,
+    end: usize,
+    position: usize,
+    
+    receiver: Receiver<SearchMessage>,
+}
+
+impl Search {
+    pub fn start(buffer: &dyn FileBuffer, needle: Vec<u8>, start: usize) -> Result<Self> {
+        let mut snapshot = buffer.snapshot()?;
+        let end = snapshot.len();
+        
+        let (sender, receiver) = mpsc::channel();
+        let thread_needle = needle.clone();
+        
+        thread::spawn(move || {
+            let result = buffer::find(&mut *snapshot, &thread_needle, start, |position| {
+                sender.send(SearchMessage::Progress(position)).is_ok()
+            });
+            
+            // the receiver might be gone already, in which case nobody cares about the result
+            let _ = sender.send(SearchMessage::Done(result));
+        });
+        
+        Ok(Self {
+            needle,
+            start,
+            end,
+            position: start,
+            receiver,
+        })
+    }
+    
+    pub fn progress_percentage(&self) -> usize {
+        let total = self.end.saturating_sub(self.start);
+        if total == 0 {
+            return 100;
+        }
+        
+        (self.position - self.start) * 100 / total
+    }
+    
+    /// Processes the messages sent by the search thread so far.
+    /// Returns the result once the search is done.
+    pub fn poll(&mut self) -> Option<Result<Option<usize>>> {
+        loop {
+            match self.receiver.try_recv() {
+                Ok(SearchMessage::Progress(position)) => self.position = position,
+                Ok(SearchMessage::Done(result)) => return Some(result),
+                Err(TryRecvError::Empty) => return None,
+                Err(TryRecvError::Disconnected) => return Some(Err(anyhow!("Search stopped unexpectedly"))),
+            }
+        }
+    }
+}
+
+enum MatchMessage {
+    Progress(usize),
+    Found(usize),
+    Done(Result<()>),
+}
+
+/// Offsets of all matches of a needle (up to [`MAX_MATCHES`]), which get collected on a background thread.
+/// Dropping it cancels collecting them.
+pub struct MatchList {
+    pub needle: Vec<u8>,
+    pub offsets: Vec<usize>,
+    /// Set once the file has been changed, since the matches might not be there anymore
+    pub stale: bool,
+    
+    end: usize,
+    position: usize,
+    
+    /// None once all matches have been found
+    receiver: Option<Receiver<MatchMessage>>,
+}
+
+impl MatchList {
+    pub fn start(buffer: &dyn FileBuffer, needle: Vec<u8>) -> Result<Self> {
+        let mut snapshot = buffer.snapshot()?;
+        let end = snapshot.len();
+        
+        let (sender, receiver) = mpsc::channel();
+        let thread_needle = needle.clone();
+        
+        thread::spawn(move || {
+            let mut count = 0;
+            let result = buffer::find_all(
+                &mut *snapshot,
+                &thread_needle,
+                |position| sender.send(MatchMessage::Progress(position)).is_ok(),
+                |offset| {
+                    count += 1;
+                    sender.send(MatchMessage::Found(offset)).is_ok() && count < MAX_MATCHES
+                },
+            );
+            
+            let _ = sender.send(MatchMessage::Done(result));
+        });
+        
+        Ok(Self {
+            needle,
+            offsets: Vec::new(),
+            stale: false,
+            end,
+            position: 0,
+            receiver: Some(receiver),
+        })
+    }
+    
+    pub fn is_done(&self) -> bool {
+        self.receiver.is_none()
+    }
+    
+    /// Whether there might be more matches than the ones listed
+    pub fn is_truncated(&self) -> bool {
+        self.offsets.len() >= MAX_MATCHES
+    }
+    
+    pub fn progress_percentage(&self) -> usize {
+        if self.end == 0 {
+            return 100;
+        }
+        
+        self.position * 100 / self.end
+    }
+    
+    /// Adds the matches found by the background thread so far.
+    /// Returns an error if finding them failed, which also stops looking for more.
+    pub fn poll(&mut self) -> Result<()> {
+        let Some(receiver) = &self.receiver else {
+            return Ok(());
+        };
+        
+        loop {
+            match receiver.try_recv() {
+                Ok(MatchMessage::Progress(position)) => self.position = position,
+                Ok(MatchMessage::Found(offset)) => self.offsets.push(offset),
+                Ok(MatchMessage::Done(result)) => {
+                    self.receiver = None;
+                    self.position = self.end;
+                    return result;
+                },
+                Err(TryRecvError::Empty) => return Ok(()),
+                Err(TryRecvError::Disconnected) => {
+                    self.receiver = None;
+                    return Err(anyhow!("Search stopped unexpectedly"));
+                },
+            }
+        }
+    }
+}
+
+/// List of the matches of the last search shown below the hex view, filtered by the typed hex digits
+#[derive(Debug, Default)]
+pub struct MatchesPanel {
+    pub filter: String,
+    /// Position of the selected match among the ones matching the filter
+    pub selected: usize,
+    /// Position of the topmost visible match among the ones matching the filter
+    pub scroll: usize,
+}
+
+impl MatchesPanel {
+    /// Returns the offsets of the matches whose offset contains the filter when written in hex
+    pub fn filtered(&self, list: &MatchList) -> Vec<usize> {
+        let filter = self.filter.to_lowercase();
+        
+        list.offsets.iter()
+            .copied()
+            .filter(|offset| format!("{offset:x}").contains(&filter))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        ops::Range,
+        path::Path,
+        sync::{Arc, atomic::{AtomicUsize, Ordering}},
+        time::Duration,
+    };
+    
+    use super::*;
+    use crate::buffer::{InMemoryBuffer, SEARCH_CHUNK_SIZE};
+    
+    fn buffer(bytes: Vec<u8>) -> InMemoryBuffer {
+        InMemoryBuffer(Arc::new(bytes))
+    }
+    
+    fn search(bytes: &[u8], needle: &[u8], start: usize) -> Option<usize> {
+        let mut search = Search::start(&buffer(bytes.to_vec()), needle.to_vec(), start).unwrap();
+        
+        loop {
+            if let Some(result) = search.poll() {
+                return result.unwrap();
+            }
+            thread::yield_now();
+        }
+    }
+    
+    fn all_matches(bytes: &[u8], needle: &[u8]) -> Vec<usize> {
+        let mut list = MatchList::start(&buffer(bytes.to_vec()), needle.to_vec()).unwrap();
+        
+        while !list.is_done() {
+            list.poll().unwrap();
+            thread::yield_now();
+        }
+        list.offsets
+    }
+    
+    #[test]
+    fn match_across_chunk_boundary() {
+        let mut bytes = vec![0; SEARCH_CHUNK_SIZE + 10];
+        bytes[SEARCH_CHUNK_SIZE - 2..SEARCH_CHUNK_SIZE + 2].copy_from_slice(b"abcd");
+        
+        assert_eq!(search(&bytes, b"abcd", 0), Some(SEARCH_CHUNK_SIZE - 2));
+        assert_eq!(search(&bytes, b"abcd", SEARCH_CHUNK_SIZE - 1), None);
+        assert_eq!(all_matches(&bytes, b"abcd"), [SEARCH_CHUNK_SIZE - 2]);
+    }
+    
+    #[test]
+    fn overlapping_matches() {
+        let bytes = b"xaaaax";
+        
+        // searching again after a match finds the one overlapping it, but the list only has separate ones
+        assert_eq!(search(bytes, b"aa", 0), Some(1));
+        assert_eq!(search(bytes, b"aa", 2), Some(2));
+        assert_eq!(search(bytes, b"aa", 3), Some(3));
+        assert_eq!(search(bytes, b"aa", 4), None);
+        assert_eq!(all_matches(bytes, b"aa"), [1, 3]);
+    }
+    
+    #[test]
+    fn match_at_the_end_of_the_file() {
+        let bytes = b"abcdef";
+        
+        assert_eq!(search(bytes, b"ef", 0), Some(4));
+        assert_eq!(search(bytes, b"ef", 4), Some(4));
+        assert_eq!(search(bytes, b"efg", 0), None);
+        assert_eq!(search(bytes, b"ef", 6), None);
+        assert_eq!(all_matches(bytes, b"f"), [5]);
+    }
+    
+    #[test]
+    fn empty_needle_matches_nothing() {
+        assert_eq!(search(b"abc", b"", 0), None);
+        assert_eq!(all_matches(b"abc", b""), []);
+        
+        // and so does an empty file
+        assert_eq!(search(b"", b"a", 0), None);
+        assert_eq!(Search::start(&buffer(Vec::new()), b"a".to_vec(), 0).unwrap().progress_percentage(), 100);
+    }
+    
+    /// Zeroes that take a while to read, counting how many chunks have been read
+    struct SlowBuffer {
+        len: usize,
+        zeroes: Arc<Vec<u8>>,
+        reads: Arc<AtomicUsize>,
+    }
+    
+    impl FileBuffer for SlowBuffer {
+        fn len(&self) -> usize {
+            self.len
+        }
+        
+        fn read(&mut self, range: Range<usize>) -> Result<&[u8]> {
+            self.reads.fetch_add(1, Ordering::SeqCst);
+            thread::sleep(Duration::from_millis(5));
+            Ok(&self.zeroes[..range.len()])
+        }
+        
+        fn write_byte(&mut self, _offset: usize, _value: u8) -> Result<()> {
+            unimplemented!()
+        }
+        
+        fn save(&mut self, _path: &Path) -> Result<()> {
+            unimplemented!()
+        }
+        
+        fn grow(&mut self, _path: &Path, _new_len: usize) -> Result<()> {
+            unimplemented!()
+        }
+        
+        fn extend(&mut self, _new_len: usize) -> Result<()> {
+            unimplemented!()
+        }
+        
+        fn snapshot(&self) -> Result<Box<dyn FileBuffer>> {
+            Ok(Box::new(SlowBuffer { len: self.len, zeroes: self.zeroes.clone(), reads: self.reads.clone() }))
+        }
+    }
+    
+    #[test]
+    fn dropping_cancels_the_search() {
+        const CHUNKS: usize = 1000;
+        
+        let reads = Arc::new(AtomicUsize::new(0));
+        let buffer = SlowBuffer {
+            len: CHUNKS * SEARCH_CHUNK_SIZE,
+            zeroes: Arc::new(vec![0; SEARCH_CHUNK_SIZE + 1]),
+            reads: reads.clone(),
+        };
+        
+        let mut search = Search::start(&buffer, b"ab".to_vec(), 0).unwrap();
+        while reads.load(Ordering::SeqCst) == 0 {
+            thread::yield_now();
+        }
+        assert!(search.poll().is_none());
+        drop(search);
+        
+        // the thread notices on its next progress update and stops reading
+        thread::sleep(Duration::from_millis(50));
+        let reads_after_drop = reads.load(Ordering::SeqCst);
+        thread::sleep(Duration::from_millis(50));
+        
+        assert_eq!(reads.load(Ordering::SeqCst), reads_after_drop);
+        assert!(reads_after_drop < CHUNKS);
+    }
+}
@@ -0,0 +1,389 @@
+use anyhow::{Result, bail};
+
+use crate::buffer::FileBuffer;
+
+/// Longest section name that gets read, longer ones are cut off
+const MAX_NAME_LEN: usize = 0x40;
+
+/// Section of an executable, as listed in its section headers
+#[derive(Debug)]
+pub struct Section {
+    pub name: String,
+    pub offset: usize,
+    /// Size of the section in the file, which is zero for sections without data like .bss
+    pub size: usize,
+}
+
+/// Reads the section headers of an ELF or PE file.
+/// If the file is cut off, only the sections whose headers are still in it get returned.
+pub fn parse(buffer: &mut dyn FileBuffer) -> Result<Vec<Section>> {
+    let magic = buffer.read(0..usize::min(4, buffer.len()))?;
+    
+    if magic == b"\x7fELF" {
+        parse_elf(buffer)
+    } else if magic.starts_with(b"MZ") {
+        parse_pe(buffer)
+    } else {
+        bail!("not an ELF or PE file");
+    }
+}
+
+fn parse_elf(buffer: &mut dyn FileBuffer) -> Result<Vec<Section>> {
+    let ident = Reader::new(buffer, false).bytes(4, 2)?;
+    let is_64_bit = match ident[0] {
+        1 => false,
+        2 => true,
+        class => bail!("unknown ELF class {class}"),
+    };
+    let big_endian = match ident[1] {
+        1 => false,
+        2 => true,
+        encoding => bail!("unknown ELF data encoding {encoding}"),
+    };
+    
+    let mut reader = Reader::new(buffer, big_endian);
+    
+    let (table_offset, entry_size, mut count, names_index) = if is_64_bit {
+        (reader.u64(0x28)?, reader.u16(0x3a)?, reader.u16(0x3c)? as usize, reader.u16(0x3e)? as usize)
+    } else {
+        (reader.u32(0x20)? as u64, reader.u16(0x2e)?, reader.u16(0x30)? as usize, reader.u16(0x32)? as usize)
+    };
+    
+    if table_offset == 0 {
+        return Ok(Vec::new());
+    }
+    
+    let table_offset = usize::try_from(table_offset)?;
+    let entry_size = entry_size as usize;
+    if entry_size < if is_64_bit { 0x28 } else { 0x18 } {
+        bail!("section headers are too small ({entry_size} bytes)");
+    }
+    
+    let header_offset = |index: usize| table_offset.checked_add(index.checked_mul(entry_size)?);
+    
+    // files with a lot of sections store the count in the first section header instead
+    if count == 0 {
+        let size_offset = table_offset.saturating_add(if is_64_bit { 0x20 } else { 0x14 });
+        count = reader.word(size_offset, is_64_bit)?;
+    }
+    
+    // (offset of the name in the string table, offset, size) of every section whose header can be read
+    let mut headers = Vec::new();
+    for index in 0..count {
+        let Some(offset) = header_offset(index).filter(|&offset| offset.checked_add(entry_size).is_some_and(|end| end <= reader.len())) else {
+            break;
+        };
+        
+        let name_offset = reader.u32(offset)? as usize;
+        let section_type = reader.u32(offset + 4)?;
+        let (data_offset, size) = if is_64_bit {
+            (reader.u64(offset + 0x18)? as usize, reader.u64(offset + 0x20)? as usize)
+        } else {
+            (reader.u32(offset + 0x10)? as usize, reader.u32(offset + 0x14)? as usize)
+        };
+        
+        // SHT_NOBITS sections take up no space in the file
+        let size = if section_type == 8 { 0 } else { size };
+        headers.push((name_offset, data_offset, size));
+    }
+    
+    if headers.is_empty() && count > 0 {
+        bail!("the section headers lie past the end of the file");
+    }
+    
+    let names_start = headers.get(names_index).map(|&(_, offset, _)| offset);
+    
+    let sections = headers.iter().enumerate()
+        // the first section header is always empty
+        .skip(1)
+        .map(|(index, &(name_offset, offset, size))| {
+            let name = names_start
+                .and_then(|start| reader.name(start.checked_add(name_offset)?))
+                .unwrap_or_else(|| format!("#{index}"));
+            
+            Section { name, offset, size }
+        })
+        .collect();
+    
+    Ok(sections)
+}
+
+fn parse_pe(buffer: &mut dyn FileBuffer) -> Result<Vec<Section>> {
+    let mut reader = Reader::new(buffer, false);
+    
+    let pe_offset = reader.u32(0x3c)? as usize;
+    if reader.bytes(pe_offset, 4)? != b"PE\0\0" {
+        bail!("no PE header, this is only a DOS executable");
+    }
+    
+    let count = reader.u16(pe_offset + 6)? as usize;
+    let optional_header_size = reader.u16(pe_offset + 20)? as usize;
+    let table_offset = pe_offset + 24 + optional_header_size;
+    
+    let mut sections = Vec::new();
+    for index in 0..count {
+        let offset = table_offset + index * 40;
+        if offset + 40 > reader.len() {
+            break;
+        }
+        
+        let name_bytes = reader.bytes(offset, 8)?;
+        let name_len = name_bytes.iter().position(|&byte| byte == 0).unwrap_or(name_bytes.len());
+        let name = String::from_utf8_lossy(&name_bytes[..name_len]).into_owned();
+        
+        sections.push(Section {
+            name,
+            offset: reader.u32(offset + 20)? as usize,
+            size: reader.u32(offset + 16)? as usize,
+        });
+    }
+    
+    if sections.is_empty() && count > 0 {
+        bail!("the section headers lie past the end of the file");
+    }
+    
+    Ok(sections)
+}
+
+/// Reads numbers from the buffer, failing instead of panicking when they're past the end
+struct Reader<'a> {
+    buffer: &'a mut dyn FileBuffer,
+    big_endian: bool,
+}
+
+impl<'a> Reader<'a> {
+    fn new(buffer: &'a mut dyn FileBuffer, big_endian: bool) -> Self {
+        Self { buffer, big_endian }
+    }
+    
+    fn len(&self) -> usize {
+        self.buffer.len()
+    }
+    
+    fn bytes(&mut self, offset: usize, len: usize) -> Result<Vec<u8>> {
+        match offset.checked_add(len) {
+            Some(end) if end <= self.buffer.len() => Ok(self.buffer.read(offset..end)?.to_vec()),
+            _ => bail!("the headers are cut off at 0x{offset:x}"),
+        }
+    }
+    
+    fn array<const N: usize>(&mut self, offset: usize) -> Result<[u8; N]> {
+        let bytes = self.bytes(offset, N)?;
+        Ok(bytes.try_into().expect("Length should match"))
+    }
+    
+    fn u16(&mut self, offset: usize) -> Result<u16> {
+        let bytes = self.array(offset)?;
+        Ok(if self.big_endian { u16::from_be_bytes(bytes) } else { u16::from_le_bytes(bytes) })
+    }
+    
+    fn u32(&mut self, offset: usize) -> Result<u32> {
+        let bytes = self.array(offset)?;
+        Ok(if self.big_endian { u32::from_be_bytes(bytes) } else { u32::from_le_bytes(bytes) })
+    }
+    
+    fn u64(&mut self, offset: usize) -> Result<u64> {
+        let bytes = self.array(offset)?;
+        Ok(if self.big_endian { u64::from_be_bytes(bytes) } else { u64::from_le_bytes(bytes) })
+    }
+    
+    /// Reads a 32 or 64 bit number, depending on the ELF class
+    fn word(&mut self, offset: usize, is_64_bit: bool) -> Result<usize> {
+        Ok(if is_64_bit { usize::try_from(self.u64(offset)?)? } else { self.u32(offset)? as usize })
+    }
+    
+    /// Reads a null-terminated name, or returns None if it's past the end of the file
+    fn name(&mut self, offset: usize) -> Option<String> {
+        if offset >= self.len() {
+            return None;
+        }
+        
+        let bytes = self.bytes(offset, usize::min(MAX_NAME_LEN, self.len() - offset)).ok()?;
+        let len = bytes.iter().position(|&byte| byte == 0).unwrap_or(bytes.len());
+        Some(String::from_utf8_lossy(&bytes[..len]).into_owned())
+    }
+}
+
+/// List of sections to jump to, filtered by what has been typed
+#[derive(Debug)]
+pub struct SectionsPanel {
+    pub sections: Vec<Section>,
+    pub filter: String,
+    /// Position of the selected section among the ones matching the filter
+    pub selected: usize,
+    /// Position of the topmost visible section among the ones matching the filter
+    pub scroll: usize,
+}
+
+impl SectionsPanel {
+    pub fn new(sections: Vec<Section>) -> Self {
+        Self {
+            sections,
+            filter: String::new(),
+            selected: 0,
+            scroll: 0,
+        }
+    }
+    
+    /// Returns the sections whose name contains the filter, ignoring case
+    pub fn matches(&self) -> Vec<&Section> {
+        let filter = self.filter.to_lowercase();
+        
+        self.sections.iter()
+            .filter(|section| section.name.to_lowercase().contains(&filter))
+            .collect()
+    }
+    
+    pub fn selected_section(&self) -> Option<&Section> {
+        self.matches().get(self.selected).copied()
+    }
+    
+    pub fn set_filter(&mut self, filter: String) {
+        self.filter = filter;
+        self.selected = 0;
+        self.scroll = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    
+    use super::*;
+    use crate::buffer::InMemoryBuffer;
+    
+    /// ELF files with a .text, a .bss and a .shstrtab section, with the section headers at the end
+    const ELF64_LE: &[u8] = include_bytes!("../test-data/sections/elf64-le.bin");
+    const ELF64_BE: &[u8] = include_bytes!("../test-data/sections/elf64-be.bin");
+    const ELF32_LE: &[u8] = include_bytes!("../test-data/sections/elf32-le.bin");
+    const ELF32_BE: &[u8] = include_bytes!("../test-data/sections/elf32-be.bin");
+    /// PE32+ file with a .text and a .data section, of which only the headers are there
+    const PE: &[u8] = include_bytes!("../test-data/sections/pe32plus.bin");
+    
+    /// Overwrites a little endian `size` byte number at `offset` of a fixture
+    fn put(bytes: &mut [u8], offset: usize, value: u64, size: usize) {
+        bytes[offset..offset + size].copy_from_slice(&value.to_le_bytes()[..size]);
+    }
+    
+    fn parse_bytes(bytes: Vec<u8>) -> Result<Vec<Section>> {
+        parse(&mut InMemoryBuffer(Arc::new(bytes)))
+    }
+    
+    fn summary(sections: &[Section]) -> Vec<(&str, usize, usize)> {
+        sections.iter().map(|section| (section.name.as_str(), section.offset, section.size)).collect()
+    }
+    
+    #[test]
+    fn elf_sections_in_every_class_and_byte_order() {
+        for bytes in [ELF64_LE, ELF64_BE] {
+            let sections = parse_bytes(bytes.to_vec()).unwrap();
+            assert_eq!(summary(&sections), [(".text", 0x56, 0x30), (".bss", 0x86, 0), (".shstrtab", 0x40, 0x16)]);
+        }
+        
+        for bytes in [ELF32_LE, ELF32_BE] {
+            let sections = parse_bytes(bytes.to_vec()).unwrap();
+            assert_eq!(summary(&sections), [(".text", 0x4a, 0x30), (".bss", 0x7a, 0), (".shstrtab", 0x34, 0x16)]);
+        }
+    }
+    
+    #[test]
+    fn elf_without_string_table_numbers_the_sections() {
+        let mut bytes = ELF64_LE.to_vec();
+        put(&mut bytes, 0x3e, 9, 2);
+        
+        let sections = parse_bytes(bytes).unwrap();
+        assert_eq!(sections.iter().map(|section| section.name.as_str()).collect::<Vec<_>>(), ["#1", "#2", "#3"]);
+    }
+    
+    #[test]
+    fn elf_without_section_headers_has_no_sections() {
+        let mut bytes = ELF64_LE.to_vec();
+        put(&mut bytes, 0x28, 0, 8);
+        
+        assert!(parse_bytes(bytes).unwrap().is_empty());
+    }
+    
+    #[test]
+    fn cut_off_elf_keeps_the_complete_headers() {
+        let bytes = ELF64_LE.to_vec();
+        // cuts into the header of .shstrtab, which also makes the names unavailable
+        let sections = parse_bytes(bytes[..bytes.len() - 0x20].to_vec()).unwrap();
+        
+        assert_eq!(summary(&sections), [("#1", 0x56, 0x30), ("#2", 0x86, 0)]);
+    }
+    
+    #[test]
+    fn malformed_elf_headers_are_rejected() {
+        let mut bytes = ELF64_LE.to_vec();
+        bytes[4] = 3;
+        assert!(parse_bytes(bytes).is_err());
+        
+        let mut bytes = ELF64_LE.to_vec();
+        bytes[5] = 0;
+        assert!(parse_bytes(bytes).is_err());
+        
+        let mut bytes = ELF64_LE.to_vec();
+        put(&mut bytes, 0x3a, 0x10, 2);
+        assert!(parse_bytes(bytes).is_err());
+        
+        let mut bytes = ELF32_LE.to_vec();
+        put(&mut bytes, 0x20, 0x1000, 4);
+        assert!(parse_bytes(bytes).is_err());
+    }
+    
+    #[test]
+    fn huge_elf_offsets_dont_overflow() {
+        for table_offset in [u64::MAX, u64::MAX - 0x10, u64::MAX / 2] {
+            let mut bytes = ELF64_LE.to_vec();
+            put(&mut bytes, 0x28, table_offset, 8);
+            assert!(parse_bytes(bytes.clone()).is_err(), "{table_offset:x}");
+            
+            // with the count stored in the first section header
+            put(&mut bytes, 0x3c, 0, 2);
+            assert!(parse_bytes(bytes).is_err(), "{table_offset:x}");
+        }
+    }
+    
+    #[test]
+    fn pe_sections() {
+        let sections = parse_bytes(PE.to_vec()).unwrap();
+        
+        assert_eq!(summary(&sections), [(".text", 0x400, 0x200), (".data", 0x600, 0x80)]);
+    }
+    
+    #[test]
+    fn dos_executables_are_rejected() {
+        let mut bytes = PE.to_vec();
+        bytes[0x40..0x44].copy_from_slice(b"NE\0\0");
+        assert!(parse_bytes(bytes).is_err());
+        
+        let mut bytes = PE.to_vec();
+        put(&mut bytes, 0x3c, 0xffff_fff0, 4);
+        assert!(parse_bytes(bytes).is_err());
+    }
+    
+    #[test]
+    fn other_files_are_rejected() {
+        assert!(parse_bytes(Vec::new()).is_err());
+        assert!(parse_bytes(b"\x7fEL".to_vec()).is_err());
+        assert!(parse_bytes(b"PK\x03\x04".to_vec()).is_err());
+    }
+    
+    #[test]
+    fn truncated_files_never_panic() {
+        for bytes in [ELF64_LE.to_vec(), ELF32_BE.to_vec(), PE.to_vec()] {
+            for len in 0..bytes.len() {
+                let _ = parse_bytes(bytes[..len].to_vec());
+            }
+        }
+    }
+    
+    #[cfg(any(target_os = "linux", windows))]
+    #[test]
+    fn own_executable_has_a_text_section() {
+        let bytes = std::fs::read(std::env::current_exe().unwrap()).unwrap();
+        let sections = parse_bytes(bytes).unwrap();
+        
+        assert!(sections.iter().any(|section| section.name == ".text"));
+    }
+}
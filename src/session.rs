@@ -0,0 +1,157 @@
+use std::{
+    cmp::Reverse,
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+
+/// Files that haven't been opened for this long are forgotten
+const MAX_SESSION_AGE: Duration = Duration::from_secs(90 * 24 * 60 * 60);
+
+/// Most files that are remembered, the ones opened longest ago get dropped first
+const MAX_SESSIONS: usize = 200;
+
+/// Where the view of a file was left when lesbin was last closed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FilePosition {
+    /// Canonical path of the file
+    pub path: PathBuf,
+    /// Seconds since the Unix epoch
+    pub last_opened: u64,
+    pub scroll_pos: usize,
+    /// Offset of the byte under the cursor, or None in pager mode
+    pub cursor: Option<usize>,
+    /// Offset where the selected range starts, if one is selected
+    pub anchor: Option<usize>,
+    /// Size of the file when it was left, to notice when it has been replaced since
+    #[serde(default)]
+    pub len: u64,
+    /// Modification time of the file in nanoseconds since the Unix epoch when it was left
+    #[serde(default)]
+    pub mtime: u64,
+}
+
+/// Positions of recently opened files, stored in the platform's data directory
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SessionStore {
+    #[serde(default)]
+    files: Vec<FilePosition>,
+}
+
+impl SessionStore {
+    /// Loads the stored positions, starting over with none if they can't be read for any reason
+    pub fn load() -> Self {
+        let Some(path) = store_path() else {
+            return Self::default();
+        };
+        
+        fs::read_to_string(path).ok()
+            .and_then(|content| toml::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+    
+    /// Writes the positions back, dropping the ones of files that haven't been opened in a long time
+    pub fn save(&mut self) -> Result<()> {
+        let path = store_path().ok_or_else(|| anyhow!("Could not find the data directory"))?;
+        
+        let now = unix_time();
+        self.files.retain(|position| now.saturating_sub(position.last_opened) < MAX_SESSION_AGE.as_secs());
+        self.files.sort_by_key(|position| Reverse(position.last_opened));
+        self.files.truncate(MAX_SESSIONS);
+        
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, toml::to_string(self)?)?;
+        Ok(())
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+    
+    /// All remembered files, most recently opened first
+    pub fn recent_files(&self) -> Vec<&FilePosition> {
+        let mut files: Vec<&FilePosition> = self.files.iter().collect();
+        files.sort_by_key(|position| Reverse(position.last_opened));
+        files
+    }
+    
+    /// Forgets about the file at `path`, which has to be the canonical path as stored
+    pub fn remove(&mut self, path: &Path) {
+        self.files.retain(|position| position.path != path);
+    }
+    
+    /// Returns the position the file at `path` was left at, if it's remembered.
+    /// Files that have been changed since then are treated as new ones, since the position might not make sense anymore.
+    pub fn get(&self, path: &Path) -> Option<&FilePosition> {
+        let path = fs::canonicalize(path).ok()?;
+        let (len, mtime) = file_version(&path)?;
+        
+        self.files.iter().find(|position| position.path == path && position.len == len && position.mtime == mtime)
+    }
+    
+    /// Remembers where the file at `path` is being left at, replacing what was stored for it before
+    pub fn update(&mut self, path: &Path, scroll_pos: usize, cursor: Option<usize>, anchor: Option<usize>) {
+        // paths that aren't valid UTF-8 can't be stored in TOML
+        let Some(path) = fs::canonicalize(path).ok().filter(|path| path.to_str().is_some()) else {
+            return;
+        };
+        let Some((len, mtime)) = file_version(&path) else {
+            return;
+        };
+        
+        self.files.retain(|position| position.path != path);
+        self.files.push(FilePosition { path, last_opened: unix_time(), scroll_pos, cursor, anchor, len, mtime });
+    }
+}
+
+/// Directory where lesbin keeps the data it remembers between runs
+pub fn data_dir() -> Option<PathBuf> {
+    Some(dirs::data_dir()?.join("lesbin"))
+}
+
+/// Size and modification time of a file, which change when it gets written to or replaced
+fn file_version(path: &Path) -> Option<(u64, u64)> {
+    let metadata = fs::metadata(path).ok()?;
+    let mtime = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+    Some((metadata.len(), u64::try_from(mtime.as_nanos()).ok()?))
+}
+
+fn store_path() -> Option<PathBuf> {
+    Some(data_dir()?.join("sessions.toml"))
+}
+
+/// Current time in seconds since the Unix epoch
+pub fn unix_time() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |duration| duration.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, process};
+    
+    use super::*;
+    
+    #[test]
+    fn changed_files_are_forgotten() {
+        let path = env::temp_dir().join(format!("lesbin-session-test-{}", process::id()));
+        fs::write(&path, [0; 0x40]).unwrap();
+        
+        let mut store = SessionStore::default();
+        store.update(&path, 2, Some(0x25), None);
+        
+        let position = store.get(&path).unwrap();
+        assert_eq!((position.scroll_pos, position.cursor), (2, Some(0x25)));
+        
+        // a different size is noticed even if the modification time ends up the same
+        fs::write(&path, [0; 0x20]).unwrap();
+        assert!(store.get(&path).is_none());
+        
+        fs::remove_file(&path).unwrap();
+        assert!(store.get(&path).is_none());
+    }
+}
@@ -0,0 +1,240 @@
+use anyhow::Result;
+
+use crate::buffer::FileBuffer;
+
+/// Amount of data scanned at once
+const SCAN_CHUNK_SIZE: usize = 1024 * 1024;
+
+/// A run of printable characters found in the file
+#[derive(Debug)]
+pub struct FoundString {
+    pub offset: usize,
+    pub text: String,
+    /// Whether the characters are UTF-16LE (every other byte is zero) instead of ASCII
+    pub utf16: bool,
+}
+
+/// Strings found in the file, sorted by offset
+#[derive(Debug)]
+pub struct StringList {
+    pub strings: Vec<FoundString>,
+    /// Set if there were more strings than `limit`, in which case only the first ones are kept
+    pub truncated: bool,
+}
+
+/// Finds all runs of at least `min_len` printable ASCII characters (plus tabs), like `strings(1)`.
+/// With `utf16` set, runs of UTF-16LE characters in the ASCII range are found as well.
+/// Scanning stops once `limit` strings have been found.
+pub fn extract(buffer: &mut dyn FileBuffer, min_len: usize, utf16: bool, limit: usize) -> Result<StringList> {
+    let min_len = min_len.max(1);
+    let len = buffer.len();
+    
+    let mut strings = Vec::new();
+    let mut ascii = RunScanner::new(false);
+    // UTF-16 strings can start at even or odd offsets
+    let mut utf16_scanners = [RunScanner::new(true), RunScanner::new(true)];
+    let mut prev_byte = None;
+    
+    for chunk_start in (0..len).step_by(SCAN_CHUNK_SIZE) {
+        let chunk = buffer.read(chunk_start..usize::min(chunk_start + SCAN_CHUNK_SIZE, len))?;
+        
+        for (i, &byte) in chunk.iter().enumerate() {
+            let offset = chunk_start + i;
+            
+            ascii.feed(is_printable(byte).then_some(byte), offset, min_len, &mut strings);
+            
+            if utf16 && let Some(low_byte) = prev_byte {
+                let char_offset = offset - 1;
+                let char = (byte == 0 && is_printable(low_byte)).then_some(low_byte);
+                utf16_scanners[char_offset % 2].feed(char, char_offset, min_len, &mut strings);
+            }
+            prev_byte = Some(byte);
+            
+            // one more than the limit shows that there are more
+            if strings.len() > limit {
+                strings.sort_by_key(|string| string.offset);
+                strings.truncate(limit);
+                return Ok(StringList { strings, truncated: true });
+            }
+        }
+    }
+    
+    ascii.finish(min_len, &mut strings);
+    for scanner in &mut utf16_scanners {
+        scanner.finish(min_len, &mut strings);
+    }
+    
+    strings.sort_by_key(|string| string.offset);
+    let truncated = strings.len() > limit;
+    strings.truncate(limit);
+    
+    Ok(StringList { strings, truncated })
+}
+
+fn is_printable(byte: u8) -> bool {
+    byte.is_ascii_graphic() || byte == b' ' || byte == b'\t'
+}
+
+/// Collects consecutive printable characters, reusing its buffer between runs
+struct RunScanner {
+    utf16: bool,
+    start: usize,
+    chars: Vec<u8>,
+}
+
+impl RunScanner {
+    fn new(utf16: bool) -> Self {
+        Self {
+            utf16,
+            start: 0,
+            chars: Vec::new(),
+        }
+    }
+    
+    /// Adds the next character at `offset`, or ends the current run if it's None
+    fn feed(&mut self, char: Option<u8>, offset: usize, min_len: usize, strings: &mut Vec<FoundString>) {
+        match char {
+            Some(char) => {
+                if self.chars.is_empty() {
+                    self.start = offset;
+                }
+                self.chars.push(char);
+            },
+            None => self.finish(min_len, strings),
+        }
+    }
+    
+    fn finish(&mut self, min_len: usize, strings: &mut Vec<FoundString>) {
+        if self.chars.len() >= min_len {
+            strings.push(FoundString {
+                offset: self.start,
+                // only printable ASCII gets collected, so this is always valid
+                text: String::from_utf8_lossy(&self.chars).into_owned(),
+                utf16: self.utf16,
+            });
+        }
+        
+        self.chars.clear();
+    }
+}
+
+/// Scrollable list of strings, filtered by what has been typed
+#[derive(Debug)]
+pub struct StringsPanel {
+    pub list: StringList,
+    pub filter: String,
+    /// Position of the selected string among the ones matching the filter
+    pub selected: usize,
+    /// Position of the topmost visible string among the ones matching the filter
+    pub scroll: usize,
+}
+
+impl StringsPanel {
+    pub fn new(list: StringList) -> Self {
+        Self {
+            list,
+            filter: String::new(),
+            selected: 0,
+            scroll: 0,
+        }
+    }
+    
+    /// Returns the strings containing the filter, ignoring case
+    pub fn matches(&self) -> Vec<&FoundString> {
+        let filter = self.filter.to_lowercase();
+        
+        self.list.strings.iter()
+            .filter(|string| filter.is_empty() || string.text.to_lowercase().contains(&filter))
+            .collect()
+    }
+    
+    pub fn selected_string(&self) -> Option<&FoundString> {
+        self.matches().get(self.selected).copied()
+    }
+    
+    pub fn set_filter(&mut self, filter: String) {
+        self.filter = filter;
+        self.selected = 0;
+        self.scroll = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    
+    use super::*;
+    use crate::buffer::InMemoryBuffer;
+    
+    fn found(bytes: &[u8], min_len: usize, utf16: bool, limit: usize) -> (Vec<(usize, String, bool)>, bool) {
+        let list = extract(&mut InMemoryBuffer(Arc::new(bytes.to_vec())), min_len, utf16, limit).unwrap();
+        let strings = list.strings.into_iter().map(|string| (string.offset, string.text, string.utf16)).collect();
+        (strings, list.truncated)
+    }
+    
+    fn texts(bytes: &[u8], min_len: usize, utf16: bool) -> Vec<String> {
+        found(bytes, min_len, utf16, usize::MAX).0.into_iter().map(|(_, text, _)| text).collect()
+    }
+    
+    #[test]
+    fn shorter_runs_are_skipped() {
+        let bytes = b"ab\0abcd\x01abc\xff";
+        
+        assert_eq!(found(bytes, 3, false, 10).0, [(3, "abcd".to_owned(), false), (8, "abc".to_owned(), false)]);
+        assert_eq!(texts(bytes, 4, false), ["abcd"]);
+        assert_eq!(texts(bytes, 5, false), Vec::<String>::new());
+        // every printable character is a string of its own then
+        assert_eq!(texts(b"a\0b", 0, false), ["a", "b"]);
+    }
+    
+    #[test]
+    fn tabs_and_spaces_belong_to_strings() {
+        assert_eq!(texts(b"\nkey:\tsome value\r\n", 4, false), ["key:\tsome value"]);
+    }
+    
+    #[test]
+    fn utf16_strings() {
+        let bytes = b"\x01h\0e\0l\0l\0o\0\xff\xff";
+        
+        assert_eq!(found(bytes, 4, true, 10).0, [(1, "hello".to_owned(), true)]);
+        assert_eq!(texts(bytes, 4, false), Vec::<String>::new());
+        
+        // both alignments are found
+        assert_eq!(found(b"a\0b\0c\0\xffx\0y\0z\0", 3, true, 10).0, [(0, "abc".to_owned(), true), (7, "xyz".to_owned(), true)]);
+    }
+    
+    #[test]
+    fn ascii_and_utf16_strings_are_sorted_together() {
+        let bytes = b"w\0i\0d\0e\0\xffnarrow\xff";
+        
+        assert_eq!(found(bytes, 4, true, 10).0, [(0, "wide".to_owned(), true), (9, "narrow".to_owned(), false)]);
+    }
+    
+    #[test]
+    fn strings_can_run_to_the_end() {
+        assert_eq!(found(b"\0\0hello", 4, false, 10).0, [(2, "hello".to_owned(), false)]);
+        assert_eq!(found(b"\0e\0n\0d\0", 3, true, 10).0, [(1, "end".to_owned(), true)]);
+        assert_eq!(texts(b"whole", 4, true), ["whole"]);
+    }
+    
+    #[test]
+    fn strings_continue_across_chunks() {
+        let mut bytes = vec![0; SCAN_CHUNK_SIZE + 0x10];
+        bytes[SCAN_CHUNK_SIZE - 4..SCAN_CHUNK_SIZE + 4].copy_from_slice(b"straddle");
+        
+        assert_eq!(found(&bytes, 4, false, 10).0, [(SCAN_CHUNK_SIZE - 4, "straddle".to_owned(), false)]);
+    }
+    
+    #[test]
+    fn scanning_stops_at_the_limit() {
+        let bytes = b"one1\0two2\0three\0four\0";
+        
+        let (strings, truncated) = found(bytes, 4, false, 2);
+        assert_eq!(strings.iter().map(|(_, text, _)| text.as_str()).collect::<Vec<_>>(), ["one1", "two2"]);
+        assert!(truncated);
+        
+        let (strings, truncated) = found(bytes, 4, false, 4);
+        assert_eq!(strings.len(), 4);
+        assert!(!truncated);
+    }
+}
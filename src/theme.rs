@@ -0,0 +1,401 @@
+use std::{env, io::Write, sync::OnceLock};
+
+use crossterm::{queue, style::{Attribute, Color, ResetColor, SetAttribute, SetBackgroundColor, SetForegroundColor}};
+use serde::{Deserialize, de};
+
+use crate::util::LineColor;
+
+static THEME: OnceLock<Theme> = OnceLock::new();
+
+/// Sets the theme used for drawing. Can only be called once, before anything has been drawn.
+pub fn init(theme: Theme) {
+    let _ = THEME.set(theme);
+}
+
+/// Returns the theme set by [`init`], or the default one if there is none
+pub fn current() -> &'static Theme {
+    THEME.get_or_init(Theme::dark)
+}
+
+/// Colors and attributes of a piece of text
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+pub struct Style {
+    pub fg: Option<ThemeColor>,
+    pub bg: Option<ThemeColor>,
+    #[serde(default)]
+    pub bold: bool,
+    #[serde(default)]
+    pub reversed: bool,
+}
+
+impl Style {
+    /// Switches to this style, resetting everything else first
+    pub fn apply(self, out: &mut impl Write) -> std::io::Result<()> {
+        queue!(out, ResetColor)?;
+        
+        if let Some(ThemeColor(fg)) = self.fg {
+            queue!(out, SetForegroundColor(fg))?;
+        }
+        if let Some(ThemeColor(bg)) = self.bg {
+            queue!(out, SetBackgroundColor(bg))?;
+        }
+        if self.bold {
+            queue!(out, SetAttribute(Attribute::Bold))?;
+        }
+        if self.reversed {
+            queue!(out, SetAttribute(Attribute::Reverse))?;
+        }
+        
+        Ok(())
+    }
+    
+    fn degrade(self, mode: ColorMode) -> Self {
+        let degrade = |color: Option<ThemeColor>| color.map(|ThemeColor(color)| ThemeColor(degrade_color(color, mode)));
+        
+        Self {
+            fg: degrade(self.fg),
+            bg: degrade(self.bg),
+            ..self
+        }
+    }
+    
+    const fn fg(color: Color) -> Self {
+        Self {
+            fg: Some(ThemeColor(color)),
+            bg: None,
+            bold: false,
+            reversed: false,
+        }
+    }
+    
+    const fn fg_bg(fg: Color, bg: Color) -> Self {
+        Self {
+            fg: Some(ThemeColor(fg)),
+            bg: Some(ThemeColor(bg)),
+            bold: false,
+            reversed: false,
+        }
+    }
+}
+
+/// Color that can be written in the config as a name (`"dark_red"`),
+/// a 256 color index (`"206"`) or a hex code (`"#ffaf5f"`)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ThemeColor(pub Color);
+
+impl<'de> Deserialize<'de> for ThemeColor {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>
+    {
+        let string = String::deserialize(deserializer)?;
+        
+        parse_color(&string).map(ThemeColor).ok_or_else(|| de::Error::invalid_value(
+            de::Unexpected::Str(&string),
+            &"a color name, a number from 0 to 255 or a hex code like #ffaf5f",
+        ))
+    }
+}
+
+fn parse_color(string: &str) -> Option<Color> {
+    if let Some(hex) = string.strip_prefix('#') {
+        let rgb = u32::from_str_radix(hex, 16).ok().filter(|_| hex.len() == 6)?;
+        let [_, r, g, b] = rgb.to_be_bytes();
+        return Some(Color::Rgb { r, g, b });
+    }
+    
+    if let Ok(index) = string.parse::<u8>() {
+        return Some(Color::AnsiValue(index));
+    }
+    
+    let color = match string.to_ascii_lowercase().replace([' ', '-'], "_").as_str() {
+        "reset" | "default" => Color::Reset,
+        "black" => Color::Black,
+        "dark_grey" | "dark_gray" => Color::DarkGrey,
+        "red" => Color::Red,
+        "dark_red" => Color::DarkRed,
+        "green" => Color::Green,
+        "dark_green" => Color::DarkGreen,
+        "yellow" => Color::Yellow,
+        "dark_yellow" => Color::DarkYellow,
+        "blue" => Color::Blue,
+        "dark_blue" => Color::DarkBlue,
+        "magenta" => Color::Magenta,
+        "dark_magenta" => Color::DarkMagenta,
+        "cyan" => Color::Cyan,
+        "dark_cyan" => Color::DarkCyan,
+        "white" => Color::White,
+        "grey" | "gray" => Color::Grey,
+        _ => return None,
+    };
+    
+    Some(color)
+}
+
+/// Colors the terminal can display
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+pub enum ColorMode {
+    #[serde(rename = "16")]
+    Ansi16,
+    #[serde(rename = "256")]
+    Ansi256,
+    #[serde(rename = "truecolor")]
+    TrueColor,
+}
+
+impl ColorMode {
+    /// Guesses the color support from `$COLORTERM` and `$TERM`
+    pub fn detect() -> Self {
+        let colorterm = env::var("COLORTERM").unwrap_or_default();
+        if colorterm == "truecolor" || colorterm == "24bit" {
+            return ColorMode::TrueColor;
+        }
+        
+        match env::var("TERM") {
+            Ok(term) if term.contains("256color") => ColorMode::Ansi256,
+            Ok(term) if term.contains("truecolor") || term.contains("direct") => ColorMode::TrueColor,
+            Ok(_) => ColorMode::Ansi16,
+            // Windows terminals don't set $TERM but all recent ones understand every color
+            Err(_) if cfg!(windows) => ColorMode::TrueColor,
+            Err(_) => ColorMode::Ansi16,
+        }
+    }
+}
+
+/// The 16 ANSI colors in the order of their indices, with xterm's default RGB values
+const ANSI_COLORS: [(Color, [u8; 3]); 16] = [
+    (Color::Black, [0x00, 0x00, 0x00]),
+    (Color::DarkRed, [0xcd, 0x00, 0x00]),
+    (Color::DarkGreen, [0x00, 0xcd, 0x00]),
+    (Color::DarkYellow, [0xcd, 0xcd, 0x00]),
+    (Color::DarkBlue, [0x00, 0x00, 0xee]),
+    (Color::DarkMagenta, [0xcd, 0x00, 0xcd]),
+    (Color::DarkCyan, [0x00, 0xcd, 0xcd]),
+    (Color::Grey, [0xe5, 0xe5, 0xe5]),
+    (Color::DarkGrey, [0x7f, 0x7f, 0x7f]),
+    (Color::Red, [0xff, 0x00, 0x00]),
+    (Color::Green, [0x00, 0xff, 0x00]),
+    (Color::Yellow, [0xff, 0xff, 0x00]),
+    (Color::Blue, [0x5c, 0x5c, 0xff]),
+    (Color::Magenta, [0xff, 0x00, 0xff]),
+    (Color::Cyan, [0x00, 0xff, 0xff]),
+    (Color::White, [0xff, 0xff, 0xff]),
+];
+
+/// Levels of each channel in the 6x6x6 color cube of the 256 color palette
+const CUBE_LEVELS: [u8; 6] = [0x00, 0x5f, 0x87, 0xaf, 0xd7, 0xff];
+
+/// Replaces a color with the closest one that can be displayed in `mode`
+pub fn degrade_color(color: Color, mode: ColorMode) -> Color {
+    match (mode, color) {
+        (ColorMode::TrueColor, _) => color,
+        (ColorMode::Ansi256, Color::Rgb { r, g, b }) => Color::AnsiValue(nearest_256([r, g, b])),
+        (ColorMode::Ansi16, Color::Rgb { r, g, b }) => nearest_16([r, g, b]),
+        (ColorMode::Ansi16, Color::AnsiValue(index)) => match ANSI_COLORS.get(index as usize) {
+            Some((ansi_color, _)) => *ansi_color,
+            None => nearest_16(ansi_256_to_rgb(index)),
+        },
+        _ => color,
+    }
+}
+
+fn nearest_16(rgb: [u8; 3]) -> Color {
+    ANSI_COLORS.iter()
+        .min_by_key(|(_, ansi_rgb)| distance(rgb, *ansi_rgb))
+        .map(|(color, _)| *color)
+        .unwrap()
+}
+
+fn nearest_256(rgb: [u8; 3]) -> u8 {
+    let nearest_level = |channel: u8| {
+        (0..6).min_by_key(|&i| CUBE_LEVELS[i].abs_diff(channel)).unwrap()
+    };
+    
+    let [r, g, b] = rgb.map(nearest_level);
+    let cube_index = 16 + 36 * r + 6 * g + b;
+    
+    // the grayscale ramp goes from 0x08 to 0xee in steps of 10
+    let average = (rgb.iter().map(|&channel| channel as usize).sum::<usize>() / 3) as u8;
+    let gray_step = (average.saturating_sub(3) / 10).min(23);
+    let gray_index = 232 + gray_step as usize;
+    
+    if distance(rgb, ansi_256_to_rgb(gray_index as u8)) < distance(rgb, ansi_256_to_rgb(cube_index as u8)) {
+        gray_index as u8
+    } else {
+        cube_index as u8
+    }
+}
+
+fn ansi_256_to_rgb(index: u8) -> [u8; 3] {
+    match index {
+        0..16 => ANSI_COLORS[index as usize].1,
+        16..232 => {
+            let index = index - 16;
+            [index / 36, index / 6 % 6, index % 6].map(|level| CUBE_LEVELS[level as usize])
+        },
+        232.. => [8 + (index - 232) * 10; 3],
+    }
+}
+
+fn distance(a: [u8; 3], b: [u8; 3]) -> u32 {
+    a.iter().zip(b).map(|(&a, b)| (a.abs_diff(b) as u32).pow(2)).sum()
+}
+
+/// Style of every [`LineColor`]
+#[derive(Clone, Debug)]
+pub struct Theme {
+    pub regular: Style,
+    pub emphasis: Style,
+    pub highlighted: Style,
+    pub text_cursor: Style,
+    pub modified: Style,
+    pub different: Style,
+    pub address: Style,
+    pub zero: Style,
+    pub title: Style,
+    /// Color ramp for the entropy of the data, from low to high
+    pub entropy: [Style; ENTROPY_LEVELS],
+}
+
+pub const ENTROPY_LEVELS: usize = 8;
+
+impl Theme {
+    pub fn dark() -> Self {
+        Self {
+            regular: Style::default(),
+            emphasis: Style { bold: true, ..Style::fg(Color::AnsiValue(39)) },
+            highlighted: Style::fg_bg(Color::Black, Color::Grey),
+            text_cursor: Style { reversed: true, ..Style::default() },
+            modified: Style::fg(Color::AnsiValue(215)),
+            different: Style::fg(Color::AnsiValue(203)),
+            address: Style::fg(Color::AnsiValue(206)),
+            zero: Style::fg(Color::DarkGrey),
+            title: Style::fg_bg(Color::Black, Color::Rgb { r: 220, g: 220, b: 220 }),
+            entropy: [238, 241, 60, 67, 74, 117, 159, 231].map(|index| Style::fg(Color::AnsiValue(index))),
+        }
+    }
+    
+    /// Darker colors that stay readable on a light background
+    pub fn light() -> Self {
+        Self {
+            regular: Style::default(),
+            emphasis: Style { bold: true, ..Style::fg(Color::AnsiValue(25)) },
+            highlighted: Style::fg_bg(Color::White, Color::AnsiValue(240)),
+            text_cursor: Style { reversed: true, ..Style::default() },
+            modified: Style::fg(Color::AnsiValue(166)),
+            different: Style::fg(Color::AnsiValue(160)),
+            address: Style::fg(Color::AnsiValue(127)),
+            zero: Style::fg(Color::AnsiValue(247)),
+            title: Style::fg_bg(Color::White, Color::AnsiValue(238)),
+            entropy: [252, 249, 146, 110, 68, 32, 25, 17].map(|index| Style::fg(Color::AnsiValue(index))),
+        }
+    }
+    
+    /// Replaces all colors the terminal can't display with the closest ones it can
+    pub fn degrade(self, mode: ColorMode) -> Self {
+        Self {
+            regular: self.regular.degrade(mode),
+            emphasis: self.emphasis.degrade(mode),
+            highlighted: self.highlighted.degrade(mode),
+            text_cursor: self.text_cursor.degrade(mode),
+            modified: self.modified.degrade(mode),
+            different: self.different.degrade(mode),
+            address: self.address.degrade(mode),
+            zero: self.zero.degrade(mode),
+            title: self.title.degrade(mode),
+            entropy: self.entropy.map(|style| style.degrade(mode)),
+        }
+    }
+    
+    pub fn style(&self, color: LineColor) -> Style {
+        match color {
+            LineColor::Regular => self.regular,
+            LineColor::Emphasis => self.emphasis,
+            LineColor::Highlighted => self.highlighted,
+            LineColor::TextCursor => self.text_cursor,
+            LineColor::Modified => self.modified,
+            LineColor::Different => self.different,
+            LineColor::Address => self.address,
+            LineColor::Zero => self.zero,
+            LineColor::Title => self.title,
+            LineColor::Entropy(level) => self.entropy[level as usize],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn rgb(hex: u32) -> Color {
+        let [_, r, g, b] = hex.to_be_bytes();
+        Color::Rgb { r, g, b }
+    }
+    
+    #[test]
+    fn cube_corners_in_256_colors() {
+        let corners = [
+            (0x000000, 16),
+            (0x0000ff, 21),
+            (0x00ff00, 46),
+            (0x00ffff, 51),
+            (0xff0000, 196),
+            (0xff00ff, 201),
+            (0xffff00, 226),
+            (0xffffff, 231),
+        ];
+        
+        for (hex, index) in corners {
+            assert_eq!(degrade_color(rgb(hex), ColorMode::Ansi256), Color::AnsiValue(index), "#{hex:06x}");
+        }
+    }
+    
+    #[test]
+    fn palette_colors_map_to_themselves() {
+        for index in 16..=255 {
+            assert_eq!(nearest_256(ansi_256_to_rgb(index)), index, "{index}");
+        }
+    }
+    
+    #[test]
+    fn grays_use_the_grayscale_ramp() {
+        assert_eq!(nearest_256([0x08; 3]), 232);
+        assert_eq!(nearest_256([0x80; 3]), 244);
+        assert_eq!(nearest_256([0x81, 0x7f, 0x80]), 244);
+        assert_eq!(nearest_256([0xee; 3]), 255);
+        
+        // the ends of the ramp are closer to black and white in the cube
+        assert_eq!(nearest_256([0x01; 3]), 16);
+        assert_eq!(nearest_256([0xfe; 3]), 231);
+    }
+    
+    #[test]
+    fn basic_colors_in_16_colors() {
+        for (color, [r, g, b]) in ANSI_COLORS {
+            assert_eq!(degrade_color(Color::Rgb { r, g, b }, ColorMode::Ansi16), color);
+        }
+        
+        assert_eq!(degrade_color(rgb(0x101010), ColorMode::Ansi16), Color::Black);
+        assert_eq!(degrade_color(rgb(0x808080), ColorMode::Ansi16), Color::DarkGrey);
+        assert_eq!(degrade_color(rgb(0xfafafa), ColorMode::Ansi16), Color::White);
+        assert_eq!(degrade_color(rgb(0xf8f810), ColorMode::Ansi16), Color::Yellow);
+        assert_eq!(degrade_color(rgb(0x8000c0), ColorMode::Ansi16), Color::DarkMagenta);
+    }
+    
+    #[test]
+    fn palette_indices_in_16_colors() {
+        assert_eq!(degrade_color(Color::AnsiValue(1), ColorMode::Ansi16), Color::DarkRed);
+        assert_eq!(degrade_color(Color::AnsiValue(15), ColorMode::Ansi16), Color::White);
+        assert_eq!(degrade_color(Color::AnsiValue(196), ColorMode::Ansi16), Color::Red);
+        assert_eq!(degrade_color(Color::AnsiValue(232), ColorMode::Ansi16), Color::Black);
+        assert_eq!(degrade_color(Color::AnsiValue(255), ColorMode::Ansi16), Color::Grey);
+    }
+    
+    #[test]
+    fn colors_that_fit_are_kept() {
+        assert_eq!(degrade_color(rgb(0x123456), ColorMode::TrueColor), rgb(0x123456));
+        assert_eq!(degrade_color(Color::AnsiValue(100), ColorMode::Ansi256), Color::AnsiValue(100));
+        assert_eq!(degrade_color(Color::DarkCyan, ColorMode::Ansi16), Color::DarkCyan);
+        assert_eq!(degrade_color(Color::Reset, ColorMode::Ansi16), Color::Reset);
+    }
+}
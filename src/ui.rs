@@ -1,217 +1,1421 @@
-use std::io::stdout;
+use std::{fmt::Display, io::{Write, stdout}, ops::Range};
 
 use anyhow::Result;
-use crossterm::{cursor::{self, MoveTo}, execute, style::{Print, ResetColor, SetBackgroundColor, SetForegroundColor}, terminal};
+use crossterm::{cursor::{self, MoveTo}, event::{KeyCode, KeyEvent, KeyModifiers}, execute, queue, terminal::{self, Clear, ClearType}};
 use itertools::Itertools;
 
-use crate::{InputState, State, cfg::{Appearance, Config, Keybinds}, util::{LineColor, LineWriter}};
+use crate::{InputState, MAX_STRINGS, OpenFile, Pane, PendingAction, State, cfg::{Appearance, Bindings, Config, Keybind, Keybinds, NamedStatusItem, StatusItem}, encoding::{HALF_ROW, TextCell, TextEncoding}, entropy::entropy_level, decode::{Interpretation, MAX_DECODE_LEN}, recent::RecentFiles, session::unix_time, hash::{Digests, HASH_NAMES}, journal::Edit, buffer::FileBuffer, prompt::PromptBuffer, search::{MAX_MATCHES, MatchList, MatchesPanel}, sections::SectionsPanel, strings::StringsPanel, theme::ENTROPY_LEVELS, util::{LineColor, LineWriter, StyledWrite, display_width, elide_middle}};
 
 pub const PADDING_TOP: u16 = 1;
 pub const PADDING_BOTTOM: u16 = 2;
 
-pub fn draw(config: &Config, state: &mut State<'_>) -> Result<()> {
+/// Bytes shown before each match in the list of matches
+const MATCH_CONTEXT: usize = 4;
+/// Bytes shown for each match in the list of matches, including the ones before it
+const MATCH_PREVIEW_LEN: usize = 8;
+
+/// Draws the whole screen, which only gets written to the terminal once everything is in place
+pub fn draw(config: &Config, state: &mut State) -> Result<()> {
+    draw_frame(config, state)?;
+    stdout().flush()?;
+    Ok(())
+}
+
+fn draw_frame(config: &Config, state: &mut State) -> Result<()> {
     let (width, height) = terminal::size()?;
-    state.screen_height = height;
     
     // Draw status ui
-    execute!(
-        stdout(),
-        MoveTo(0, 0),
-        SetForegroundColor(crossterm::style::Color::Black),
-        SetBackgroundColor(crossterm::style::Color::Rgb { r: 220, g: 220, b: 220 }),
-        Print(state.file_name),
-        ResetColor,
-    )?;
+    draw_title(state, width)?;
     
-    draw_bottom(&config.keybinds, state, width, height - 2)?;
-    
-    // Draw main page
     let Appearance { margin_horizontal, margin_vertical, .. } = config.appearance;
+    let content_y = margin_vertical + PADDING_TOP;
+    let content_height = height.saturating_sub(state.total_vertical_padding);
     
-    for i in 0..height - (margin_vertical * 2 + PADDING_TOP + PADDING_BOTTOM) {
-        let absolute_row_idx = i as usize + state.scroll_pos;
-        if absolute_row_idx >= state.max_rows {
-            break;
-        }
+    // the list of matches takes up the bottom third below the hex view
+    let matches_height = if matches!(state.input_state, InputState::Matches(_)) { content_height / 3 } else { 0 };
+    layout_panes(state, content_y, content_height - matches_height);
+    state.clamp_scroll();
+    
+    draw_bottom(config, state, width, height - 2)?;
+    
+    if let InputState::Histogram { selected } = state.input_state {
+        let content_y = margin_vertical + PADDING_TOP;
+        let content_height = height.saturating_sub(state.total_vertical_padding);
         
-        draw_line(state, margin_horizontal, i + margin_vertical + PADDING_TOP, absolute_row_idx)?;
+        queue!(stdout(), cursor::Hide)?;
+        return draw_histogram(state, selected, margin_horizontal, content_y, content_height, width);
     }
     
-    // position text cursor
-    let mut text_cursor_shown = false;
+    if let InputState::Strings(panel) = &mut state.input_state {
+        let content_y = margin_vertical + PADDING_TOP;
+        let content_height = height.saturating_sub(state.total_vertical_padding);
+        
+        queue!(stdout(), cursor::Hide)?;
+        return draw_strings(panel, margin_horizontal, content_y, content_height, width);
+    }
+    
+    if let InputState::Sections(panel) = &mut state.input_state {
+        let content_y = margin_vertical + PADDING_TOP;
+        let content_height = height.saturating_sub(state.total_vertical_padding);
+        
+        queue!(stdout(), cursor::Hide)?;
+        return draw_sections(panel, margin_horizontal, content_y, content_height, width);
+    }
     
-    if let Some((row, col)) = state.selection && row >= state.scroll_pos {
-        let relative_y = (row - state.scroll_pos) as u16;
+    if let InputState::Decoded { range, interpretations, selected } = &state.input_state {
+        let content_y = margin_vertical + PADDING_TOP;
+        let content_height = height.saturating_sub(state.total_vertical_padding);
         
-        if (relative_y as usize) < state.visible_content_rows() {
-            text_cursor_shown = true;
+        queue!(stdout(), cursor::Hide)?;
+        return draw_decoded(range, interpretations, *selected, margin_horizontal, content_y, content_height, width);
+    }
+    
+    if let (InputState::RecoveredEdits { scroll }, Some(edits)) = (&mut state.input_state, &state.file.recovered_edits) {
+        let content_y = margin_vertical + PADDING_TOP;
+        let content_height = height.saturating_sub(state.total_vertical_padding);
+        
+        queue!(stdout(), cursor::Hide)?;
+        return draw_recovered_edits(edits, scroll, margin_horizontal, content_y, content_height, width);
+    }
+    
+    if let InputState::Hashes { digests, selected } = &state.input_state {
+        let content_y = margin_vertical + PADDING_TOP;
+        let content_height = height.saturating_sub(state.total_vertical_padding);
+        
+        queue!(stdout(), cursor::Hide)?;
+        return draw_hashes(digests, *selected, margin_horizontal, content_y, content_height, width);
+    }
+    
+    // Draw main page
+    let row_count = state.content_rows();
+    let mut row_writer = LineWriter::new(margin_horizontal, 0);
+    
+    for pane_idx in 0..state.file.panes.len() {
+        let Pane { scroll_pos, y, height, .. } = state.file.panes[pane_idx];
+        let selected_range = state.file.panes[pane_idx].selected_range(state.file.bytes.len());
+        
+        for i in 0..height {
+            let absolute_row_idx = i as usize + scroll_pos;
             
-            let mut screen_x = (col / 2 * 3) + col % 2 + 0xe;
-            if col >= 16 {
-                screen_x += 1;
+            if absolute_row_idx < row_count {
+                row_writer.move_to(margin_horizontal, y + i)?;
+                draw_line(state, &mut row_writer, absolute_row_idx, selected_range.clone())?;
+                row_writer.flush()?;
+            } else {
+                queue!(stdout(), MoveTo(0, y + i), Clear(ClearType::CurrentLine))?;
             }
-            
-            let screen_y = relative_y + config.appearance.margin_vertical + PADDING_TOP;
-            
-            execute!(stdout(), cursor::Show, MoveTo(screen_x as u16, screen_y))?;
         }
     }
     
-    if !text_cursor_shown {
-        execute!(stdout(), cursor::Hide)?;
+    if row_count == 0 {
+        let new_file = state.file.disk_metadata.is_none() && state.file.file_name.is_some();
+        
+        let mut hint = LineWriter::new(margin_horizontal, state.file.panes[0].y);
+        hint.write(LineColor::Zero, format_args!(
+            "({} — press {} to start typing bytes)",
+            if new_file { "new file" } else { "empty file" },
+            config.keybinds.edit,
+        ));
+        hint.flush()?;
+    }
+    
+    let State { input_state, file, .. } = state;
+    if let (InputState::Matches(panel), Some(matches)) = (input_state, &file.matches) {
+        let matches_y = content_y + content_height - matches_height;
+        draw_matches(panel, matches, &mut *file.bytes, margin_horizontal, matches_y, matches_height, width)?;
+    }
+    
+    if state.file.panes.len() > 1 {
+        let separator_y = state.file.panes[0].y + state.file.panes[0].height;
+        
+        let mut separator = LineWriter::new(margin_horizontal, separator_y);
+        separator.write_str(LineColor::Zero, &"─".repeat(width.saturating_sub(margin_horizontal * 2) as usize));
+        separator.flush()?;
+    }
+    
+    // position text cursor
+    if let Some((screen_x, screen_y)) = cursor_position(state.pane(), margin_horizontal) {
+        queue!(stdout(), cursor::Show, MoveTo(screen_x, screen_y))?;
+    } else {
+        queue!(stdout(), cursor::Hide)?;
     }
     
     Ok(())
 }
 
-fn draw_bottom(keybinds: &Keybinds, state: &State<'_>, width: u16, start_y: u16) -> Result<()> {
+/// Screen position of the text cursor in a pane drawn at `x`, or None if it's hidden or scrolled out of view
+fn cursor_position(pane: &Pane, x: u16) -> Option<(u16, u16)> {
+    let (row, col) = pane.selection?;
+    let relative_y = row.checked_sub(pane.scroll_pos)?;
+    
+    (relative_y < pane.visible_content_rows())
+        .then(|| (hex_column(x, col / 2) + (col % 2) as u16, relative_y as u16 + pane.y))
+}
+
+/// Shows the names of all open files, with the active one emphasized and a `*` after unsaved ones.
+/// The active file also shows how many bytes have been changed and whether it's read-only,
+/// and its path gets shortened in the middle when it doesn't fit.
+fn draw_title(state: &State, width: u16) -> Result<()> {
+    queue!(stdout(), MoveTo(0, 0), Clear(ClearType::CurrentLine))?;
+    
+    let width = width as usize;
+    let mut line = LineWriter::new(0, 0);
+    line.set_max_width(Some(width));
+    
+    let title_of_inactive = |file: &OpenFile| {
+        let mut title = file.display_name().into_owned();
+        if !file.modified_bytes.is_empty() {
+            title.push('*');
+        }
+        title
+    };
+    
+    let mut active_suffix = match state.file.file_type {
+        Some(signature) => format!(" ({}, {})", format_size(state.file.bytes.len()), signature.name),
+        None => format!(" ({})", format_size(state.file.bytes.len())),
+    };
+    let modified_count = state.file.modified_bytes.len();
+    if modified_count == 1 {
+        active_suffix += " [+1 byte]";
+    } else if modified_count > 1 {
+        active_suffix += &format!(" [+{modified_count} bytes]");
+    }
+    if state.readonly {
+        active_suffix += " [RO]";
+    }
+    if let Some(comparison) = &state.file.comparison {
+        active_suffix += &format!(" (compared with {})", comparison.name.display());
+    }
+    
+    let active_name = state.display_name();
+    let active_width = display_width(&active_name) + display_width(&active_suffix);
+    
+    // the files before the active one make room for it if needed
+    let files_before: Vec<String> = state.files().take(state.active_file).map(title_of_inactive).collect();
+    let files_before_width: usize = files_before.iter().map(|title| display_width(title) + 2).sum();
+    
+    if files_before_width + active_width <= width {
+        for title in &files_before {
+            line.write_str(LineColor::Zero, title);
+            line.write_str(LineColor::Regular, "  ");
+        }
+    }
+    
+    let name_width = width.saturating_sub(display_width(&active_suffix));
+    line.write_str(LineColor::Title, &elide_middle(&active_name, name_width));
+    line.write_str(LineColor::Title, &active_suffix);
+    
+    for file in state.files().skip(state.active_file + 1) {
+        line.write_str(LineColor::Regular, "  ");
+        line.write_str(LineColor::Zero, &title_of_inactive(file));
+    }
+    
+    line.flush()?;
+    Ok(())
+}
+
+/// Divides the rows available for content between the panes, leaving a row between them for a separator
+fn layout_panes(state: &mut State, start_y: u16, content_height: u16) {
+    let pane_count = state.file.panes.len() as u16;
+    let available_height = content_height.saturating_sub(pane_count - 1);
+    
+    let mut y = start_y;
+    for (i, pane) in state.file.panes.iter_mut().enumerate() {
+        // the last pane takes the remaining rows if they can't be divided evenly
+        pane.height = if i as u16 == pane_count - 1 {
+            available_height - available_height / pane_count * (pane_count - 1)
+        } else {
+            available_height / pane_count
+        };
+        
+        pane.y = y;
+        y += pane.height + 1;
+    }
+}
+
+fn draw_bottom(config: &Config, state: &mut State, width: u16, start_y: u16) -> Result<()> {
+    let keybinds = &config.keybinds;
+    
+    queue!(stdout(), MoveTo(0, start_y), Clear(ClearType::CurrentLine), MoveTo(0, start_y + 1), Clear(ClearType::CurrentLine))?;
+    
     let mut line1 = LineWriter::new(0, start_y);
     let mut line2 = LineWriter::new(0, start_y + 1);
+    line1.set_max_width(Some(width as usize));
     
-    let (save_color, save_color_bold) = if state.modified_bytes.is_empty() {
-        (LineColor::Zero, LineColor::Zero)
-    } else {
-        (LineColor::Regular, LineColor::Emphasis)
+    let mut hint_areas = HintAreas::default();
+    let right_segments = status_segments(&config.statusbar.right, state, false)?;
+    
+    // messages are more important than anything on the right
+    let message_width = match (&state.input_state, &state.bottom_text) {
+        (InputState::Regular, Some(bottom_text)) if state.file.search.is_none() && state.file.hashing.is_none() => display_width(bottom_text),
+        _ => 0,
     };
+    let layout = layout_status_line(&right_segments, message_width, width as usize);
+    
+    // prompts and messages get cut off before the right side, just like the left items
+    line2.set_max_width(Some(layout.left_width));
     
     match &state.input_state {
         InputState::Goto(goto_buffer) => {
-            line2.write_str(LineColor::Emphasis, "Go to: 0x")?;
-            line2.write_str(LineColor::Regular, goto_buffer)?;
+            line2.write_str(LineColor::Emphasis, "Go to: 0x");
             // TODO: figure out blinking cursor
-            line2.write_char(LineColor::TextCursor, ' ')?;
+            draw_prompt_buffer(&mut line2, goto_buffer, None);
+        },
+        InputState::SaveAs(path_input) | InputState::ExportPatch(path_input) | InputState::ApplyPatch(path_input) => {
+            if let Some(completion) = &path_input.completion {
+                draw_completion_candidates(&mut line1, &completion.candidates, width);
+            }
+            
+            let prompt = match state.input_state {
+                InputState::SaveAs(_) => "Save as: ",
+                InputState::ExportPatch(_) => "Export patch to (.ips or text): ",
+                _ => "Apply patch: ",
+            };
+            line2.write_str(LineColor::Emphasis, prompt);
+            draw_prompt_buffer(&mut line2, &path_input.buffer, None);
+        },
+        InputState::SwitchFile(query) => {
+            for (i, file) in state.files().enumerate() {
+                let color = if i == state.active_file { LineColor::Emphasis } else { LineColor::Regular };
+                line1.write(color, format_args!("{} {}", i + 1, file.display_name()));
+                line1.write_whitespace("  ");
+            }
+            
+            line2.write_str(LineColor::Emphasis, "Switch to file (number or name): ");
+            draw_prompt_buffer(&mut line2, query, None);
         },
         InputState::Find => {
-            line2.write(LineColor::Emphasis, format_args!("Find what?  {}", keybinds.find_binary))?;
-            line2.write_str(LineColor::Regular, " bytes, ")?;
-            line2.write(LineColor::Emphasis, format_args!("{}", keybinds.find_text))?;
-            line2.write_str(LineColor::Regular, " text, (")?;
-            line2.write_str(LineColor::Emphasis, "Esc")?;
-            line2.write_str(LineColor::Regular, " back)")?;
+            line2.write_str(LineColor::Emphasis, "Find what?  ");
+            hint_areas.write_binding(&mut line2, &keybinds.find_binary, " bytes");
+            line2.write_str(LineColor::Regular, ", ");
+            hint_areas.write_binding(&mut line2, &keybinds.find_text, " text");
+            line2.write_str(LineColor::Regular, ", ");
+            hint_areas.write_binding(&mut line2, &keybinds.find_next, " next match");
+            line2.write_str(LineColor::Regular, ", (");
+            hint_areas.write_key(&mut line2, KeyCode::Esc, " back");
+            line2.write_str(LineColor::Regular, ")");
         },
         InputState::FindBytes(byte_buffer) => {
-            line2.write_str(LineColor::Emphasis, "Find byte sequence (in hex): ")?;
+            line2.write_str(LineColor::Emphasis, "Find byte sequence (in hex): ");
+            draw_prompt_buffer(&mut line2, byte_buffer, Some(2));
+        },
+        InputState::FindString(string_buffer) => {
+            line2.write_str(LineColor::Emphasis, "Find text: ");
+            draw_prompt_buffer(&mut line2, string_buffer, None);
+        },
+        InputState::UnsavedChanges(PendingAction::Quit) => {
+            if state.file_count() > 1 {
+                line1.write(LineColor::Highlighted, format_args!(" There are unsaved changes in '{}' ", state.display_name()));
+            } else {
+                line1.write_str(LineColor::Highlighted, " There are unsaved changes ");
+            }
+            
+            line2.write_str(LineColor::Emphasis, "Save before quitting?  ");
+            hint_areas.write_key(&mut line2, KeyCode::Char('y'), " save");
+            line2.write_str(LineColor::Regular, ", ");
+            hint_areas.write_key(&mut line2, KeyCode::Char('n'), " discard");
+            line2.write_str(LineColor::Regular, ", (");
+            hint_areas.write_key(&mut line2, KeyCode::Esc, " back");
+            line2.write_str(LineColor::Regular, ")");
+        },
+        InputState::UnsavedChanges(PendingAction::Reload) => {
+            line1.write_str(LineColor::Highlighted, " There are unsaved changes ");
+            
+            line2.write_str(LineColor::Emphasis, "Discard them and reload?  ");
+            hint_areas.write_key(&mut line2, KeyCode::Char('y'), " reload");
+            line2.write_str(LineColor::Regular, ", ");
+            hint_areas.write_key(&mut line2, KeyCode::Char('n'), " cancel");
+        },
+        InputState::PatchPastEnd(_) => {
+            line1.write_str(LineColor::Highlighted, " The patch goes past the end of the file ");
             
-            let chunks = byte_buffer.chars().chunks(2);
-            for (i, chunk) in chunks.into_iter().enumerate() {
-                for c in chunk {
-                    line2.write_char(LineColor::Regular, c)?;
+            line2.write_str(LineColor::Emphasis, "Grow the file to fit it?  ");
+            hint_areas.write_key(&mut line2, KeyCode::Char('y'), " grow");
+            line2.write_str(LineColor::Regular, ", ");
+            hint_areas.write_key(&mut line2, KeyCode::Char('n'), " don't apply");
+        },
+        InputState::RecoverEdits | InputState::RecoveredEdits { .. } => {
+            if let Some(bottom_text) = &state.bottom_text {
+                line1.write_str(LineColor::Regular, bottom_text);
+            } else if state.file_count() > 1 {
+                line1.write(LineColor::Highlighted, format_args!(" Found unsaved changes to '{}' from a previous session ", state.display_name()));
+            } else {
+                line1.write_str(LineColor::Highlighted, " Found unsaved changes from a previous session ");
+            }
+            
+            line2.write_str(LineColor::Emphasis, "Restore them?  ");
+            hint_areas.write_key(&mut line2, KeyCode::Char('a'), " apply");
+            line2.write_str(LineColor::Regular, ", ");
+            hint_areas.write_key(&mut line2, KeyCode::Char('d'), " discard");
+            line2.write_str(LineColor::Regular, ", ");
+            if matches!(state.input_state, InputState::RecoverEdits) {
+                hint_areas.write_key(&mut line2, KeyCode::Char('v'), " view");
+            } else {
+                line2.write_str(LineColor::Emphasis, "Up/Down");
+                line2.write_str(LineColor::Regular, " scroll, (");
+                hint_areas.write_key(&mut line2, KeyCode::Esc, " back");
+                line2.write_str(LineColor::Regular, ")");
+            }
+        },
+        InputState::ExternallyModified { .. } => {
+            line1.write_str(LineColor::Highlighted, " The file has been changed by another program ");
+            
+            hint_areas.write_key(&mut line2, KeyCode::Char('o'), " overwrite anyway");
+            line2.write_str(LineColor::Regular, ", ");
+            hint_areas.write_key(&mut line2, KeyCode::Char('r'), " reload from disk (discard edits)");
+            line2.write_str(LineColor::Regular, ", ");
+            hint_areas.write_key(&mut line2, KeyCode::Char('c'), " cancel");
+        },
+        InputState::Histogram { selected } => {
+            let selected = *selected;
+            
+            if let Some(histogram) = &state.file.histogram {
+                line1.write(LineColor::Emphasis, format_args!("0x{selected:02x}"));
+                if selected.is_ascii_graphic() {
+                    line1.write(LineColor::Regular, format_args!(" '{}'", selected as char));
                 }
+                line1.write(LineColor::Regular, format_args!(": {} times ({:.2}%)", histogram.counts[selected as usize], histogram.percentage(selected)));
                 
-                if i * 2 + 1 < byte_buffer.len() {
-                    line2.write_whitespace(" ");
+                line2.write(LineColor::Emphasis, format_args!("Byte values in {}  Arrows", format_range(&histogram.range)));
+            }
+            
+            line2.write_str(LineColor::Regular, " select, ");
+            hint_areas.write_key(&mut line2, KeyCode::Enter, " jump to first occurrence");
+            line2.write_str(LineColor::Regular, ", (");
+            hint_areas.write_key(&mut line2, KeyCode::Esc, " back");
+            line2.write_str(LineColor::Regular, ")");
+        },
+        InputState::Matches(panel) => {
+            if let Some(bottom_text) = &state.bottom_text {
+                line1.write_str(LineColor::Regular, bottom_text);
+            } else if let Some(matches) = &state.file.matches {
+                let needle = matches.needle.iter().map(|byte| format!("{byte:02x}")).join(" ");
+                line1.write(LineColor::Regular, format_args!("{} of {} matches of ", panel.filtered(matches).len(), matches.offsets.len()));
+                line1.write_str(LineColor::Emphasis, &needle);
+                
+                if !matches.is_done() {
+                    line1.write(LineColor::Zero, format_args!(" (searching… {}%)", matches.progress_percentage()));
+                } else if matches.is_truncated() {
+                    line1.write(LineColor::Zero, format_args!(" (only the first {MAX_MATCHES} are listed)"));
+                }
+                if matches.stale {
+                    line1.write(LineColor::Zero, format_args!(" (outdated, {} to search again)", keybinds.list_matches));
                 }
             }
             
-            line2.write_char(LineColor::TextCursor, ' ')?;
+            line2.write_str(LineColor::Emphasis, "Filter offsets: ");
+            line2.write_str(LineColor::Regular, &panel.filter);
+            line2.write_char(LineColor::TextCursor, ' ');
+            line2.write_str(LineColor::Regular, "  ");
+            hint_areas.write_key(&mut line2, KeyCode::Enter, " jump");
+            line2.write_str(LineColor::Regular, ", (");
+            hint_areas.write_key(&mut line2, KeyCode::Esc, " back");
+            line2.write_str(LineColor::Regular, ")");
         },
-        InputState::FindString(string_buffer) => {
-            line2.write_str(LineColor::Emphasis, "Find text: ")?;
-            line2.write_str(LineColor::Regular, string_buffer)?;
-            line2.write_char(LineColor::TextCursor, ' ')?;
+        InputState::Sections(panel) => {
+            match &state.bottom_text {
+                Some(bottom_text) => line1.write_str(LineColor::Regular, bottom_text),
+                None => line1.write(LineColor::Regular, format_args!("{} of {} sections", panel.matches().len(), panel.sections.len())),
+            }
+            
+            line2.write_str(LineColor::Emphasis, "Go to section: ");
+            line2.write_str(LineColor::Regular, &panel.filter);
+            line2.write_char(LineColor::TextCursor, ' ');
+            line2.write_str(LineColor::Regular, "  ");
+            hint_areas.write_key(&mut line2, KeyCode::Enter, " jump");
+            line2.write_str(LineColor::Regular, ", (");
+            hint_areas.write_key(&mut line2, KeyCode::Esc, " back");
+            line2.write_str(LineColor::Regular, ")");
         },
-        InputState::Edit { .. } => {
-            line1.write(LineColor::Emphasis, format_args!("{}", keybinds.quit))?;
-            line1.write_str(LineColor::Regular, " exit, ")?;
-            line1.write_str(LineColor::Emphasis, "Esc")?;
-            line1.write_str(LineColor::Regular, " go back, ")?;
-            line1.write_str(LineColor::Emphasis, "0-9 A-F")?;
-            line1.write_str(LineColor::Regular, " overwrite bytes, ")?;
-                line1.write(save_color_bold, format_args!("{}", keybinds.save))?;
-                line1.write_str(save_color, " save")?;
+        InputState::Hashes { .. } | InputState::Decoded { .. } => {
+            if let Some(bottom_text) = &state.bottom_text {
+                line1.write_str(LineColor::Regular, bottom_text);
+            }
             
-            line2.write(LineColor::Emphasis, format_args!("{}{}{}{}/Arrows",
-                keybinds.left, keybinds.down, keybinds.up, keybinds.right))?;
-            line2.write_str(LineColor::Regular, " move selection (")?;
-            line2.write_str(LineColor::Emphasis, "Alt")?;
-            line2.write_str(LineColor::Regular, " to move by digits) ")?;
+            line2.write_str(LineColor::Emphasis, "Up/Down");
+            line2.write_str(LineColor::Regular, " select, ");
+            hint_areas.write_key(&mut line2, KeyCode::Enter, " copy to clipboard");
+            line2.write_str(LineColor::Regular, ", (");
+            hint_areas.write_key(&mut line2, KeyCode::Esc, " back");
+            line2.write_str(LineColor::Regular, ")");
+        },
+        InputState::Strings(panel) => {
+            let match_count = panel.matches().len();
+            
+            line1.write(LineColor::Regular, format_args!("{match_count} of {} strings", panel.list.strings.len()));
+            if panel.list.truncated {
+                line1.write(LineColor::Zero, format_args!(" (only the first {MAX_STRINGS} are listed)"));
+            }
+            
+            line2.write_str(LineColor::Emphasis, "Filter strings: ");
+            line2.write_str(LineColor::Regular, &panel.filter);
+            line2.write_char(LineColor::TextCursor, ' ');
+            line2.write_str(LineColor::Regular, "  ");
+            hint_areas.write_key(&mut line2, KeyCode::Enter, " jump");
+            line2.write_str(LineColor::Regular, ", (");
+            hint_areas.write_key(&mut line2, KeyCode::Esc, " back");
+            line2.write_str(LineColor::Regular, ")");
+        },
+        InputState::Edit { .. } => {
+            let left_segments = status_segments(&config.statusbar.left, state, true)?;
+            draw_segments(&mut line1, &mut line2, &mut hint_areas, &left_segments, keybinds, state);
         },
         InputState::Regular => {
-            if let Some(bottom_text) = state.bottom_text.as_deref() {
-                line2.write_str(LineColor::Regular, bottom_text)?;
-            } else if state.selection.is_some() {
-                line1.write(LineColor::Emphasis, format_args!("{}", keybinds.quit))?;
-                line1.write_str(LineColor::Regular, " exit, ")?;
-                line1.write(LineColor::Emphasis, format_args!("{}", keybinds.toggle_cursor))?;
-                line1.write_str(LineColor::Regular, " pager,  ")?;
-                line1.write(LineColor::Emphasis, format_args!("{}", keybinds.edit))?;
-                line1.write_str(LineColor::Regular, " edit, ")?;
-                line1.write(LineColor::Emphasis, format_args!("{}", keybinds.go_to))?;
-                line1.write_str(LineColor::Regular, " go to, ")?;
-                line1.write(LineColor::Emphasis, format_args!("{}", keybinds.find))?;
-                line1.write_str(LineColor::Regular, " find, ")?;
-                line1.write(save_color_bold, format_args!("{}", keybinds.save))?;
-                line1.write_str(save_color, " save")?;
-                
-                line2.write(LineColor::Emphasis, format_args!("{}{}{}{}/Arrows",
-                    keybinds.left, keybinds.down, keybinds.up, keybinds.right))?;
-                line2.write_str(LineColor::Regular, " move selection (")?;
-                line2.write_str(LineColor::Emphasis, "Alt")?;
-                line2.write_str(LineColor::Regular, " to move by digits) ")?;
+            if let Some(search) = &state.file.search {
+                line2.write(LineColor::Emphasis, format_args!("Searching… {}%", search.progress_percentage()));
+                line2.write_str(LineColor::Regular, " (");
+                hint_areas.write_key(&mut line2, KeyCode::Esc, " cancel");
+                line2.write_str(LineColor::Regular, ")");
+            } else if let Some(hashing) = &state.file.hashing {
+                line2.write(LineColor::Emphasis, format_args!("Hashing… {}%", hashing.progress_percentage()));
+                line2.write_str(LineColor::Regular, " (");
+                hint_areas.write_key(&mut line2, KeyCode::Esc, " cancel");
+                line2.write_str(LineColor::Regular, ")");
+            } else if let Some(bottom_text) = state.bottom_text.as_deref() {
+                line2.write_str(LineColor::Regular, bottom_text);
             } else {
-                line1.write(LineColor::Emphasis, format_args!("{}", keybinds.quit))?;
-                line1.write_str(LineColor::Regular, " exit, ")?;
-                line1.write(LineColor::Emphasis, format_args!("{}", keybinds.toggle_cursor))?;
-                line1.write_str(LineColor::Regular, " cursor, ")?;
-                line1.write(LineColor::Emphasis, format_args!("{}", keybinds.edit))?;
-                line1.write_str(LineColor::Regular, " edit, ")?;
-                line1.write(LineColor::Emphasis, format_args!("{}", keybinds.go_to))?;
-                line1.write_str(LineColor::Regular, " go to, ")?;
-                line1.write(LineColor::Emphasis, format_args!("{}", keybinds.find))?;
-                line1.write_str(LineColor::Regular, " find, ")?;
-                line1.write(save_color_bold, format_args!("{}", keybinds.save))?;
-                line1.write_str(save_color, " save")?;
-                
-                line2.write(LineColor::Emphasis, format_args!("{}/Down", keybinds.down))?;
-                line2.write_str(LineColor::Regular, " scroll down, ")?;
-                line2.write(LineColor::Emphasis, format_args!("{}/Up", keybinds.up))?;
-                line2.write_str(LineColor::Regular, " scroll up ")?;
+                let left_segments = status_segments(&config.statusbar.left, state, true)?;
+                draw_segments(&mut line1, &mut line2, &mut hint_areas, &left_segments, keybinds, state);
             }
         },
     }
     
-    // display percentage
-    let visible_bytes = usize::min(
-        (state.scroll_pos + state.visible_content_rows() - 1) * 0x10,
-        state.bytes.len() - 0x10,
-    );
-    let percentage = ((visible_bytes + 0x10) as f32 / state.bytes.len() as f32 * 100.0) as usize;
-    let percentage_string = format!("{:x} / {:x}, {}%", visible_bytes, state.bytes.len(), percentage);
+    line2.set_max_width(None);
+    line2.seek(width.saturating_sub(layout.right_width as u16))?;
+    draw_segments(&mut line1, &mut line2, &mut hint_areas, &right_segments[layout.first_shown..], keybinds, state);
+    
+    line1.flush()?;
+    line2.flush()?;
+    
+    state.hint_areas = hint_areas;
+    Ok(())
+}
+
+/// Writes the keyboard shortcuts that can be used right now, on both lines
+fn draw_hints(line1: &mut LineWriter, line2: &mut LineWriter, hint_areas: &mut HintAreas, keybinds: &Keybinds, state: &State) {
+    let save_colors = if state.file.modified_bytes.is_empty() {
+        (LineColor::Zero, LineColor::Zero)
+    } else {
+        (LineColor::Emphasis, LineColor::Regular)
+    };
+    
+    if matches!(state.input_state, InputState::Edit { .. }) {
+        hint_areas.write_binding(line1, &keybinds.quit, " exit");
+        line1.write_str(LineColor::Regular, ", ");
+        hint_areas.write_key(line1, KeyCode::Esc, " go back");
+        line1.write_str(LineColor::Regular, ", ");
+        line1.write_str(LineColor::Emphasis, "0-9 A-F");
+        line1.write_str(LineColor::Regular, " overwrite bytes, ");
+        hint_areas.write(line1, keybinds.save.key_event(), &keybinds.save, " save", save_colors);
+        
+        line2.write(LineColor::Emphasis, format_args!("{}{}{}{}/Arrows",
+            keybinds.left, keybinds.down, keybinds.up, keybinds.right));
+        line2.write_str(LineColor::Regular, " move selection (");
+        line2.write_str(LineColor::Emphasis, "Alt");
+        line2.write_str(LineColor::Regular, " to move by digits)");
+    } else if state.pane().selection.is_some() {
+        hint_areas.write_binding(line1, &keybinds.quit, " exit");
+        line1.write_str(LineColor::Regular, ", ");
+        hint_areas.write_binding(line1, &keybinds.toggle_cursor, " pager");
+        line1.write_str(LineColor::Regular, ",  ");
+        hint_areas.write_binding(line1, &keybinds.edit, " edit");
+        line1.write_str(LineColor::Regular, ", ");
+        hint_areas.write_binding(line1, &keybinds.go_to, " go to");
+        line1.write_str(LineColor::Regular, ", ");
+        hint_areas.write_binding(line1, &keybinds.find, " find");
+        line1.write_str(LineColor::Regular, ", ");
+        hint_areas.write_binding(line1, &keybinds.reload, " reload");
+        line1.write_str(LineColor::Regular, ", ");
+        hint_areas.write(line1, keybinds.save.key_event(), &keybinds.save, " save", save_colors);
+        line1.write_str(LineColor::Regular, ", ");
+        hint_areas.write_binding(line1, &keybinds.save_as, " save as");
+        
+        line2.write(LineColor::Emphasis, format_args!("{}{}{}{}/Arrows",
+            keybinds.left, keybinds.down, keybinds.up, keybinds.right));
+        line2.write_str(LineColor::Regular, " move selection (");
+        line2.write_str(LineColor::Emphasis, "Alt");
+        line2.write_str(LineColor::Regular, " to move by digits), ");
+        hint_areas.write_binding(line2, &keybinds.export_patch, " export patch");
+        line2.write_str(LineColor::Regular, ", ");
+        hint_areas.write_binding(line2, &keybinds.apply_patch, " apply patch");
+        line2.write_str(LineColor::Regular, ", ");
+        hint_areas.write_binding(line2, &keybinds.hash, " hash selection");
+        line2.write_str(LineColor::Regular, ", ");
+        hint_areas.write_binding(line2, &keybinds.decode, " decode");
+        
+        if state.file.jumps.back_len() > 0 {
+            line2.write_str(LineColor::Regular, ", ");
+            line2.write(LineColor::Emphasis, format_args!("{}/{}", keybinds.jump_back, keybinds.jump_forward));
+            line2.write_str(LineColor::Regular, " jump back/forward");
+        }
+    } else {
+        hint_areas.write_binding(line1, &keybinds.quit, " exit");
+        line1.write_str(LineColor::Regular, ", ");
+        hint_areas.write_binding(line1, &keybinds.toggle_cursor, " cursor");
+        line1.write_str(LineColor::Regular, ", ");
+        hint_areas.write_binding(line1, &keybinds.edit, " edit");
+        line1.write_str(LineColor::Regular, ", ");
+        hint_areas.write_binding(line1, &keybinds.go_to, " go to");
+        line1.write_str(LineColor::Regular, ", ");
+        hint_areas.write_binding(line1, &keybinds.find, " find");
+        line1.write_str(LineColor::Regular, ", ");
+        hint_areas.write_binding(line1, &keybinds.reload, " reload");
+        line1.write_str(LineColor::Regular, ", ");
+        hint_areas.write(line1, keybinds.save.key_event(), &keybinds.save, " save", save_colors);
+        line1.write_str(LineColor::Regular, ", ");
+        hint_areas.write_binding(line1, &keybinds.save_as, " save as");
+        
+        hint_areas.write(line2, keybinds.down.key_event(), &format_args!("{}/Down", keybinds.down), " scroll down", HINT_COLORS);
+        line2.write_str(LineColor::Regular, ", ");
+        hint_areas.write(line2, keybinds.up.key_event(), &format_args!("{}/Up", keybinds.up), " scroll up", HINT_COLORS);
+        line2.write_str(LineColor::Regular, ", ");
+        hint_areas.write_binding(line2, &keybinds.follow, " follow");
+        line2.write_str(LineColor::Regular, ", ");
+        hint_areas.write_binding(line2, &keybinds.split, " split");
+        line2.write_str(LineColor::Regular, ", ");
+        line2.write(LineColor::Emphasis, format_args!("{}{}", keybinds.prev_run, keybinds.next_run));
+        line2.write_str(LineColor::Regular, " skip run, ");
+        hint_areas.write_binding(line2, &keybinds.toggle_entropy, " entropy");
+        line2.write_str(LineColor::Regular, ", ");
+        hint_areas.write_binding(line2, &keybinds.histogram, " histogram");
+        line2.write_str(LineColor::Regular, ", ");
+        hint_areas.write_binding(line2, &keybinds.cycle_encoding, " encoding");
+        line2.write_str(LineColor::Regular, ", ");
+        hint_areas.write_binding(line2, &keybinds.toggle_control_pictures, " control chars");
+        
+        if state.file.panes.len() > 1 {
+            line2.write_str(LineColor::Regular, ", ");
+            hint_areas.write_binding(line2, &keybinds.switch_pane, " other pane");
+        }
+        
+        if state.file_count() > 1 {
+            line2.write_str(LineColor::Regular, ", ");
+            line2.write(LineColor::Emphasis, format_args!("{}{}", keybinds.prev_file, keybinds.next_file));
+            line2.write_str(LineColor::Regular, " switch file");
+        }
+        
+        if state.file.comparison.is_some() {
+            line2.write_str(LineColor::Regular, ", ");
+            line2.write(LineColor::Emphasis, format_args!("{}/{}", keybinds.next_difference, keybinds.prev_difference));
+            line2.write_str(LineColor::Regular, " next/previous difference");
+        }
+        
+        if !state.file.modified_bytes.is_empty() {
+            line2.write_str(LineColor::Regular, ", ");
+            line2.write(LineColor::Emphasis, format_args!("{}/{}", keybinds.next_change, keybinds.prev_change));
+            line2.write_str(LineColor::Regular, " next/previous change");
+        }
+    }
+}
+
+/// Colors of the key and the description of a hint
+const HINT_COLORS: (LineColor, LineColor) = (LineColor::Emphasis, LineColor::Regular);
+
+/// Hint in the bottom bar, which presses its key when clicked
+#[derive(Debug)]
+struct HintArea {
+    row: u16,
+    columns: Range<u16>,
+    key: KeyEvent,
+}
+
+/// Where the clickable hints have been drawn in the bottom bar
+#[derive(Debug, Default)]
+pub struct HintAreas(Vec<HintArea>);
+
+impl HintAreas {
+    /// Returns the key of the hint at the clicked position, if there is one
+    pub fn key_at(&self, column: u16, row: u16) -> Option<KeyEvent> {
+        self.0.iter()
+            .find(|area| area.row == row && area.columns.contains(&column))
+            .map(|area| area.key)
+    }
+    
+    /// Writes a key followed by what it does, remembering the columns it ended up in.
+    /// Hints that have been cut off entirely aren't clickable and ones cut off partially only where they're visible.
+    fn write(&mut self, line: &mut LineWriter, key: KeyEvent, key_text: &dyn Display, description: &str, colors: (LineColor, LineColor)) {
+        let start = line.column();
+        
+        line.write(colors.0, format_args!("{key_text}"));
+        line.write_str(colors.1, description);
+        
+        if let Some(start) = start {
+            self.0.push(HintArea { row: line.y(), columns: start..line.end_column(), key });
+        }
+    }
+    
+    fn write_binding(&mut self, line: &mut LineWriter, bindings: &Bindings, description: &str) {
+        self.write(line, bindings.key_event(), bindings, description, HINT_COLORS);
+    }
+    
+    /// Writes a hint for a key that can't be rebound, like Esc or Enter
+    fn write_key(&mut self, line: &mut LineWriter, code: KeyCode, description: &str) {
+        let key_text = Keybind { code, modifiers: KeyModifiers::NONE };
+        self.write(line, KeyEvent::from(code), &key_text, description, HINT_COLORS);
+    }
+}
+
+/// An item of the status line, ready to be drawn
+#[derive(Debug)]
+enum StatusSegment {
+    Hints,
+    Text(String),
+    /// Differently colored pieces of text
+    Styled(Vec<(LineColor, String)>),
+}
+
+impl StatusSegment {
+    fn width(&self) -> usize {
+        match self {
+            StatusSegment::Hints => 0,
+            StatusSegment::Text(text) => display_width(text),
+            StatusSegment::Styled(pieces) => pieces.iter().map(|(_, text)| display_width(text)).sum(),
+        }
+    }
+    
+    /// Whether there's a gap between this and the next segment. Literal text goes right next to its neighbors.
+    fn separated_from(&self, next: &StatusSegment) -> bool {
+        !matches!(self, StatusSegment::Text(_)) && !matches!(next, StatusSegment::Text(_))
+    }
+}
+
+const STATUS_SEPARATOR: &str = "  ";
+
+/// Turns the configured status items into segments, leaving out the ones that have nothing to show
+/// (like the selection when nothing is selected) along with the literal text next to them
+fn status_segments(items: &[StatusItem], state: &mut State, with_hints: bool) -> Result<Vec<StatusSegment>> {
+    let file_len = state.file.bytes.len();
+    let cursor_offset = state.pane().cursor_offset();
+    
+    let mut segments = Vec::with_capacity(items.len());
+    
+    for item in items {
+        let segment = match item {
+            StatusItem::Text { text } => Some(StatusSegment::Text(text.clone())),
+            StatusItem::Named(NamedStatusItem::Hints) => with_hints.then_some(StatusSegment::Hints),
+            StatusItem::Named(NamedStatusItem::Filename) => {
+                Some(StatusSegment::Styled(vec![(LineColor::Regular, state.display_name().into_owned())]))
+            },
+            StatusItem::Named(NamedStatusItem::Offset) => cursor_offset.map(|offset| {
+                StatusSegment::Styled(vec![(LineColor::Regular, format_offset(offset))])
+            }),
+            StatusItem::Named(NamedStatusItem::Value) => match cursor_offset {
+                Some(offset) => {
+                    let value = if offset < file_len { Some(state.file.bytes.read_byte(offset)?) } else { None };
+                    let mut pieces = vec![(LineColor::Regular, format_value(value))];
+                    
+                    if state.file.modified_bytes.contains_key(&offset) {
+                        pieces.push((LineColor::Modified, " [modified]".to_owned()));
+                    }
+                    
+                    Some(StatusSegment::Styled(pieces))
+                },
+                None => None,
+            },
+            StatusItem::Named(NamedStatusItem::Selection) => state.pane().selected_range(file_len).map(|range| {
+                StatusSegment::Styled(vec![(LineColor::Highlighted, format_range(&range))])
+            }),
+            StatusItem::Named(NamedStatusItem::Percent) => {
+                let percentage_string = if file_len == 0 {
+                    "empty file".to_owned()
+                } else {
+                    // how far into the file the end of the screen is
+                    let visible_end = usize::min(
+                        (state.pane().scroll_pos + state.pane().visible_content_rows()) * 0x10,
+                        file_len,
+                    );
+                    let percentage = visible_end * 100 / file_len;
+                    format!("{visible_end:x} / {file_len:x}, {percentage}%")
+                };
+                
+                Some(StatusSegment::Styled(vec![(LineColor::Regular, percentage_string)]))
+            },
+            StatusItem::Named(NamedStatusItem::Differences) => state.file.comparison.as_ref().map(|comparison| {
+                StatusSegment::Styled(vec![(LineColor::Different, format!("{} bytes differ", comparison.differing_bytes))])
+            }),
+            StatusItem::Named(NamedStatusItem::Follow) => state.file.following.then(|| {
+                StatusSegment::Styled(vec![(LineColor::Highlighted, " FOLLOW ".to_owned())])
+            }),
+            StatusItem::Named(NamedStatusItem::Entropy) => state.show_entropy.then(|| {
+                let mut pieces = vec![(LineColor::Regular, "entropy: low ".to_owned())];
+                pieces.extend((0..ENTROPY_LEVELS as u8).map(|level| (LineColor::Entropy(level), "█".to_owned())));
+                pieces.push((LineColor::Regular, " high".to_owned()));
+                
+                StatusSegment::Styled(pieces)
+            }),
+            StatusItem::Named(NamedStatusItem::Encoding) => (state.text_encoding != TextEncoding::Ascii).then(|| {
+                StatusSegment::Styled(vec![(LineColor::Emphasis, state.text_encoding.name().to_owned())])
+            }),
+        };
+        
+        segments.push(segment);
+    }
+    
+    // literal text only makes sense next to the items it describes
+    let is_missing = |index: Option<usize>| index.and_then(|index| segments.get(index)).is_some_and(Option::is_none);
+    let keep: Vec<bool> = (0..segments.len()).map(|i| match &segments[i] {
+        Some(StatusSegment::Text(_)) => !is_missing(i.checked_sub(1)) && !is_missing(Some(i + 1)),
+        segment => segment.is_some(),
+    }).collect();
+    
+    Ok(segments.into_iter().zip(keep).filter_map(|(segment, keep)| segment.filter(|_| keep)).collect())
+}
+
+/// Width of every segment, including the gap to the next one
+fn segment_widths(segments: &[StatusSegment]) -> Vec<usize> {
+    segments.iter().enumerate().map(|(i, segment)| {
+        match segments.get(i + 1) {
+            Some(next) if segment.separated_from(next) => segment.width() + STATUS_SEPARATOR.len(),
+            _ => segment.width(),
+        }
+    }).collect()
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct StatusLayout {
+    /// Index of the first segment shown on the right side
+    first_shown: usize,
+    right_width: usize,
+    left_width: usize,
+}
+
+/// Decides how to fit the status line into `width` columns.
+/// As many of the right side's segments as possible are shown while leaving at least `min_left_width` columns
+/// for the left side, dropping them from the start (the segments at the right edge matter most).
+/// Literal text gets dropped together with the item before it.
+/// The left side gets the rest of the line, minus a column to keep both sides apart.
+fn layout_status_line(right_segments: &[StatusSegment], min_left_width: usize, width: usize) -> StatusLayout {
+    let right_widths = segment_widths(right_segments);
+    
+    let mut first_shown = 0;
+    let mut right_width: usize = right_widths.iter().sum();
+    
+    while first_shown < right_segments.len() {
+        let orphaned_text = first_shown > 0 && matches!(right_segments[first_shown], StatusSegment::Text(_));
+        
+        if right_width + min_left_width < width && !orphaned_text {
+            break;
+        }
+        
+        right_width -= right_widths[first_shown];
+        first_shown += 1;
+    }
+    
+    StatusLayout {
+        first_shown,
+        right_width,
+        left_width: width.saturating_sub(right_width + 1),
+    }
+}
+
+fn draw_segments(line1: &mut LineWriter, line2: &mut LineWriter, hint_areas: &mut HintAreas, segments: &[StatusSegment], keybinds: &Keybinds, state: &State) {
+    for (i, segment) in segments.iter().enumerate() {
+        match segment {
+            StatusSegment::Hints => draw_hints(line1, line2, hint_areas, keybinds, state),
+            StatusSegment::Text(text) => line2.write_str(LineColor::Regular, text),
+            StatusSegment::Styled(pieces) => {
+                for (color, text) in pieces {
+                    line2.write_str(*color, text);
+                }
+            },
+        }
+        
+        if segments.get(i + 1).is_some_and(|next| segment.separated_from(next)) {
+            line2.write_whitespace(STATUS_SEPARATOR);
+        }
+    }
+}
+
+/// Formats the offset of the byte under the cursor, like `0x0001_2a3f`
+fn format_offset(offset: usize) -> String {
+    format!("0x{:04x}_{:04x}", offset >> 16, offset & 0xFFFF)
+}
+
+/// Formats the value of the byte under the cursor, like `0x4d (77)`.
+/// `value` is None if the cursor is past the end of the file.
+fn format_value(value: Option<u8>) -> String {
+    match value {
+        Some(value) => format!("0x{value:02x} ({value})"),
+        None => "--".to_owned(),
+    }
+}
+
+/// Lists the ways the range could be read, with a note if it's too long to be decoded completely
+fn draw_decoded(range: &Range<usize>, interpretations: &[Interpretation], selected: usize, x: u16, y: u16, height: u16, width: u16) -> Result<()> {
+    let max_width = Some(width.saturating_sub(x) as usize);
+    let mut lines = Vec::new();
+    
+    let mut header = LineWriter::new(x, y);
+    header.set_max_width(max_width);
+    header.write(LineColor::Emphasis, format_args!("Decoded {}", format_inclusive_range(range)));
+    if range.len() > MAX_DECODE_LEN {
+        header.write(LineColor::Zero, format_args!(" (only the first {MAX_DECODE_LEN} bytes)"));
+    }
+    lines.push(header);
+    
+    for (i, interpretation) in interpretations.iter().enumerate() {
+        let mut line = LineWriter::new(x, y + 2 + i as u16);
+        line.set_max_width(max_width);
+        line.write(LineColor::Regular, format_args!("{:<9}", interpretation.name));
+        
+        let color = if i == selected { LineColor::Highlighted } else { LineColor::Regular };
+        line.write_str(color, &interpretation.text);
+        lines.push(line);
+    }
+    
+    for i in 0..height {
+        queue!(stdout(), MoveTo(0, y + i), Clear(ClearType::CurrentLine))?;
+    }
+    
+    for mut line in lines.into_iter().filter(|line| line.y() < y + height) {
+        line.flush()?;
+    }
+    
+    Ok(())
+}
+
+/// Lists the edits left behind by a previous session in the order they were made
+fn draw_recovered_edits(edits: &[Edit], scroll: &mut usize, x: u16, y: u16, height: u16, width: u16) -> Result<()> {
+    let visible_rows = height.saturating_sub(2) as usize;
+    *scroll = usize::min(*scroll, edits.len().saturating_sub(visible_rows));
+    
+    let max_width = Some(width.saturating_sub(x) as usize);
+    let mut lines = Vec::new();
+    
+    let mut header = LineWriter::new(x, y);
+    header.set_max_width(max_width);
+    header.write(LineColor::Emphasis, format_args!("{} edits from the previous session", edits.len()));
+    lines.push(header);
+    
+    for (i, edit) in edits.iter().skip(*scroll).take(visible_rows).enumerate() {
+        let mut line = LineWriter::new(x, y + 2 + i as u16);
+        line.set_max_width(max_width);
+        line.write(LineColor::Address, format_args!("{:04x} {:04x}", edit.offset >> 16, edit.offset & 0xFFFF));
+        line.write_str(LineColor::Regular, ":  ");
+        line.write(LineColor::Zero, format_args!("{:02x} → ", edit.old));
+        line.write(LineColor::Modified, format_args!("{:02x}", edit.new));
+        lines.push(line);
+    }
+    
+    for i in 0..height {
+        queue!(stdout(), MoveTo(0, y + i), Clear(ClearType::CurrentLine))?;
+    }
+    
+    for mut line in lines.into_iter().filter(|line| line.y() < y + height) {
+        line.flush()?;
+    }
+    
+    Ok(())
+}
+
+/// Lists the digests of each hash below the range they have been calculated over
+fn draw_hashes(digests: &Digests, selected: usize, x: u16, y: u16, height: u16, width: u16) -> Result<()> {
+    let max_width = Some(width.saturating_sub(x) as usize);
+    let mut lines = Vec::new();
+    
+    let mut header = LineWriter::new(x, y);
+    header.set_max_width(max_width);
+    header.write(LineColor::Emphasis, format_args!("Hashes of {}", format_inclusive_range(&digests.range)));
+    lines.push(header);
+    
+    for (i, (name, value)) in HASH_NAMES.iter().zip(&digests.values).enumerate() {
+        let mut line = LineWriter::new(x, y + 2 + i as u16);
+        line.set_max_width(max_width);
+        line.write(LineColor::Regular, format_args!("{name:<9}"));
+        
+        let color = if i == selected { LineColor::Highlighted } else { LineColor::Regular };
+        line.write_str(color, value);
+        lines.push(line);
+    }
+    
+    for i in 0..height {
+        queue!(stdout(), MoveTo(0, y + i), Clear(ClearType::CurrentLine))?;
+    }
+    
+    for mut line in lines.into_iter().filter(|line| line.y() < y + height) {
+        line.flush()?;
+    }
+    
+    Ok(())
+}
+
+/// Shows how often each byte value occurs as a 16x16 grid (high nibble down, low nibble across),
+/// followed by the totals of a few classes of values
+fn draw_histogram(state: &State, selected: u8, x: u16, y: u16, height: u16, width: u16) -> Result<()> {
+    let mut lines = Vec::new();
     
-    line2.seek(width - percentage_string.len() as u16)?;
-    line2.write_str(LineColor::Regular, &percentage_string)?;
+    if let Some(histogram) = &state.file.histogram {
+        let most_frequent = histogram.most_frequent();
+        
+        let mut header = LineWriter::new(x, y);
+        header.write_whitespace("  ");
+        for low in 0..0x10 {
+            header.write(LineColor::Zero, format_args!("  _{low:x}"));
+        }
+        lines.push(header);
+        
+        for high in 0..0x10u8 {
+            let mut line = LineWriter::new(x, y + 1 + high as u16);
+            line.write(LineColor::Address, format_args!("{high:x}_"));
+            
+            for low in 0..0x10u8 {
+                let value = high << 4 | low;
+                let count = histogram.counts[value as usize];
+                
+                let color = if value == selected {
+                    LineColor::Highlighted
+                } else if value == most_frequent && count > 0 {
+                    LineColor::Emphasis
+                } else if count == 0 {
+                    LineColor::Zero
+                } else {
+                    LineColor::Regular
+                };
+                
+                line.write_whitespace(" ");
+                line.write(color, format_args!("{:>3}", format_count(count)));
+            }
+            
+            lines.push(line);
+        }
+        
+        let total = histogram.range.len().max(1);
+        let classes = [
+            ("zero", histogram.counts[0]),
+            ("printable ASCII", histogram.class_count(0x20..0x7f)),
+            ("high-bit", histogram.class_count(0x80..=0xff)),
+        ];
+        
+        let mut totals = LineWriter::new(x, y + 0x12);
+        totals.set_max_width(Some(width.saturating_sub(x) as usize));
+        for (name, count) in classes {
+            totals.write(LineColor::Emphasis, format_args!("{name}: "));
+            totals.write(LineColor::Regular, format_args!("{count} ({:.1}%)", count as f64 * 100.0 / total as f64));
+            totals.write_whitespace("  ");
+        }
+        lines.push(totals);
+    }
+    
+    for i in 0..height {
+        queue!(stdout(), MoveTo(0, y + i), Clear(ClearType::CurrentLine))?;
+    }
+    
+    for mut line in lines.into_iter().filter(|line| line.y() < y + height) {
+        line.flush()?;
+    }
+    
+    Ok(())
+}
+
+/// Shows the strings matching the filter with their offsets, scrolling to keep the selected one visible
+fn draw_strings(panel: &mut StringsPanel, x: u16, y: u16, height: u16, width: u16) -> Result<()> {
+    let visible_rows = height as usize;
+    
+    if panel.selected < panel.scroll {
+        panel.scroll = panel.selected;
+    } else if panel.selected >= panel.scroll + visible_rows {
+        panel.scroll = panel.selected + 1 - visible_rows;
+    }
+    
+    let matches = panel.matches();
+    
+    for i in 0..height {
+        queue!(stdout(), MoveTo(0, y + i), Clear(ClearType::CurrentLine))?;
+        
+        let index = panel.scroll + i as usize;
+        let Some(string) = matches.get(index) else {
+            continue;
+        };
+        
+        let mut line = LineWriter::new(x, y + i);
+        line.set_max_width(Some(width.saturating_sub(x * 2) as usize));
+        
+        line.write(LineColor::Address, format_args!("{:04x} {:04x}", string.offset >> 16, string.offset & 0xFFFF));
+        line.write_str(LineColor::Regular, ":  ");
+        if string.utf16 {
+            line.write_str(LineColor::Zero, "UTF-16 ");
+        }
+        
+        let text_color = if index == panel.selected { LineColor::Highlighted } else { LineColor::Regular };
+        line.write_str(text_color, &string.text.replace('\t', " "));
+        line.flush()?;
+    }
+    
+    Ok(())
+}
+
+/// Shows the matches whose offsets contain the filter below a separator, with the bytes around them.
+/// Scrolls to keep the selected one visible.
+fn draw_matches(panel: &mut MatchesPanel, matches: &MatchList, bytes: &mut dyn FileBuffer, x: u16, y: u16, height: u16, width: u16) -> Result<()> {
+    let visible_rows = height.saturating_sub(1) as usize;
+    
+    if panel.selected < panel.scroll {
+        panel.scroll = panel.selected;
+    } else if panel.selected >= panel.scroll + visible_rows {
+        panel.scroll = panel.selected + 1 - visible_rows;
+    }
+    
+    let filtered = panel.filtered(matches);
+    if height == 0 {
+        return Ok(());
+    }
+    
+    for i in 0..height {
+        queue!(stdout(), MoveTo(0, y + i), Clear(ClearType::CurrentLine))?;
+    }
+    
+    let mut separator = LineWriter::new(x, y);
+    separator.write_str(LineColor::Zero, &"─".repeat(width.saturating_sub(x * 2) as usize));
+    separator.flush()?;
+    
+    for (i, &offset) in filtered.iter().skip(panel.scroll).take(visible_rows).enumerate() {
+        let index = panel.scroll + i;
+        let preview_start = offset.saturating_sub(MATCH_CONTEXT);
+        let preview = bytes.read(preview_start..usize::min(preview_start + MATCH_PREVIEW_LEN, bytes.len()))?;
+        let needle_range = offset..offset + matches.needle.len();
+        
+        let mut line = LineWriter::new(x, y + 1 + i as u16);
+        line.set_max_width(Some(width.saturating_sub(x * 2) as usize));
+        
+        let address_color = if index == panel.selected { LineColor::Highlighted } else { LineColor::Address };
+        line.write(address_color, format_args!("{:04x} {:04x}", offset >> 16, offset & 0xFFFF));
+        line.write_str(LineColor::Regular, ":  ");
+        
+        for (j, &byte) in preview.iter().enumerate() {
+            let color = if needle_range.contains(&(preview_start + j)) { LineColor::Emphasis } else { LineColor::Zero };
+            line.write(color, format_args!("{byte:02x} "));
+        }
+        for _ in preview.len()..MATCH_PREVIEW_LEN {
+            line.write_whitespace("   ");
+        }
+        
+        line.write_whitespace(" ");
+        for (j, &byte) in preview.iter().enumerate() {
+            let color = if needle_range.contains(&(preview_start + j)) { LineColor::Emphasis } else { LineColor::Zero };
+            let char = if byte.is_ascii_graphic() || byte == b' ' { byte as char } else { '.' };
+            line.write_char(color, char);
+        }
+        
+        line.flush()?;
+    }
+    
+    Ok(())
+}
+
+/// Shows the sections matching the filter with their offsets and sizes, scrolling to keep the selected one visible
+fn draw_sections(panel: &mut SectionsPanel, x: u16, y: u16, height: u16, width: u16) -> Result<()> {
+    let visible_rows = height as usize;
+    
+    if panel.selected < panel.scroll {
+        panel.scroll = panel.selected;
+    } else if panel.selected >= panel.scroll + visible_rows {
+        panel.scroll = panel.selected + 1 - visible_rows;
+    }
+    
+    let matches = panel.matches();
+    
+    for i in 0..height {
+        queue!(stdout(), MoveTo(0, y + i), Clear(ClearType::CurrentLine))?;
+        
+        let index = panel.scroll + i as usize;
+        let Some(section) = matches.get(index) else {
+            continue;
+        };
+        
+        let mut line = LineWriter::new(x, y + i);
+        line.set_max_width(Some(width.saturating_sub(x * 2) as usize));
+        
+        line.write(LineColor::Address, format_args!("{:04x} {:04x}", section.offset >> 16, section.offset & 0xFFFF));
+        line.write_str(LineColor::Regular, ":  ");
+        line.write(LineColor::Zero, format_args!("{:>10}  ", format!("0x{:x}", section.size)));
+        
+        let name_color = if index == panel.selected { LineColor::Highlighted } else { LineColor::Regular };
+        line.write_str(name_color, &section.name);
+        line.flush()?;
+    }
+    
+    Ok(())
+}
+
+/// Draws the list of recently opened files, which is shown before any file has been opened
+/// Number of files the recent files picker can show at once in a terminal `terminal_height` rows high
+pub fn recent_files_rows(config: &Config, terminal_height: u16) -> usize {
+    let margin_vertical = config.appearance.margin_vertical;
+    terminal_height.saturating_sub(margin_vertical * 2 + PADDING_TOP + PADDING_BOTTOM) as usize
+}
+
+pub fn draw_recent_files(config: &Config, picker: &RecentFiles) -> Result<()> {
+    let (width, height) = terminal::size()?;
+    let Appearance { margin_horizontal: x, margin_vertical, .. } = config.appearance;
+    let y = margin_vertical + PADDING_TOP;
+    let list_height = recent_files_rows(config, height) as u16;
+    
+    queue!(stdout(), MoveTo(0, 0), Clear(ClearType::CurrentLine))?;
+    let mut title = LineWriter::new(0, 0);
+    title.set_max_width(Some(width as usize));
+    title.write_str(LineColor::Title, "Recent files");
+    title.flush()?;
+    
+    let now = unix_time();
+    
+    for i in 0..list_height {
+        queue!(stdout(), MoveTo(0, y + i), Clear(ClearType::CurrentLine))?;
+        
+        let mut line = LineWriter::new(x, y + i);
+        line.set_max_width(Some(width.saturating_sub(x * 2) as usize));
+        
+        let index = picker.scroll + i as usize;
+        let Some(file) = picker.files.get(index) else {
+            if picker.files.is_empty() && i == 0 {
+                line.write_str(LineColor::Zero, "(no recent files left)");
+                line.flush()?;
+            }
+            continue;
+        };
+        
+        line.write(LineColor::Zero, format_args!("{:>13}  ", format_age(now.saturating_sub(file.last_opened))));
+        
+        // files that don't exist anymore are dimmed
+        let size = file.size.map_or_else(|| "missing".to_owned(), |size| format_size(size as usize));
+        line.write(LineColor::Zero, format_args!("{size:>10}  "));
+        
+        let path_color = match file.size {
+            _ if index == picker.selected => LineColor::Highlighted,
+            Some(_) => LineColor::Regular,
+            None => LineColor::Zero,
+        };
+        line.write(path_color, format_args!("{}", file.path.display()));
+        line.flush()?;
+    }
+    
+    let bottom_y = height.saturating_sub(2);
+    queue!(stdout(), MoveTo(0, bottom_y), Clear(ClearType::CurrentLine), MoveTo(0, bottom_y + 1), Clear(ClearType::CurrentLine))?;
+    
+    let mut line1 = LineWriter::new(0, bottom_y);
+    let mut line2 = LineWriter::new(0, bottom_y + 1);
+    line1.set_max_width(Some(width as usize));
+    line2.set_max_width(Some(width as usize));
+    
+    if let Some(message) = &picker.message {
+        line1.write_str(LineColor::Regular, message);
+    }
+    
+    for (i, (key, description)) in [("Up/Down", " select"), ("Enter", " open"), ("D", " remove"), ("Esc", " quit")].into_iter().enumerate() {
+        if i > 0 {
+            line2.write_str(LineColor::Regular, ", ");
+        }
+        line2.write_str(HINT_COLORS.0, key);
+        line2.write_str(HINT_COLORS.1, description);
+    }
     
     line1.flush()?;
     line2.flush()?;
+    execute!(stdout(), cursor::Hide)?;
     Ok(())
 }
 
-fn draw_line(state: &State<'_>, x: u16, y: u16, row_idx: usize) -> Result<()> {
+/// How long ago something happened, like `5 minutes ago`
+fn format_age(seconds: u64) -> String {
+    let (value, unit) = match seconds {
+        0..60 => return "just now".to_owned(),
+        60..3_600 => (seconds / 60, "minute"),
+        3_600..86_400 => (seconds / 3_600, "hour"),
+        _ => (seconds / 86_400, "day"),
+    };
+    
+    if value == 1 { format!("1 {unit} ago") } else { format!("{value} {unit}s ago") }
+}
+
+/// Shortens a count to at most 3 characters for the histogram grid, like `12k`
+fn format_count(count: usize) -> String {
+    match count {
+        0..1_000 => count.to_string(),
+        1_000..100_000 => format!("{}k", count / 1_000),
+        100_000..1_000_000 => format!(".{}M", count / 100_000),
+        1_000_000..100_000_000 => format!("{}M", count / 1_000_000),
+        100_000_000..1_000_000_000 => format!(".{}G", count / 100_000_000),
+        _ => format!("{}G", count / 1_000_000_000),
+    }
+}
+
+/// Describes a selected range, like `0x120..0x148 (0x28 / 40 bytes)`
+fn format_range(range: &Range<usize>) -> String {
+    format!("0x{:x}..0x{:x} (0x{:x} / {} bytes)", range.start, range.end, range.len(), range.len())
+}
+
+/// Size in bytes, or in KiB, MiB or GiB for larger sizes
+fn format_size(size: usize) -> String {
+    const UNITS: [&str; 3] = ["KiB", "MiB", "GiB"];
+    
+    if size == 1 {
+        return "1 byte".to_owned();
+    }
+    if size < 0x400 {
+        return format!("{size} bytes");
+    }
+    
+    let mut value = size as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    
+    format!("{value:.1} {}", UNITS[unit])
+}
+
+/// Like [`format_range`], but with the offset of the last byte instead of the one after it
+fn format_inclusive_range(range: &Range<usize>) -> String {
+    if range.is_empty() {
+        return "0 bytes".to_owned();
+    }
+    
+    format!("0x{:x} to 0x{:x} inclusive (0x{:x} / {} bytes)", range.start, range.end - 1, range.len(), range.len())
+}
+
+/// Writes the text typed into a prompt with the cursor on it, which sits after the text when it's at the end.
+/// With `group_size`, the text is split into groups of that many characters, like the bytes of a hex string.
+fn draw_prompt_buffer(line: &mut LineWriter, buffer: &PromptBuffer, group_size: Option<usize>) {
+    for (i, (offset, c)) in buffer.text().char_indices().enumerate() {
+        if let Some(group_size) = group_size && i > 0 && i.is_multiple_of(group_size) {
+            line.write_whitespace(" ");
+        }
+        
+        let color = if offset == buffer.cursor() { LineColor::TextCursor } else { LineColor::Regular };
+        line.write_char(color, c);
+    }
+    
+    if buffer.cursor() == buffer.text().len() {
+        // the cursor starts a new group once the last one is full
+        let char_count = buffer.text().chars().count();
+        if let Some(group_size) = group_size && char_count > 0 && char_count.is_multiple_of(group_size) {
+            line.write_whitespace(" ");
+        }
+        
+        line.write_char(LineColor::TextCursor, ' ');
+    }
+}
+
+fn draw_completion_candidates(line: &mut LineWriter, candidates: &[String], width: u16) {
+    if candidates.is_empty() {
+        line.write_str(LineColor::Zero, "(no matches)");
+        return;
+    }
+    
+    // leave room for the "(n more)" suffix
+    let max_width = (width as usize).saturating_sub(16);
+    let mut used_width = 0;
+    
+    for (i, candidate) in candidates.iter().enumerate() {
+        let candidate_width = display_width(candidate) + 2;
+        
+        if used_width + candidate_width > max_width {
+            line.write(LineColor::Zero, format_args!("({} more)", candidates.len() - i));
+            break;
+        }
+        
+        line.write_str(LineColor::Regular, candidate);
+        line.write_whitespace("  ");
+        used_width += candidate_width;
+    }
+}
+
+fn draw_line(state: &mut State, writer: &mut impl StyledWrite, row_idx: usize, selected_range: Option<Range<usize>>) -> Result<()> {
     let offset = row_idx * 0x10;
     
-    let modified_bytes = state.modified_bytes.get(&row_idx).copied().unwrap_or_default();
+    let address_color = if state.show_entropy {
+        let entropy = state.file.entropy.entropy_at(&mut *state.file.bytes, offset)?;
+        LineColor::Entropy(entropy_level(entropy, ENTROPY_LEVELS) as u8)
+    } else {
+        LineColor::Address
+    };
+    
+    let row_bytes = state.file.bytes.read(offset..usize::min(
+        offset + 0x10,
+        state.file.bytes.len(),
+    ))?;
     
-    let mut writer = LineWriter::new(x, y);
+    let mut highlights = [None; 0x10];
     
+    if let Some(comparison) = &mut state.file.comparison {
+        for (highlight, differs) in highlights.iter_mut().zip(comparison.row_differences(offset, row_bytes)?) {
+            if differs {
+                *highlight = Some(LineColor::Different);
+            }
+        }
+    }
+    
+    for (&modified_offset, _) in state.file.modified_bytes.range(offset..offset + 0x10) {
+        highlights[modified_offset - offset] = Some(LineColor::Modified);
+    }
+    
+    if let Some(selected_range) = selected_range {
+        for selected_offset in usize::max(selected_range.start, offset)..usize::min(selected_range.end, offset + 0x10) {
+            highlights[selected_offset - offset] = Some(LineColor::Highlighted);
+        }
+    }
+    
+    format_row(writer, offset, row_bytes, highlights, address_color, state.text_encoding, state.control_pictures);
+    Ok(())
+}
+
+/// Columns taken up by the address and the colon after it
+const ADDRESS_WIDTH: u16 = 12;
+/// Columns taken up by a byte value and the space after it
+const BYTE_WIDTH: u16 = 3;
+
+/// Screen column of the first digit of a byte value in a row drawn by [`format_row`] at `x`
+fn hex_column(x: u16, byte: usize) -> u16 {
+    // the halves of the row are separated by an extra space
+    x + ADDRESS_WIDTH + byte as u16 * BYTE_WIDTH + (byte >= HALF_ROW) as u16
+}
+
+/// Screen column of a byte in the text column of a row drawn by [`format_row`] at `x`
+fn text_column(x: u16, byte: usize) -> u16 {
+    hex_column(x, 0x10) + 1 + byte as u16 + (byte >= HALF_ROW) as u16
+}
+
+/// Part of a row of the hex view that is at some screen column
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RowPosition {
+    /// A digit of a byte value (or the space after it, which counts as the low digit)
+    Hex { byte: usize, digit: usize },
+    /// A byte in the text column
+    Text(usize),
+}
+
+/// Finds the byte at a screen column of a row drawn at `x`, or None for the address, the gaps and past the end
+pub fn row_position_at(x: u16, column: u16) -> Option<RowPosition> {
+    let hex = (0..0x10).find_map(|byte| {
+        let start = hex_column(x, byte);
+        (start..start + BYTE_WIDTH).contains(&column)
+            .then(|| RowPosition::Hex { byte, digit: usize::min((column - start) as usize, 1) })
+    });
+    
+    hex.or_else(|| (0..0x10).find(|&byte| text_column(x, byte) == column).map(RowPosition::Text))
+}
+
+/// Writes one row of the hex view: the address, up to 16 byte values in two halves, and their text representation.
+/// Bytes with a highlight color (e.g. modified ones) are drawn in that color.
+/// Used both for drawing the TUI and for dumping the file as text.
+pub fn format_row(
+    writer: &mut impl StyledWrite,
+    offset: usize,
+    row_bytes: &[u8],
+    highlights: [Option<LineColor>; 0x10],
+    address_color: LineColor,
+    encoding: TextEncoding,
+    control_pictures: bool,
+) {
     // Write offset
-    writer.write(LineColor::Address, format_args!("{:04x} {:04x}", offset >> 16, offset & 0xFFFF))?;
-    writer.write_str(LineColor::Regular, ":  ")?;
-    
-    let first_half = &state.bytes[offset..usize::min(
-        offset + 0x8, 
-        state.bytes.len(),
-    )];
-    let second_half = &state.bytes[usize::min(
-        offset + 0x8, 
-        state.bytes.len(),
-    )..usize::min(
-        offset + 0x10, 
-        state.bytes.len(),
-    )];
+    writer.write(address_color, format_args!("{:04x} {:04x}", offset >> 16, offset & 0xFFFF));
+    writer.write_str(LineColor::Regular, ":  ");
+    
+    let (first_half, second_half) = row_bytes.split_at(usize::min(row_bytes.len(), HALF_ROW));
     
     let color_of = |col: usize, x: u8| {
-        if modified_bytes[col] {
-            LineColor::Modified
+        if let Some(highlight) = highlights[col] {
+            highlight
         } else if x == 0 {
             LineColor::Zero
         } else {
@@ -220,44 +1424,311 @@ fn draw_line(state: &State<'_>, x: u16, y: u16, row_idx: usize) -> Result<()> {
     };
     
     // Write byte values
-    let write_byte = |writer: &mut LineWriter, col: usize, x: u8| -> Result<()> {
-        writer.write(color_of(col, x), format_args!("{:02x} ", x))?;
-        Ok(())
+    let write_byte = |writer: &mut _, col: usize, x: u8| {
+        StyledWrite::write(writer, color_of(col, x), format_args!("{:02x} ", x));
     };
     
     for (i, x) in first_half.iter().copied().enumerate() {
-        write_byte(&mut writer, i, x)?;
+        write_byte(writer, i, x);
     }
     
     writer.write_whitespace(" ");
     
     for (i, x) in second_half.iter().copied().enumerate() {
-        write_byte(&mut writer, i + 0x8, x)?;
+        write_byte(writer, i + HALF_ROW, x);
     }
     
-    // Write ascii text
-    writer.seek(64)?;
+    // Pad partial rows so the text column always lines up
+    for _ in row_bytes.len()..0x10 {
+        writer.write_whitespace("   ");
+    }
+    writer.write_whitespace(" ");
     
-    for x in first_half.iter().copied() {
-        let mut ascii = x as char;
-        if x & 0x80 == 1 || !ascii.is_ascii_graphic() {
-            ascii = '.';
+    // Write text
+    let cells = encoding.decode_row(row_bytes, control_pictures);
+    let (first_half, second_half) = cells.split_at(usize::min(cells.len(), HALF_ROW));
+    
+    let write_cells = |writer: &mut _, cells: &[TextCell]| {
+        for &cell in cells {
+            match cell {
+                TextCell::Char(char) => StyledWrite::write_char(writer, LineColor::Regular, char),
+                TextCell::Picture(char) => StyledWrite::write_char(writer, LineColor::Zero, char),
+                TextCell::Covered => {},
+            }
         }
+    };
+    
+    write_cells(writer, first_half);
+    writer.write_whitespace(" ");
+    write_cells(writer, second_half);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    
+    use super::*;
+    use crate::{buffer::InMemoryBuffer, max_col_for_row, max_row, util::TextWriter};
+    
+    fn row(offset: usize, bytes: &[u8], encoding: TextEncoding, control_pictures: bool) -> String {
+        let mut writer = TextWriter::new(false);
+        format_row(&mut writer, offset, bytes, [None; 0x10], LineColor::Address, encoding, control_pictures);
+        String::from_utf8(writer.buffer).unwrap()
+    }
+    
+    #[test]
+    fn full_row() {
+        let bytes: Vec<u8> = (0..0x10).collect();
         
-        writer.write_char(LineColor::Regular, ascii)?;
+        assert_eq!(
+            row(0x12340, &bytes, TextEncoding::Ascii, false),
+            "0001 2340:  00 01 02 03 04 05 06 07  08 09 0a 0b 0c 0d 0e 0f  ........ ........",
+        );
     }
     
-    writer.write_whitespace(" ");
+    #[test]
+    fn text_column() {
+        assert_eq!(
+            row(0, b"Hello, world!\n\0\xff", TextEncoding::Ascii, false),
+            "0000 0000:  48 65 6c 6c 6f 2c 20 77  6f 72 6c 64 21 0a 00 ff  Hello,.w orld!...",
+        );
+        assert_eq!(
+            row(0, b"Hello, world!\n\0\xff", TextEncoding::Ascii, true),
+            "0000 0000:  48 65 6c 6c 6f 2c 20 77  6f 72 6c 64 21 0a 00 ff  Hello,␣w orld!␊␀.",
+        );
+    }
     
-    for x in second_half.iter().copied() {
-        let mut ascii = x as char;
-        if x & 0x80 == 1 || !ascii.is_ascii_graphic() {
-            ascii = '.';
+    #[test]
+    fn partial_rows_line_up() {
+        let full = row(0, &[b'Z'; 0x10], TextEncoding::Ascii, false);
+        let text_start = full.find('Z').unwrap();
+        
+        for len in [1, 7, 8, 9, 15] {
+            let partial = row(0x10, &b"ABCDEFGHIJKLMNOP"[..len], TextEncoding::Ascii, false);
+            assert_eq!(partial.find('A'), Some(text_start), "{len} bytes");
         }
         
-        writer.write_char(LineColor::Regular, ascii)?;
+        assert_eq!(row(0x10, b"ABC", TextEncoding::Ascii, false), format!("0000 0010:  41 42 43 {}ABC ", " ".repeat(13 * 3 + 2)));
     }
     
-    writer.flush()?;
-    Ok(())
+    #[test]
+    fn wide_characters_keep_the_width() {
+        let ascii = row(0, &[b'Z'; 0x10], TextEncoding::Ascii, false);
+        let sjis = row(0, b"\x93\xfa\x96\x7b\x8c\xea\x82\xcc\x83\x65\x83\x4c\x83\x58\x83\x67", TextEncoding::ShiftJis, false);
+        
+        assert!(sjis.ends_with("  日本語の テキスト"), "{sjis}");
+        assert_eq!(display_width(&sjis), display_width(&ascii));
+    }
+    
+    #[test]
+    fn plain_text_has_no_colors() {
+        let mut highlights = [None; 0x10];
+        highlights[3] = Some(LineColor::Modified);
+        
+        let mut writer = TextWriter::new(false);
+        format_row(&mut writer, 0, &[0, 1, 2, 3], highlights, LineColor::Entropy(3), TextEncoding::Ascii, false);
+        assert!(!writer.buffer.contains(&0x1b));
+        
+        let mut writer = TextWriter::new(true);
+        format_row(&mut writer, 0, &[0, 1, 2, 3], highlights, LineColor::Entropy(3), TextEncoding::Ascii, false);
+        assert!(writer.buffer.contains(&0x1b));
+    }
+    
+    /// Rows of the hex view that get drawn for the focused pane, as plain text
+    fn drawn_rows(state: &mut State) -> Vec<String> {
+        let Pane { scroll_pos, height, .. } = *state.pane();
+        let row_count = state.content_rows();
+        
+        (scroll_pos..scroll_pos + height as usize).take_while(|&row_idx| row_idx < row_count).map(|row_idx| {
+            let mut writer = TextWriter::new(false);
+            draw_line(state, &mut writer, row_idx, None).unwrap();
+            String::from_utf8(writer.buffer).unwrap()
+        }).collect()
+    }
+    
+    #[test]
+    fn tiny_files_draw_every_byte() {
+        for len in [0, 1, 15, 16, 17] {
+            let bytes: Vec<u8> = (b'A'..).take(len).collect();
+            let mut state = state_with_bytes(&bytes);
+            
+            let expected: Vec<String> = bytes.chunks(0x10).enumerate()
+                .map(|(i, chunk)| row(i * 0x10, chunk, TextEncoding::Ascii, state.control_pictures))
+                .collect();
+            assert_eq!(drawn_rows(&mut state), expected, "{len} bytes");
+        }
+        
+        let mut state = state_with_bytes(&(b'A'..).take(17).collect::<Vec<u8>>());
+        assert_eq!(drawn_rows(&mut state)[1], format!("0000 0010:  51 {}Q ", " ".repeat(15 * 3 + 2)));
+    }
+    
+    #[test]
+    fn cursor_on_the_last_byte_of_tiny_files() {
+        for len in [1, 15, 16, 17] {
+            let mut state = state_with_bytes(&vec![0; len]);
+            let row = max_row(len, false);
+            state.pane_mut().selection = Some((row, max_col_for_row(len, row, false) / 2 * 2));
+            
+            let last_byte = (len - 1) % 0x10;
+            assert_eq!(cursor_position(state.pane(), 2), Some((hex_column(2, last_byte), state.pane().y + row as u16)), "{len} bytes");
+        }
+    }
+    
+    #[test]
+    fn empty_file() {
+        let items = [StatusItem::Named(NamedStatusItem::Percent), StatusItem::Named(NamedStatusItem::Offset), StatusItem::Named(NamedStatusItem::Value)];
+        let mut state = state_with_bytes(&[]);
+        
+        assert_eq!(drawn_rows(&mut state), Vec::<String>::new());
+        assert_eq!(status_text(&items, &mut state), "empty file");
+        assert_eq!(cursor_position(state.pane(), 2), None);
+        
+        // the cursor sits where the first byte would be typed
+        state.pane_mut().show_cursor(0);
+        assert_eq!(status_text(&items, &mut state), "empty file  0x0000_0000  --");
+        assert_eq!(cursor_position(state.pane(), 2), Some((hex_column(2, 0), state.pane().y)));
+    }
+    
+    /// State with a single file of `bytes`, shown in a pane 4 rows high
+    fn state_with_bytes(bytes: &[u8]) -> State {
+        let file = OpenFile::new(None, Box::new(InMemoryBuffer(Arc::new(bytes.to_vec()))));
+        let mut state = State::new(&Config::default_config(), vec![file], false, false);
+        state.pane_mut().height = 4;
+        state
+    }
+    
+    /// Text of the status segments, with the gaps between them
+    fn status_text(items: &[StatusItem], state: &mut State) -> String {
+        let segments = status_segments(items, state, false).unwrap();
+        
+        segments.iter().enumerate().map(|(i, segment)| {
+            let text = match segment {
+                StatusSegment::Hints => String::new(),
+                StatusSegment::Text(text) => text.clone(),
+                StatusSegment::Styled(pieces) => pieces.iter().map(|(_, text)| text.as_str()).collect(),
+            };
+            
+            match segments.get(i + 1) {
+                Some(next) if segment.separated_from(next) => text + STATUS_SEPARATOR,
+                _ => text,
+            }
+        }).collect()
+    }
+    
+    #[test]
+    fn offsets_have_both_halves() {
+        assert_eq!(format_offset(0), "0x0000_0000");
+        assert_eq!(format_offset(0x12a3f), "0x0001_2a3f");
+        assert_eq!(format_offset(0xffff_ffff), "0xffff_ffff");
+    }
+    
+    #[test]
+    fn values_in_hex_and_decimal() {
+        assert_eq!(format_value(Some(0x4d)), "0x4d (77)");
+        assert_eq!(format_value(Some(0)), "0x00 (0)");
+        assert_eq!(format_value(Some(0xff)), "0xff (255)");
+        assert_eq!(format_value(None), "--");
+    }
+    
+    #[test]
+    fn value_under_the_cursor() {
+        let items = [StatusItem::Named(NamedStatusItem::Offset), StatusItem::Named(NamedStatusItem::Value)];
+        let mut state = state_with_bytes(&[0x00, 0x11, 0x22, 0x33, 0x4d]);
+        
+        // nothing to show in pager mode
+        assert_eq!(status_text(&items, &mut state), "");
+        
+        state.pane_mut().selection = Some((0, 8));
+        assert_eq!(status_text(&items, &mut state), "0x0000_0004  0x4d (77)");
+        
+        state.write_byte(4, 0x4e).unwrap();
+        assert_eq!(status_text(&items, &mut state), "0x0000_0004  0x4e (78) [modified]");
+        
+        // typing past the end of the file
+        state.pane_mut().selection = Some((0, 10));
+        assert_eq!(status_text(&items, &mut state), "0x0000_0005  --");
+    }
+    
+    #[test]
+    fn sizes_and_ranges() {
+        assert_eq!(format_size(1), "1 byte");
+        assert_eq!(format_size(0x3ff), "1023 bytes");
+        assert_eq!(format_size(0x400), "1.0 KiB");
+        assert_eq!(format_size(0x180000), "1.5 MiB");
+        assert_eq!(format_size(0x40000000 * 3), "3.0 GiB");
+        
+        assert_eq!(format_range(&(0x120..0x148)), "0x120..0x148 (0x28 / 40 bytes)");
+        assert_eq!(format_inclusive_range(&(0x120..0x148)), "0x120 to 0x147 inclusive (0x28 / 40 bytes)");
+        assert_eq!(format_inclusive_range(&(5..5)), "0 bytes");
+    }
+    
+    #[test]
+    fn counts_fit_into_3_characters() {
+        let counts = [(0, "0"), (999, "999"), (1_000, "1k"), (99_999, "99k"), (100_000, ".1M"), (12_345_678, "12M"), (999_999_999, ".9G"), (2_000_000_000, "2G")];
+        
+        for (count, text) in counts {
+            assert_eq!(format_count(count), text);
+        }
+    }
+    
+    fn styled(text: &str) -> StatusSegment {
+        StatusSegment::Styled(vec![(LineColor::Regular, text.to_owned())])
+    }
+    
+    /// Segments 10, 10, 11, 3 and 9 columns wide, including the gaps
+    fn right_segments() -> Vec<StatusSegment> {
+        vec![styled("UTF-16LE"), styled(" FOLLOW "), styled("0x0000_0004"), StatusSegment::Text(" = ".to_owned()), styled("0x4d (77)")]
+    }
+    
+    fn layout(width: usize) -> (usize, usize, usize) {
+        let layout = layout_status_line(&right_segments(), 20, width);
+        (layout.first_shown, layout.right_width, layout.left_width)
+    }
+    
+    #[test]
+    fn wide_status_line_shows_everything() {
+        assert_eq!(layout(100), (0, 43, 56));
+        assert_eq!(layout(64), (0, 43, 20));
+    }
+    
+    #[test]
+    fn narrow_status_line_drops_segments_from_the_start() {
+        assert_eq!(layout(63), (1, 33, 29));
+        assert_eq!(layout(50), (2, 23, 26));
+    }
+    
+    #[test]
+    fn text_gets_dropped_with_the_item_before_it() {
+        // " = " would be left at the start of the right side
+        assert_eq!(layout(43), (4, 9, 33));
+        assert_eq!(layout(30), (4, 9, 20));
+    }
+    
+    #[test]
+    fn tiny_status_line_only_has_the_left_side() {
+        assert_eq!(layout(29), (5, 0, 28));
+        assert_eq!(layout(1), (5, 0, 0));
+        assert_eq!(layout(0), (5, 0, 0));
+        
+        assert_eq!(layout_status_line(&[], 20, 10), StatusLayout { first_shown: 0, right_width: 0, left_width: 9 });
+    }
+    
+    #[test]
+    fn text_next_to_missing_items_is_left_out() {
+        let text = |text: &str| StatusItem::Text { text: text.to_owned() };
+        let items = [
+            StatusItem::Named(NamedStatusItem::Percent),
+            text(" | "),
+            StatusItem::Named(NamedStatusItem::Follow),
+            StatusItem::Named(NamedStatusItem::Offset),
+            text(" = "),
+            StatusItem::Named(NamedStatusItem::Value),
+        ];
+        let mut state = state_with_bytes(&[0x4d; 0x100]);
+        
+        assert_eq!(status_text(&items, &mut state), "40 / 100, 25%");
+        
+        state.pane_mut().selection = Some((0, 0));
+        assert_eq!(status_text(&items, &mut state), "40 / 100, 25%  0x0000_0000 = 0x4d (77)");
+    }
 }
@@ -1,7 +1,11 @@
-use std::{fmt::{Arguments}, io::{Write, stdout}};
+use std::{borrow::Cow, fmt::{self, Arguments}, io::{Write, stdout}, mem};
 
 use anyhow::Result;
-use crossterm::{QueueableCommand, cursor::MoveTo, queue, style::{Attribute, ResetColor, SetAttribute, SetBackgroundColor, SetForegroundColor}};
+use base64::{Engine, prelude::BASE64_STANDARD};
+use crossterm::{QueueableCommand, cursor::MoveTo, queue, style::ResetColor};
+use unicode_width::UnicodeWidthChar;
+
+use crate::theme;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum LineColor {
@@ -10,56 +14,85 @@ pub enum LineColor {
     Highlighted,
     TextCursor,
     Modified,
+    Different,
     Address,
     Zero,
+    Title,
+    /// Step of the entropy color ramp, below [`theme::ENTROPY_LEVELS`]
+    Entropy(u8),
 }
 
 impl LineColor {
-    fn encode(self, buffer: &mut Vec<u8>) -> Result<()> {
-        match self {
-            LineColor::Regular => queue!(buffer, ResetColor),
-            LineColor::Emphasis => queue!(
-                buffer,
-                ResetColor,
-                SetForegroundColor(crossterm::style::Color::AnsiValue(39)),
-                SetAttribute(Attribute::Bold),
-            ),
-            LineColor::Highlighted => queue!(
-                buffer,
-                ResetColor,
-                SetForegroundColor(crossterm::style::Color::Black),
-                SetBackgroundColor(crossterm::style::Color::Grey),
-            ),
-            LineColor::TextCursor => queue!(
-                buffer,
-                ResetColor,
-                SetAttribute(Attribute::Reverse),
-            ),
-            LineColor::Modified => queue!(
-                buffer,
-                ResetColor,
-                SetForegroundColor(crossterm::style::Color::AnsiValue(215)),
-            ),
-            LineColor::Address => queue!(
-                buffer,
-                ResetColor,
-                SetForegroundColor(crossterm::style::Color::AnsiValue(206)),
-            ),
-            LineColor::Zero => queue!(
-                buffer,
-                ResetColor,
-                SetForegroundColor(crossterm::style::Color::DarkGrey),
-            ),
-        }.map_err(Into::into)
+    fn encode(self, buffer: &mut Vec<u8>) {
+        theme::current().style(self).apply(buffer).expect("Writing to a Vec should never fail");
     }
 }
 
+/// Columns taken up by the text in the terminal, counting wide characters twice and control characters not at all
+pub fn display_width(text: &str) -> usize {
+    text.chars().map(|c| c.width().unwrap_or(0)).sum()
+}
+
+/// Shortens text to at most `max_width` columns by replacing its middle with an ellipsis,
+/// which keeps both the start and the end of a path readable
+pub fn elide_middle(text: &str, max_width: usize) -> Cow<'_, str> {
+    if display_width(text) <= max_width {
+        return Cow::Borrowed(text);
+    }
+    if max_width == 0 {
+        return Cow::Borrowed("");
+    }
+    
+    // the end of a path is usually more interesting, so it gets the extra column
+    let kept = max_width - 1;
+    let head: String = take_width(text.chars(), kept / 2).into_iter().collect();
+    let tail: String = take_width(text.chars().rev(), kept - kept / 2).into_iter().rev().collect();
+    
+    Cow::Owned(format!("{head}…{tail}"))
+}
+
+/// Takes characters for as long as they fit into `max_width` columns
+fn take_width(chars: impl Iterator<Item = char>, max_width: usize) -> Vec<char> {
+    let mut width = 0;
+    
+    chars
+        .take_while(|c| {
+            width += c.width().unwrap_or(0);
+            width <= max_width
+        })
+        .collect()
+}
+
+/// Destination for colored text, so the same formatting code can draw to the screen or produce plain output.
+/// The text only gets collected in memory, so writing it can't fail, only flushing it to the terminal can.
+pub trait StyledWrite {
+    fn write_str(&mut self, color: LineColor, content: &str);
+    
+    fn write_char(&mut self, color: LineColor, content: char);
+    
+    fn write(&mut self, color: LineColor, content: Arguments<'_>);
+    
+    fn write_whitespace(&mut self, content: &str);
+}
+
 pub struct LineWriter {
     buffer: Vec<u8>,
     cur_color: Option<LineColor>,
     
     x: u16,
     y: u16,
+    
+    /// Columns written since the last seek, counting wide characters twice and including the ellipsis
+    width: usize,
+    /// Text going past this many columns gets cut off with an ellipsis
+    max_width: Option<usize>,
+    /// Where the last character that takes up any columns starts, along with the zero-width ones after it
+    last_char_start: usize,
+    last_char_width: usize,
+    truncated: bool,
+    
+    /// Reused for formatting, since that happens for every byte on the screen
+    scratch: String,
 }
 
 impl LineWriter {
@@ -69,63 +102,287 @@ impl LineWriter {
             cur_color: None,
             x,
             y,
+            width: 0,
+            max_width: None,
+            last_char_start: 0,
+            last_char_width: 0,
+            truncated: false,
+            scratch: String::new(),
         }
     }
     
-    pub fn write_str(&mut self, color: LineColor, content: &str) -> Result<()> {
-        if self.cur_color.is_none_or(|cur_color| cur_color != color) {
-            color.encode(&mut self.buffer)?;
-            self.cur_color = Some(color);
+    /// Continues on another line, keeping the buffers around for drawing many lines in a row
+    pub fn move_to(&mut self, x: u16, y: u16) -> Result<()> {
+        self.seek(x)?;
+        self.y = y;
+        Ok(())
+    }
+    
+    pub fn seek(&mut self, x: u16) -> Result<()> {
+        self.flush()?;
+        self.x = x;
+        self.width = 0;
+        self.truncated = false;
+        Ok(())
+    }
+    
+    pub fn y(&self) -> u16 {
+        self.y
+    }
+    
+    /// Column the next character will be written to, or None if the line has already been cut off
+    pub fn column(&self) -> Option<u16> {
+        (!self.truncated).then(|| self.offset_column(self.width))
+    }
+    
+    /// Column after the last one that has been written to, including the ellipsis
+    pub fn end_column(&self) -> u16 {
+        self.offset_column(self.max_width.map_or(self.width, |max_width| usize::min(self.width, max_width)))
+    }
+    
+    /// Column `width` columns to the right of the start of the line, stopping at the last one there is
+    fn offset_column(&self, width: usize) -> u16 {
+        self.x.saturating_add(u16::try_from(width).unwrap_or(u16::MAX))
+    }
+    
+    pub fn set_max_width(&mut self, max_width: Option<usize>) {
+        self.max_width = max_width;
+    }
+    
+    /// Queues the line to be written to the terminal, which happens once the frame is drawn
+    pub fn flush(&mut self) -> Result<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
         }
         
-        self.buffer.extend_from_slice(content.as_bytes());
+        let mut stdout = stdout();
+        stdout.queue(MoveTo(self.x, self.y))?;
+        stdout.write_all(&self.buffer)?;
+        
+        self.cur_color = None;
+        self.buffer.clear();
         Ok(())
     }
     
-    pub fn write_char(&mut self, color: LineColor, content: char) -> Result<()> {
+    /// Appends text, cutting it off with an ellipsis where it would go past the maximum width.
+    /// The width is counted the same way as [`display_width`], so the columns stay in sync with the terminal.
+    fn push(&mut self, content: &str) {
+        if self.truncated || self.max_width == Some(0) {
+            return;
+        }
+        
+        for c in content.chars() {
+            let char_width = c.width().unwrap_or(0);
+            
+            if let Some(max_width) = self.max_width && self.width + char_width > max_width {
+                // the last character makes room for the ellipsis if it doesn't fit after it
+                if self.width == max_width {
+                    self.buffer.truncate(self.last_char_start);
+                    self.width -= self.last_char_width;
+                }
+                self.buffer.extend_from_slice("…".as_bytes());
+                self.width += 1;
+                self.truncated = true;
+                return;
+            }
+            
+            if char_width > 0 {
+                self.last_char_start = self.buffer.len();
+                self.last_char_width = char_width;
+            }
+            let mut char_buffer: [u8; 4] = [0; 4];
+            self.buffer.extend_from_slice(c.encode_utf8(&mut char_buffer).as_bytes());
+            self.width += char_width;
+        }
+    }
+}
+
+impl StyledWrite for LineWriter {
+    fn write_str(&mut self, color: LineColor, content: &str) {
         if self.cur_color.is_none_or(|cur_color| cur_color != color) {
-            color.encode(&mut self.buffer)?;
+            color.encode(&mut self.buffer);
             self.cur_color = Some(color);
         }
         
+        self.push(content);
+    }
+    
+    fn write_char(&mut self, color: LineColor, content: char) {
         let mut buffer: [u8; 4] = [0; 4];
-        content.encode_utf8(&mut buffer);
-        self.buffer.extend_from_slice(&buffer[..content.len_utf8()]);
-        Ok(())
+        self.write_str(color, content.encode_utf8(&mut buffer));
     }
     
-    pub fn write(&mut self, color: LineColor, content: Arguments<'_>) -> Result<()> {
-        if self.cur_color.is_none_or(|cur_color| cur_color != color) {
-            color.encode(&mut self.buffer)?;
+    fn write(&mut self, color: LineColor, content: Arguments<'_>) {
+        let mut scratch = mem::take(&mut self.scratch);
+        scratch.clear();
+        fmt::Write::write_fmt(&mut scratch, content).expect("A Display implementation returned an error unexpectedly");
+        
+        self.write_str(color, &scratch);
+        self.scratch = scratch;
+    }
+    
+    fn write_whitespace(&mut self, content: &str) {
+        self.push(content);
+    }
+}
+
+/// Writes lines of text into a buffer, with colors as ANSI escape codes or without any colors
+pub struct TextWriter {
+    pub buffer: Vec<u8>,
+    colored: bool,
+    cur_color: Option<LineColor>,
+}
+
+impl TextWriter {
+    pub fn new(colored: bool) -> Self {
+        Self {
+            buffer: Vec::new(),
+            colored,
+            cur_color: None,
+        }
+    }
+    
+    fn set_color(&mut self, color: LineColor) {
+        if self.colored && self.cur_color.is_none_or(|cur_color| cur_color != color) {
+            color.encode(&mut self.buffer);
             self.cur_color = Some(color);
         }
-        
-        self.buffer.write_fmt(content)?;
-        Ok(())
     }
     
-    pub fn write_whitespace(&mut self, content: &str) {
+    pub fn end_line(&mut self) {
+        if self.cur_color.is_some() {
+            queue!(self.buffer, ResetColor).expect("Writing to a Vec should never fail");
+            self.cur_color = None;
+        }
+        
+        self.buffer.push(b'\n');
+    }
+}
+
+impl StyledWrite for TextWriter {
+    fn write_str(&mut self, color: LineColor, content: &str) {
+        self.set_color(color);
         self.buffer.extend_from_slice(content.as_bytes());
     }
     
-    pub fn seek(&mut self, x: u16) -> Result<()> {
-        self.flush()?;
-        self.x = x;
-        Ok(())
+    fn write_char(&mut self, color: LineColor, content: char) {
+        self.set_color(color);
+        
+        let mut buffer: [u8; 4] = [0; 4];
+        content.encode_utf8(&mut buffer);
+        self.buffer.extend_from_slice(&buffer[..content.len_utf8()]);
     }
     
-    pub fn flush(&mut self) -> Result<()> {
-        if self.buffer.is_empty() {
-            return Ok(());
+    fn write(&mut self, color: LineColor, content: Arguments<'_>) {
+        self.set_color(color);
+        // like format!, this only fails if a Display implementation is broken
+        self.buffer.write_fmt(content).expect("A Display implementation returned an error unexpectedly");
+    }
+    
+    fn write_whitespace(&mut self, content: &str) {
+        self.buffer.extend_from_slice(content.as_bytes());
+    }
+}
+
+/// Asks the terminal to put `text` into the clipboard (OSC 52), which also works over SSH.
+/// Terminals that don't support it silently ignore this.
+pub fn copy_to_clipboard(text: &str) -> Result<()> {
+    let mut stdout = stdout();
+    write!(stdout, "\x1b]52;c;{}\x07", BASE64_STANDARD.encode(text))?;
+    stdout.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    /// What a line ends up as when pushed in pieces with at most `max_width` columns
+    fn pushed(pieces: &[&str], max_width: Option<usize>) -> (String, u16) {
+        let mut line = LineWriter::new(10, 0);
+        line.set_max_width(max_width);
+        
+        for piece in pieces {
+            line.push(piece);
         }
         
-        let mut stdout = stdout();
-        stdout.queue(MoveTo(self.x, self.y))?;
-        stdout.write(&self.buffer)?;
-        stdout.flush()?;
+        (String::from_utf8(line.buffer.clone()).unwrap(), line.end_column())
+    }
+    
+    #[test]
+    fn width_of_multi_byte_text() {
+        assert_eq!(display_width(""), 0);
+        assert_eq!(display_width("héllo"), 5);
+        // a combining accent doesn't take up a column of its own
+        assert_eq!(display_width("he\u{301}llo"), 5);
+        assert_eq!(display_width("a\tb\n"), 2);
+    }
+    
+    #[test]
+    fn width_of_wide_text() {
+        assert_eq!(display_width("日本語"), 6);
+        assert_eq!(display_width("ｶﾀｶﾅ"), 4);
+        assert_eq!(display_width("a🦀b"), 4);
+    }
+    
+    #[test]
+    fn short_text_isnt_elided() {
+        assert!(matches!(elide_middle("short", 5), Cow::Borrowed("short")));
+        assert!(matches!(elide_middle("日本", 4), Cow::Borrowed("日本")));
+    }
+    
+    #[test]
+    fn elided_text_keeps_both_ends() {
+        assert_eq!(elide_middle("abcdefghij", 5), "ab…ij");
+        assert_eq!(elide_middle("abcdefghij", 6), "ab…hij");
+        assert_eq!(elide_middle("/home/user/some/long/path.bin", 16), "/home/u…path.bin");
+    }
+    
+    #[test]
+    fn elided_wide_text_stays_within_the_width() {
+        // a wide character that only half fits is left out
+        assert_eq!(elide_middle("日本語テキスト", 6), "日…ト");
+        assert_eq!(elide_middle("日本語テキスト", 8), "日…スト");
         
-        self.cur_color = None;
-        self.buffer.clear();
-        Ok(())
+        for max_width in 0..14 {
+            assert!(display_width(&elide_middle("日本語テキスト", max_width)) <= max_width, "{max_width}");
+        }
+    }
+    
+    #[test]
+    fn nothing_fits_into_no_columns() {
+        assert_eq!(elide_middle("text", 0), "");
+        assert_eq!(elide_middle("text", 1), "…");
+        assert_eq!(elide_middle("", 0), "");
+    }
+    
+    #[test]
+    fn line_without_limit() {
+        assert_eq!(pushed(&["héllo ", "日本"], None), ("héllo 日本".to_owned(), 20));
+    }
+    
+    #[test]
+    fn line_gets_cut_off_with_an_ellipsis() {
+        assert_eq!(pushed(&["abc", "def"], Some(6)), ("abcdef".to_owned(), 16));
+        assert_eq!(pushed(&["abc", "defg"], Some(6)), ("abcde…".to_owned(), 16));
+        assert_eq!(pushed(&["héllo wörld"], Some(6)), ("héllo…".to_owned(), 16));
+    }
+    
+    #[test]
+    fn wide_character_straddling_the_limit() {
+        // the ellipsis takes the column the wide character doesn't fit into
+        assert_eq!(pushed(&["abcd", "日本"], Some(5)), ("abcd…".to_owned(), 15));
+        // the wide character right before the limit makes room for it
+        assert_eq!(pushed(&["abc日", "x"], Some(5)), ("abc…".to_owned(), 14));
+        assert_eq!(pushed(&["日本語"], Some(5)), ("日本…".to_owned(), 15));
+        
+        // combining accents go along with the character they're on
+        assert_eq!(pushed(&["abcde\u{301}", "x"], Some(5)), ("abcd…".to_owned(), 15));
+    }
+    
+    #[test]
+    fn nothing_gets_written_into_no_columns() {
+        assert_eq!(pushed(&["abc", "日本"], Some(0)), (String::new(), 10));
+        assert_eq!(pushed(&["日本"], Some(1)), ("…".to_owned(), 11));
     }
 }